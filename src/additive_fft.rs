@@ -0,0 +1,260 @@
+// The additive FFT of Lin, Chung and Han ("Novel Polynomial Basis and Its Application to
+// Reed-Solomon Erasure Codes", 2014), which evaluates a polynomial of degree below 2^k at the 2^k
+// points of a coset of an additive subgroup of a binary field with O(k 2^k) multiplications. The
+// usual FFT needs a multiplicative subgroup of that size, which GF(2^n) rarely has.
+//
+// Points are symbols, so W_j, the symbols below 2^j, is the subspace spanned by v_i = 2^i for i < j
+// (see BinaryField). Its vanishing polynomial s_j(x), the product of x - w over w in W_j, is
+// GF(2)-linear, with s_0(x) = x and s_{j+1}(x) = s_j(x) * (s_j(x) + s_j(v_j)). Normalized as
+// ŝ_j(x) = s_j(x) / s_j(v_j), the products X_i(x) of ŝ_j(x) over the bits j of i are the "novel
+// polynomial basis". In it, a polynomial D(x) with 2^k terms is D_0(x) + ŝ_{k-1}(x) D_1(x), with
+// 2^(k-1) terms in each of D_0 and D_1, and ŝ_{k-1} is constant on each half of a coset of W_k, so
+// evaluating D on the coset is evaluating one polynomial of half the size on each half.
+//
+// Polynomials are given as regions (the same chunk of many stripes), like Matrix::mul_regions, so
+// that each step of the transforms is a mul_add_region over all of the stripes at once.
+
+use crate::field::{BinaryField, Gf, Symbol};
+
+// The constants of the novel polynomial basis for the subspaces W_j up to W_bits.
+pub struct NovelBasis<F: BinaryField> {
+    // s_j(v_j), which normalizes s_j.
+    normalizers: Vec<Gf<F>>,
+    // The derivative of ŝ_j, which is a constant because s_j is linear.
+    derivatives: Vec<F::Symbol>,
+}
+
+// Sets dst[i] += src[i].
+fn add_region<F: BinaryField>(src: &[F::Symbol], dst: &mut [F::Symbol]) {
+    assert_eq!(src.len(), dst.len());
+    for (s, d) in src.iter().zip(dst.iter_mut()) {
+        *d = F::add(*d, *s);
+    }
+}
+
+impl<F: BinaryField> NovelBasis<F> {
+    // The basis for polynomials of up to 2^bits terms.
+    pub fn new(bits: usize) -> Self {
+        assert!(bits < usize::BITS as usize && 1 << bits <= F::Symbol::ORDER);
+        let mut basis = NovelBasis {
+            normalizers: Vec::with_capacity(bits),
+            derivatives: Vec::with_capacity(bits),
+        };
+        // The only term of s_j with degree 1 is x times the product of s_i(v_i) for i < j, since
+        // s_{i+1}(x) = s_i(x)^2 + s_i(v_i) s_i(x) and squaring only gives even powers.
+        let mut slope = Gf::<F>::one();
+        for j in 0..bits {
+            let normalizer = basis.vanishing(j, 1 << j);
+            basis.normalizers.push(normalizer);
+            basis.derivatives.push((slope / normalizer).value());
+            slope *= normalizer;
+        }
+        return basis;
+    }
+
+    // s_j(x), for j up to the number of normalizers.
+    fn vanishing(&self, j: usize, x: usize) -> Gf<F> {
+        let mut s = Gf::<F>::new(F::Symbol::from_usize(x));
+        for normalizer in self.normalizers.iter().take(j) {
+            s = s * (s + *normalizer);
+        }
+        return s;
+    }
+
+    // ŝ_j(x).
+    fn normalized(&self, j: usize, x: usize) -> F::Symbol {
+        return (self.vanishing(j, x) / self.normalizers[j]).value();
+    }
+
+    // Checks that the regions are a polynomial (or its values) on the coset of W_k at offset.
+    fn check(&self, regions: &[Vec<F::Symbol>], offset: usize) {
+        let n = regions.len();
+        assert!(n.is_power_of_two() && n <= 1 << self.normalizers.len());
+        assert!(offset % n == 0 && offset + n <= F::Symbol::ORDER);
+    }
+
+    // Replaces the coefficients of a polynomial in the novel basis, regions[i] being those of
+    // X_i, with its values at the points offset + i. There must be a power of two regions, up to
+    // 2^bits, and offset must be a multiple of their number.
+    pub fn fft(&self, regions: &mut [Vec<F::Symbol>], offset: usize, field: &F) {
+        self.check(regions, offset);
+        self.fft_coset(regions, offset, field);
+    }
+
+    fn fft_coset(&self, regions: &mut [Vec<F::Symbol>], offset: usize, field: &F) {
+        if regions.len() == 1 {
+            return;
+        }
+        let half = regions.len() / 2;
+        // ŝ_j is c on offset + W_j and c + 1 on offset + v_j + W_j.
+        let c = self.normalized(half.trailing_zeros() as usize, offset);
+        let (low, high) = regions.split_at_mut(half);
+        for (l, h) in low.iter_mut().zip(high.iter_mut()) {
+            if c != F::zero() {
+                field.mul_add_region(c, h, l);
+            }
+            add_region::<F>(l, h);
+        }
+        self.fft_coset(low, offset, field);
+        self.fft_coset(high, offset + half, field);
+    }
+
+    // The inverse of fft: replaces the values of a polynomial at the points offset + i with its
+    // coefficients in the novel basis.
+    pub fn ifft(&self, regions: &mut [Vec<F::Symbol>], offset: usize, field: &F) {
+        self.check(regions, offset);
+        self.ifft_coset(regions, offset, field);
+    }
+
+    fn ifft_coset(&self, regions: &mut [Vec<F::Symbol>], offset: usize, field: &F) {
+        if regions.len() == 1 {
+            return;
+        }
+        let half = regions.len() / 2;
+        let c = self.normalized(half.trailing_zeros() as usize, offset);
+        let (low, high) = regions.split_at_mut(half);
+        self.ifft_coset(low, offset, field);
+        self.ifft_coset(high, offset + half, field);
+        for (l, h) in low.iter_mut().zip(high.iter_mut()) {
+            add_region::<F>(l, h);
+            if c != F::zero() {
+                field.mul_add_region(c, h, l);
+            }
+        }
+    }
+
+    // Replaces the coefficients of a polynomial in the novel basis with those of its formal
+    // derivative. The derivative of X_i is the sum, over the bits j of i, of X_(i - 2^j) times the
+    // derivative of ŝ_j.
+    pub fn formal_derivative(&self, regions: &mut [Vec<F::Symbol>], field: &F) {
+        self.check(regions, 0);
+        // Coefficients only move to lower ones, so going up, each is moved (and cleared) before any
+        // are added to it.
+        for i in 0..regions.len() {
+            let (lower, higher) = regions.split_at_mut(i);
+            let region = &mut higher[0];
+            for j in 0..self.derivatives.len() {
+                if i & (1 << j) != 0 {
+                    field.mul_add_region(self.derivatives[j], region, &mut lower[i ^ (1 << j)]);
+                }
+            }
+            for x in region.iter_mut() {
+                *x = F::zero();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    use super::*;
+    use crate::field::ExpLogField65536;
+    use crate::finite_field::DirectField;
+    use crate::polynomial::Polynomial;
+
+    // The polynomials X_i, for i below 2^bits, in the usual basis.
+    fn novel_basis<F: BinaryField>(bits: usize) -> Vec<Polynomial<F>> {
+        let basis = NovelBasis::<F>::new(bits);
+        let mut normalized = Vec::new();
+        let mut s = Polynomial::<F>::x();
+        for j in 0..bits {
+            normalized.push(s.scale(basis.normalizers[j].inv()));
+            s = &s * &(&s + &Polynomial::constant(basis.normalizers[j]));
+        }
+        return (0..1 << bits)
+            .map(|i| {
+                return (0..bits)
+                    .filter(|j| i & (1 << j) != 0)
+                    .fold(Polynomial::one(), |x, j| &x * &normalized[j]);
+            })
+            .collect();
+    }
+
+    fn random_regions<F: BinaryField>(n: usize, length: usize) -> Vec<Vec<F::Symbol>> {
+        return (0..n)
+            .map(|_| {
+                (0..length)
+                    .map(|_| F::Symbol::from_usize(rand::random::<usize>()))
+                    .collect()
+            })
+            .collect();
+    }
+
+    // The polynomial in the usual basis with the given coefficients in the novel basis.
+    fn from_novel<F: BinaryField>(
+        basis: &[Polynomial<F>],
+        coefficients: &[F::Symbol],
+    ) -> Polynomial<F> {
+        return basis
+            .iter()
+            .zip(coefficients)
+            .fold(Polynomial::zero(), |p, (x, c)| &p + &x.scale(Gf::new(*c)));
+    }
+
+    fn fft_evaluates_for<F: BinaryField>() {
+        let field = F::default();
+        let basis = NovelBasis::<F>::new(4);
+        let polynomials = novel_basis::<F>(4);
+        for n in &[1, 2, 8, 16] {
+            for offset in &[0, 16, 48] {
+                let coefficients = random_regions::<F>(*n, 1);
+                let mut values = coefficients.clone();
+                basis.fft(&mut values, *offset, &field);
+                let column: Vec<_> = coefficients.iter().map(|c| c[0]).collect();
+                let p = from_novel(&polynomials, &column);
+                for (i, value) in values.iter().enumerate() {
+                    assert_eq!(value[0], p.evaluate(F::Symbol::from_usize(offset + i)));
+                }
+                basis.ifft(&mut values, *offset, &field);
+                assert_eq!(values, coefficients);
+            }
+        }
+    }
+
+    #[test]
+    fn fft_evaluates() {
+        fft_evaluates_for::<DirectField>();
+        fft_evaluates_for::<ExpLogField65536>();
+    }
+
+    fn formal_derivative_for<F: BinaryField>() {
+        let field = F::default();
+        let basis = NovelBasis::<F>::new(5);
+        let polynomials = novel_basis::<F>(5);
+        let mut coefficients = random_regions::<F>(32, 3);
+        let p: Vec<_> = (0..3)
+            .map(|s| {
+                let column: Vec<_> = coefficients.iter().map(|c| c[s]).collect();
+                return from_novel(&polynomials, &column).derivative();
+            })
+            .collect();
+        basis.formal_derivative(&mut coefficients, &field);
+        for s in 0..3 {
+            let column: Vec<_> = coefficients.iter().map(|c| c[s]).collect();
+            assert_eq!(from_novel(&polynomials, &column), p[s]);
+        }
+    }
+
+    #[test]
+    fn formal_derivative() {
+        formal_derivative_for::<DirectField>();
+        formal_derivative_for::<ExpLogField65536>();
+    }
+
+    #[test]
+    fn regions_are_transformed_together() {
+        let field = DirectField::default();
+        let basis = NovelBasis::<DirectField>::new(3);
+        let regions = random_regions::<DirectField>(8, 5);
+        let mut values = regions.clone();
+        basis.fft(&mut values, 8, &field);
+        for s in 0..5 {
+            let mut column: Vec<_> = regions.iter().map(|r| vec![r[s]]).collect();
+            basis.fft(&mut column, 8, &field);
+            for i in 0..8 {
+                assert_eq!(column[i][0], values[i][s]);
+            }
+        }
+    }
+}
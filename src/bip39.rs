@@ -0,0 +1,188 @@
+// BIP-39 mnemonics.
+//
+// See https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki for the specification. A
+// mnemonic encodes 128 to 256 bits of entropy (in multiples of 32) followed by the first
+// entropy_bits / 32 bits of its SHA-256 hash as a checksum, 11 bits per word.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::include_bytes;
+
+const WORDLIST_RAW: &[u8] = include_bytes!("../assets/bip39_english.txt");
+
+// The number of bits encoded by each word.
+const RADIX_BITS: usize = 11;
+const MIN_ENTROPY_BYTES: usize = 16;
+const MAX_ENTROPY_BYTES: usize = 32;
+
+pub fn load_word_list() -> Vec<String> {
+    return String::from_utf8_lossy(WORDLIST_RAW)
+        .lines()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect();
+}
+
+fn checksum_bits(entropy: &[u8]) -> Vec<bool> {
+    let hash = Sha256::digest(entropy);
+    return (0..entropy.len() / 4)
+        .map(|i| (hash[i / 8] >> (7 - i % 8)) & 1 == 1)
+        .collect();
+}
+
+// Returns whether the given number of bytes can be encoded as a mnemonic.
+pub fn is_valid_entropy_length(length: usize) -> bool {
+    return length >= MIN_ENTROPY_BYTES && length <= MAX_ENTROPY_BYTES && length % 4 == 0;
+}
+
+// The length in bytes of the shortest entropy with at least the given number of bits.
+pub fn entropy_length_for_bits(bits: usize) -> Result<usize, String> {
+    if bits == 0 || bits > MAX_ENTROPY_BYTES * 8 {
+        return Err(format!(
+            "BIP-39 mnemonics can have at most {} bits of entropy.",
+            MAX_ENTROPY_BYTES * 8
+        ));
+    }
+    let length = std::cmp::max((bits + 31) / 32 * 4, MIN_ENTROPY_BYTES);
+    return Ok(length);
+}
+
+pub fn entropy_to_mnemonic(entropy: &[u8]) -> Result<String, String> {
+    if !is_valid_entropy_length(entropy.len()) {
+        return Err(format!(
+            "BIP-39 entropy must be 16 to 32 bytes in multiples of 4, got {} bytes.",
+            entropy.len()
+        ));
+    }
+    let wordlist = load_word_list();
+    let bits: Vec<bool> = entropy
+        .iter()
+        .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1 == 1))
+        .chain(checksum_bits(entropy))
+        .collect();
+    return Ok(bits
+        .chunks(RADIX_BITS)
+        .map(|word| word.iter().fold(0, |acc, b| (acc << 1) | *b as usize))
+        .map(|i| wordlist[i].as_str())
+        .collect::<Vec<&str>>()
+        .join(" "));
+}
+
+pub fn mnemonic_to_entropy(mnemonic: &str) -> Result<Vec<u8>, String> {
+    let wordlist = load_word_list();
+    let wordmap: HashMap<&str, usize> = wordlist
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (w.as_str(), i))
+        .collect();
+
+    let mut bits: Vec<bool> = Vec::new();
+    for word in mnemonic.split_whitespace() {
+        match wordmap.get(word.to_lowercase().as_str()) {
+            Some(index) => bits.extend((0..RADIX_BITS).rev().map(|i| (index >> i) & 1 == 1)),
+            None => return Err(format!("Invalid BIP-39 word {:?}.", word)),
+        }
+    }
+
+    // Each 32 bits of entropy adds one checksum bit, so the entropy is 32/33 of the total.
+    let entropy_bits = bits.len() * 32 / 33;
+    if bits.len() % 33 != 0 || !is_valid_entropy_length(entropy_bits / 8) {
+        return Err(String::from(
+            "BIP-39 mnemonics must have 12, 15, 18, 21 or 24 words.",
+        ));
+    }
+    let entropy: Vec<u8> = bits[..entropy_bits]
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, b| (acc << 1) | *b as u8))
+        .collect();
+    if checksum_bits(&entropy) != &bits[entropy_bits..] {
+        return Err(String::from("Invalid BIP-39 checksum."));
+    }
+    return Ok(entropy);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        return (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+    }
+
+    // Test vectors from the reference implementation (https://github.com/trezor/python-mnemonic).
+    const VECTORS: [(&str, &str); 6] = [
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        ),
+        (
+            "000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+        ),
+    ];
+
+    #[test]
+    fn entropy_to_mnemonic_vectors() {
+        for (entropy, mnemonic) in VECTORS.iter() {
+            assert_eq!(entropy_to_mnemonic(&from_hex(entropy)).unwrap(), *mnemonic);
+        }
+    }
+
+    #[test]
+    fn mnemonic_to_entropy_vectors() {
+        for (entropy, mnemonic) in VECTORS.iter() {
+            assert_eq!(mnemonic_to_entropy(mnemonic).unwrap(), from_hex(entropy));
+        }
+    }
+
+    #[test]
+    fn entropy_length_for_bits_rounds_up() {
+        assert_eq!(entropy_length_for_bits(100), Ok(16));
+        assert_eq!(entropy_length_for_bits(129), Ok(20));
+        assert_eq!(entropy_length_for_bits(256), Ok(32));
+        assert!(entropy_length_for_bits(257).is_err());
+        assert!(entropy_length_for_bits(0).is_err());
+    }
+
+    #[test]
+    fn invalid_checksum() {
+        assert!(mnemonic_to_entropy(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_word() {
+        assert!(mnemonic_to_entropy(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon shamir"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_length() {
+        assert!(mnemonic_to_entropy("zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo").is_err());
+        assert!(entropy_to_mnemonic(&[0; 15]).is_err());
+        assert!(entropy_to_mnemonic(&[0; 17]).is_err());
+    }
+}
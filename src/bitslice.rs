@@ -0,0 +1,189 @@
+// Bitsliced GF(2^8) arithmetic for encoding large amounts of data.
+//
+// Instead of storing 64 symbols as 64 bytes, a bitsliced block stores them as 8 u64 "planes",
+// where plane b holds bit b of every symbol. Multiplication by a constant c is linear over GF(2),
+// so it is an 8x8 bit matrix whose column j is c * x^j, and multiplying a block by it is just
+// XORing together the input planes selected by each row: 64 ANDs and XORs for 64 symbols, with no
+// table lookups (and so, like SecretField, no timing that depends on the data).
+//
+// Converting to and from planes is a bit matrix transpose, which is only done once per region, so
+// it pays off when each region is multiplied by many coefficients (i.e. by a whole matrix).
+
+use crate::finite_field::Field256;
+use crate::matrix::Matrix;
+
+// The number of symbols in a block.
+const BLOCK: usize = 64;
+
+type Block = [u64; 8];
+
+// Transposes an 8x8 bit matrix, where byte i of x is row i and bit j of a byte is column j.
+// See "Hacker's Delight", section 7-3.
+fn transpose8(x: u64) -> u64 {
+    let mut x = x;
+    let t = (x ^ (x >> 7)) & 0x00aa_00aa_00aa_00aa;
+    x = x ^ t ^ (t << 7);
+    let t = (x ^ (x >> 14)) & 0x0000_cccc_0000_cccc;
+    x = x ^ t ^ (t << 14);
+    let t = (x ^ (x >> 28)) & 0x0000_0000_f0f0_f0f0;
+    x = x ^ t ^ (t << 28);
+    return x;
+}
+
+// Converts a region into blocks, padding the last one with zeros.
+pub fn to_blocks(region: &[u8]) -> Vec<Block> {
+    let mut blocks = Vec::with_capacity((region.len() + BLOCK - 1) / BLOCK);
+    for symbols in region.chunks(BLOCK) {
+        let mut block: Block = [0; 8];
+        // Transpose 8 symbols at a time, which gives bit b of each of them in byte b.
+        for (g, group) in symbols.chunks(8).enumerate() {
+            let mut bytes = [0u8; 8];
+            bytes[..group.len()].copy_from_slice(group);
+            let t = transpose8(u64::from_le_bytes(bytes));
+            for b in 0..8 {
+                block[b] |= ((t >> (8 * b)) & 0xff) << (8 * g);
+            }
+        }
+        blocks.push(block);
+    }
+    return blocks;
+}
+
+// Converts blocks back into a region of the given length.
+pub fn from_blocks(blocks: &[Block], region: &mut [u8]) {
+    for (block, symbols) in blocks.iter().zip(region.chunks_mut(BLOCK)) {
+        for (g, group) in symbols.chunks_mut(8).enumerate() {
+            let mut t = 0;
+            for b in 0..8 {
+                t |= ((block[b] >> (8 * g)) & 0xff) << (8 * b);
+            }
+            let bytes = transpose8(t).to_le_bytes();
+            group.copy_from_slice(&bytes[..group.len()]);
+        }
+    }
+}
+
+// Multiplication by a constant as a bit matrix.
+pub struct BitMatrix {
+    // masks[j][i] is all ones if bit i of c * x^j is set, and zero otherwise.
+    masks: [[u64; 8]; 8],
+}
+
+impl BitMatrix {
+    pub fn new<F: Field256>(field: &F, c: u8) -> BitMatrix {
+        let mut masks = [[0; 8]; 8];
+        for j in 0..8 {
+            let column = field.mul(c, 1 << j);
+            for i in 0..8 {
+                masks[j][i] = (((column >> i) & 1) as u64).wrapping_neg();
+            }
+        }
+        return BitMatrix { masks: masks };
+    }
+
+    // Sets dst += c * src.
+    pub fn mul_add(&self, src: &Block, dst: &mut Block) {
+        for j in 0..8 {
+            for i in 0..8 {
+                dst[i] ^= src[j] & self.masks[j][i];
+            }
+        }
+    }
+}
+
+// The same as Matrix::mul_regions, but bitslicing the regions first.
+pub fn mul_regions<M: Field256, F: Field256>(
+    matrix: &Matrix<M>,
+    regions: &[Vec<u8>],
+    out: &mut [Vec<u8>],
+    field: &F,
+) {
+    assert!(matrix.mat.len() == out.len());
+    let inputs: Vec<Vec<Block>> = regions.iter().map(|region| to_blocks(region)).collect();
+    let blocks = inputs.first().map_or(0, |input| input.len());
+    let mut output: Vec<Block> = vec![[0; 8]; blocks];
+    for (row, region) in matrix.mat.iter().zip(out.iter_mut()) {
+        assert!(row.len() == inputs.len());
+        for block in output.iter_mut() {
+            *block = [0; 8];
+        }
+        for (c, input) in row.iter().zip(inputs.iter()) {
+            let bits = BitMatrix::new(field, c.value());
+            for (src, dst) in input.iter().zip(output.iter_mut()) {
+                bits.mul_add(src, dst);
+            }
+        }
+        from_blocks(&output, region);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    use super::*;
+    use crate::finite_field::DirectField;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn transpose8_is_transpose() {
+        let x: u64 = rand::random();
+        let t = transpose8(x);
+        for i in 0..8 {
+            for j in 0..8 {
+                assert_eq!((t >> (8 * i + j)) & 1, (x >> (8 * j + i)) & 1);
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_round_trip() {
+        for length in &[0, 1, 7, 8, 63, 64, 65, 200] {
+            let region: Vec<u8> = (0..*length).map(|_| rand::random::<u8>()).collect();
+            let blocks = to_blocks(&region);
+            assert_eq!(blocks.len(), (length + BLOCK - 1) / BLOCK);
+            // Plane b holds bit b of each symbol.
+            for (i, x) in region.iter().enumerate() {
+                for b in 0..8 {
+                    assert_eq!(
+                        (blocks[i / BLOCK][b] >> (i % BLOCK)) & 1,
+                        (*x as u64 >> b) & 1
+                    );
+                }
+            }
+            let mut out = vec![0; *length];
+            from_blocks(&blocks, &mut out);
+            assert_eq!(out, region);
+        }
+    }
+
+    #[test]
+    fn bit_matrix_agrees_with_mul() {
+        let direct = DirectField::default();
+        let src: Vec<u8> = (0..64).map(|_| rand::random::<u8>()).collect();
+        let dst: Vec<u8> = (0..64).map(|_| rand::random::<u8>()).collect();
+        for c in 0..=255 {
+            let mut block = to_blocks(&dst)[0];
+            BitMatrix::new(&direct, c).mul_add(&to_blocks(&src)[0], &mut block);
+            let mut out = vec![0; 64];
+            from_blocks(&[block], &mut out);
+            for i in 0..64 {
+                assert_eq!(out[i], dst[i] ^ direct.mul(c, src[i]));
+            }
+        }
+    }
+
+    #[test]
+    fn mul_regions_agrees_with_matrix() {
+        let direct = DirectField::default();
+        let m = Matrix::<DirectField>::try_from(&[&[1u8, 2u8, 3u8][..], &[4u8, 5u8, 6u8][..]][..])
+            .unwrap();
+        let regions: Vec<Vec<u8>> = (0..3)
+            .map(|_| (0..100).map(|_| rand::random::<u8>()).collect())
+            .collect();
+        let mut out = vec![vec![0xff; 100]; 2];
+        let mut expected = vec![vec![0; 100]; 2];
+        mul_regions(&m, &regions, &mut out, &direct);
+        m.mul_regions(&regions, &mut expected, &direct);
+        assert_eq!(out, expected);
+    }
+}
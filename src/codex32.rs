@@ -0,0 +1,576 @@
+// codex32 (BIP-93) shares.
+//
+// See https://github.com/bitcoin/bips/blob/master/bip-0093.mediawiki for the specification. A
+// codex32 string looks like "ms1" followed by a threshold digit, a four character identifier, a
+// share index, the payload and a 13 character checksum, all written with the bech32 alphabet.
+// Every character is an element of GF(32), so shares are split and combined character by
+// character using Lagrange interpolation over `Field32`, which is simple enough to be done by
+// hand with paper volvelles.
+//
+// Strings with the long (15 character) checksum, for 64 byte secrets, can be read, but split only
+// writes the short (13 character) one, which limits secrets to 46 bytes.
+
+use crate::finite_field::{ExpLogField32, Field32};
+use crate::secret::{Secret, ShareBytes};
+use crate::shamir::gen_random_bytes;
+use rand::{CryptoRng, RngCore};
+
+const CHARSET: &'static [u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const HRP: &'static str = "ms";
+const SEPARATOR: char = '1';
+
+// The share index used for the secret itself.
+const SECRET_INDEX: char = 's';
+// Share indices in the order they are handed out, which is alphabetical except for the secret.
+const SHARE_INDICES: &'static str = "acdefghjklmnpqrtuvwxyz023456789";
+
+// Threshold digit, identifier and share index.
+const HEADER_LENGTH: usize = 6;
+const CHECKSUM_LENGTH: usize = 13;
+// The longest data part (everything after the separator) that uses the short checksum.
+const MAX_DATA_LENGTH: usize = 93;
+const LONG_CHECKSUM_LENGTH: usize = 15;
+// The lengths of data parts with the long checksum.
+const MIN_LONG_DATA_LENGTH: usize = 122;
+const MAX_LONG_DATA_LENGTH: usize = 124;
+const MIN_SECRET_BYTES: usize = 16;
+const MAX_SECRET_BYTES: usize = (MAX_DATA_LENGTH - HEADER_LENGTH - CHECKSUM_LENGTH) * 5 / 8;
+
+// The checksum is a BCH code over GF(32). The residue of a valid string is this constant.
+const MS32_CONST: u128 = 0x10ce0795c2fd1e62a;
+const MS32_GEN: [u128; 5] = [
+    0x19dc500ce73fde210,
+    0x1bfae00def77fe529,
+    0x1fbd920fffe7bee52,
+    0x1739640bdeee3fdad,
+    0x07729a039cfc75f5a,
+];
+const MS32_LONG_CONST: u128 = 0x43381e570bf4798ab26;
+const MS32_LONG_GEN: [u128; 5] = [
+    0x3d59d273535ea62d897,
+    0x7a9becb6361c6c51507,
+    0x543f9b7e6c38d8a2a0e,
+    0x0c577eaeccf1990d13c,
+    0x1887f74f8dc71b10651,
+];
+
+// A parsed codex32 string.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Share {
+    // '0' for an unshared secret, otherwise '2' to '9'.
+    pub threshold: char,
+    pub identifier: String,
+    pub index: char,
+    // The payload as GF(32) elements, not including the checksum.
+    pub payload: ShareBytes,
+}
+
+// The length in bytes of the shortest secret with at least the given number of bits.
+pub fn secret_length_for_bits(bits: usize) -> Result<usize, String> {
+    if bits == 0 || bits > MAX_SECRET_BYTES * 8 {
+        return Err(format!(
+            "codex32 secrets can have at most {} bits of entropy.",
+            MAX_SECRET_BYTES * 8
+        ));
+    }
+    return Ok(std::cmp::max((bits + 7) / 8, MIN_SECRET_BYTES));
+}
+
+fn char_to_value(c: char) -> Result<u8, String> {
+    return match CHARSET.iter().position(|x| *x as char == c) {
+        Some(i) => Ok(i as u8),
+        None => Err(format!("Invalid codex32 character {:?}.", c)),
+    };
+}
+
+fn value_to_char(v: u8) -> char {
+    return CHARSET[v as usize] as char;
+}
+
+fn ms32_polymod(values: &[u8]) -> u128 {
+    let mut residue: u128 = 0x23181b3;
+    for v in values {
+        let b = residue >> 60;
+        residue = ((residue & 0x0fffffffffffffff) << 5) ^ *v as u128;
+        for i in 0..5 {
+            if (b >> i) & 1 == 1 {
+                residue ^= MS32_GEN[i];
+            }
+        }
+    }
+    return residue;
+}
+
+fn ms32_long_polymod(values: &[u8]) -> u128 {
+    let mut residue: u128 = 0x23181b3;
+    for v in values {
+        let b = residue >> 70;
+        residue = ((residue & 0x3fffffffffffffffff) << 5) ^ *v as u128;
+        for i in 0..5 {
+            if (b >> i) & 1 == 1 {
+                residue ^= MS32_LONG_GEN[i];
+            }
+        }
+    }
+    return residue;
+}
+
+// The length of the checksum of a data part of the given length, including the checksum.
+fn checksum_length(data_length: usize) -> usize {
+    if data_length > MAX_DATA_LENGTH {
+        return LONG_CHECKSUM_LENGTH;
+    }
+    return CHECKSUM_LENGTH;
+}
+
+fn ms32_verify_checksum(data: &[u8]) -> bool {
+    if checksum_length(data.len()) == LONG_CHECKSUM_LENGTH {
+        return ms32_long_polymod(data) == MS32_LONG_CONST;
+    }
+    return ms32_polymod(data) == MS32_CONST;
+}
+
+// The checksum of the data part without it, which is long if the short one wouldn't fit.
+fn ms32_create_checksum(data: &[u8]) -> Vec<u8> {
+    let length = checksum_length(data.len() + CHECKSUM_LENGTH);
+    let mut values = ShareBytes::zeroed(data.len() + length);
+    values.expose_mut()[..data.len()].copy_from_slice(data);
+    let polymod = if length == LONG_CHECKSUM_LENGTH {
+        ms32_long_polymod(values.expose()) ^ MS32_LONG_CONST
+    } else {
+        ms32_polymod(values.expose()) ^ MS32_CONST
+    };
+    return (0..length)
+        .map(|i| ((polymod >> (5 * (length - 1 - i))) & 31) as u8)
+        .collect();
+}
+
+// Converts bytes to 5-bit values, padding the last value with zero bits.
+fn bytes_to_values(bytes: &[u8]) -> Vec<u8> {
+    let mut values = Vec::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+    for byte in bytes {
+        acc = (acc << 8) | *byte as u32;
+        acc_bits += 8;
+        while acc_bits >= 5 {
+            acc_bits -= 5;
+            values.push(((acc >> acc_bits) & 31) as u8);
+        }
+    }
+    if acc_bits > 0 {
+        values.push(((acc << (5 - acc_bits)) & 31) as u8);
+    }
+    return values;
+}
+
+// Converts 5-bit values back to bytes, discarding the (at most 4) padding bits.
+fn values_to_bytes(values: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(values.len() * 5 / 8);
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+    for value in values {
+        acc = (acc << 5) | *value as u32;
+        acc_bits += 5;
+        if acc_bits >= 8 {
+            acc_bits -= 8;
+            bytes.push((acc >> acc_bits) as u8);
+        }
+    }
+    return bytes;
+}
+
+impl Share {
+    // The data part of the string (everything after the separator) as GF(32) elements, including
+    // the checksum.
+    fn data(&self) -> ShareBytes {
+        let mut data: Vec<u8> =
+            Vec::with_capacity(HEADER_LENGTH + self.payload.len() + LONG_CHECKSUM_LENGTH);
+        data.push(char_to_value(self.threshold).unwrap());
+        for c in self.identifier.chars() {
+            data.push(char_to_value(c).unwrap());
+        }
+        data.push(char_to_value(self.index).unwrap());
+        data.extend(self.payload.expose());
+        let checksum = ms32_create_checksum(&data);
+        data.extend(checksum);
+        return ShareBytes::new(data);
+    }
+
+    fn from_data(data: &[u8]) -> Share {
+        return Share {
+            threshold: value_to_char(data[0]),
+            identifier: data[1..5].iter().map(|v| value_to_char(*v)).collect(),
+            index: value_to_char(data[5]),
+            payload: ShareBytes::from_slice(&data[HEADER_LENGTH..]),
+        };
+    }
+
+    pub fn secret(&self) -> Secret {
+        return Secret::new(values_to_bytes(self.payload.expose()));
+    }
+
+    pub fn parse(s: &str) -> Result<Share, String> {
+        let lower = s.trim().to_lowercase();
+        if lower != s.trim() && s.trim().to_uppercase() != s.trim() {
+            return Err(String::from(
+                "codex32 strings must be all upper or all lower case.",
+            ));
+        }
+        let data_part = match lower.rfind(SEPARATOR) {
+            Some(i) if &lower[..i] == HRP => &lower[i + 1..],
+            _ => return Err(format!("codex32 strings must start with \"{}1\".", HRP)),
+        };
+        if data_part.len() > MAX_DATA_LENGTH
+            && (data_part.len() < MIN_LONG_DATA_LENGTH || data_part.len() > MAX_LONG_DATA_LENGTH)
+        {
+            return Err(String::from("Invalid codex32 length."));
+        }
+        if data_part.len() < HEADER_LENGTH + CHECKSUM_LENGTH + (MIN_SECRET_BYTES * 8 + 4) / 5 {
+            return Err(String::from("The codex32 string is too short."));
+        }
+
+        let mut data = ShareBytes::zeroed(data_part.len());
+        for (v, c) in data.expose_mut().iter_mut().zip(data_part.chars()) {
+            *v = char_to_value(c)?;
+        }
+        let data = data.expose();
+        if !ms32_verify_checksum(data) {
+            return Err(String::from("Invalid codex32 checksum."));
+        }
+        let checksum = checksum_length(data.len());
+        // The payload must not have more than 4 bits of padding.
+        if ((data.len() - HEADER_LENGTH - checksum) * 5) % 8 > 4 {
+            return Err(String::from("Invalid codex32 payload length."));
+        }
+
+        let share = Share::from_data(&data[..data.len() - checksum]);
+        if share.threshold == '1' || !share.threshold.is_ascii_digit() {
+            return Err(format!("Invalid codex32 threshold {:?}.", share.threshold));
+        }
+        if share.threshold == '0' && share.index != SECRET_INDEX {
+            return Err(String::from(
+                "A codex32 string with threshold 0 must have share index \"s\".",
+            ));
+        }
+        return Ok(share);
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut s = String::from(HRP);
+        s.push(SEPARATOR);
+        s.extend(self.data().expose().iter().map(|v| value_to_char(*v)));
+        return s;
+    }
+}
+
+// Evaluates the (character-wise) polynomials through the given shares at the given share index.
+// Because the checksum is linear, the result is a share with a valid checksum.
+pub fn interpolate_at(shares: &[Share], index: char) -> Result<Share, String> {
+    let field = ExpLogField32::default();
+    let target = char_to_value(index)?;
+    let indices: Vec<u8> = shares
+        .iter()
+        .map(|s| char_to_value(s.index))
+        .collect::<Result<_, _>>()?;
+
+    // Include the checksum so that the resulting share keeps a valid one.
+    let data: Vec<ShareBytes> = shares.iter().map(|s| s.data()).collect();
+
+    let mut result = ShareBytes::zeroed(data[0].len());
+    for (i, xi) in indices.iter().enumerate() {
+        // Computes the Lagrange basis polynomial for share i at the target index.
+        let mut weight = ExpLogField32::one();
+        for (j, xj) in indices.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let numerator = ExpLogField32::sub(target, *xj);
+            let denominator = ExpLogField32::sub(*xi, *xj);
+            weight = field.mul(weight, field.div(numerator, denominator));
+        }
+        for (r, y) in result.expose_mut().iter_mut().zip(data[i].expose()) {
+            *r = ExpLogField32::add(*r, field.mul(weight, *y));
+        }
+    }
+    return Ok(Share::from_data(
+        &result.expose()[..result.len() - checksum_length(result.len())],
+    ));
+}
+
+fn random_identifier<R: RngCore + CryptoRng>(rng: &mut R) -> String {
+    return gen_random_bytes(rng, 4)
+        .iter()
+        .map(|b| value_to_char(b & 31))
+        .collect();
+}
+
+// Splits a secret into codex32 shares. With a threshold of 0 the only string is the (unshared)
+// secret, otherwise `total` shares are returned and the secret share is not included.
+pub fn split<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    secret: &[u8],
+    threshold: usize,
+    total: usize,
+) -> Result<Vec<String>, String> {
+    if secret.len() < MIN_SECRET_BYTES || secret.len() > MAX_SECRET_BYTES {
+        return Err(format!(
+            "codex32 secrets must be between {} and {} bytes.",
+            MIN_SECRET_BYTES, MAX_SECRET_BYTES
+        ));
+    }
+    let identifier = random_identifier(rng);
+    let secret_share = Share {
+        threshold: std::char::from_digit(threshold as u32, 10).unwrap_or('?'),
+        identifier: identifier.clone(),
+        index: SECRET_INDEX,
+        payload: ShareBytes::new(bytes_to_values(secret)),
+    };
+    if threshold == 0 {
+        return Ok(vec![secret_share.to_string()]);
+    }
+    if threshold < 2 || threshold > 9 {
+        return Err(String::from(
+            "The codex32 threshold must be between 2 and 9.",
+        ));
+    }
+    if total < threshold || total > SHARE_INDICES.len() {
+        return Err(format!(
+            "The number of codex32 shares must be between the threshold and {}.",
+            SHARE_INDICES.len()
+        ));
+    }
+
+    // The first threshold - 1 shares are random, and together with the secret they determine all
+    // other shares.
+    let mut base: Vec<Share> = SHARE_INDICES
+        .chars()
+        .take(threshold - 1)
+        .map(|index| Share {
+            threshold: secret_share.threshold,
+            identifier: identifier.clone(),
+            index: index,
+            payload: ShareBytes::new(bytes_to_values(
+                ShareBytes::new(gen_random_bytes(rng, secret.len())).expose(),
+            )),
+        })
+        .collect();
+    base.push(secret_share);
+
+    let mut shares: Vec<String> = base[..threshold - 1]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    for index in SHARE_INDICES
+        .chars()
+        .skip(threshold - 1)
+        .take(total - (threshold - 1))
+    {
+        shares.push(interpolate_at(&base, index)?.to_string());
+    }
+    return Ok(shares);
+}
+
+// Recovers the secret share from a set of codex32 strings.
+pub fn combine<S: AsRef<str>>(strings: &[S]) -> Result<Share, String> {
+    let mut shares: Vec<Share> = Vec::with_capacity(strings.len());
+    for s in strings {
+        let share = Share::parse(s.as_ref())?;
+        if share.index == SECRET_INDEX {
+            return Ok(share);
+        }
+        if let Some(first) = shares.first() {
+            if share.threshold != first.threshold
+                || share.identifier != first.identifier
+                || share.payload.len() != first.payload.len()
+            {
+                return Err(String::from(
+                    "All codex32 shares must have the same threshold, identifier and length.",
+                ));
+            }
+        }
+        if shares.iter().any(|s| s.index == share.index) {
+            if shares.iter().any(|s| *s == share) {
+                continue;
+            }
+            return Err(format!(
+                "Conflicting codex32 shares with index {:?}.",
+                share.index
+            ));
+        }
+        shares.push(share);
+    }
+    if shares.len() == 0 {
+        return Err(String::from("No codex32 shares were given."));
+    }
+    let threshold = shares[0].threshold.to_digit(10).unwrap() as usize;
+    if shares.len() < threshold {
+        return Err(format!(
+            "{} codex32 shares are required, but only {} were given.",
+            threshold,
+            shares.len()
+        ));
+    }
+    return interpolate_at(&shares[..threshold], SECRET_INDEX);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn to_hex(bytes: &[u8]) -> String {
+        return bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    }
+
+    // Test vectors from BIP-93.
+
+    #[test]
+    fn vector_1_unshared_secret() {
+        let share = Share::parse("ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw").unwrap();
+        assert_eq!(share.threshold, '0');
+        assert_eq!(share.identifier, "test");
+        assert_eq!(share.index, 's');
+        assert_eq!(
+            to_hex(share.secret().expose()),
+            "318c6318c6318c6318c6318c6318c631"
+        );
+    }
+
+    #[test]
+    fn vector_2_two_of_n() {
+        let shares = [
+            "MS12NAMEA320ZYXWVUTSRQPNMLKJHGFEDCAXRPP870HKKQRM",
+            "MS12NAMECACDEFGHJKLMNPQRSTUVWXYZ023FTR2GDZMPY6PN",
+        ];
+        let secret = combine(&shares).unwrap();
+        assert_eq!(
+            secret.to_string(),
+            "ms12names6xqguzttxkeqnjsjzv4jv3nz5k3kwgsphuh6evw"
+        );
+        assert_eq!(
+            to_hex(secret.secret().expose()),
+            "d1808e096b35b209ca12132b264662a5"
+        );
+    }
+
+    #[test]
+    fn vector_3_three_of_five() {
+        let secret = "ms13cashsllhdmn9m42vcsamx24zrxgs3qqjzqud4m0d6nln";
+        let shares = [
+            "ms13casha320zyxwvutsrqpnmlkjhgfedca2a8d0zehn8a0t",
+            "ms13cashcacdefghjklmnpqrstuvwxyz023949xq35my48dr",
+        ];
+        let derived = [
+            "ms13cashd0wsedstcdcts64cd7wvy4m90lm28w4ffupqs7rm",
+            "ms13casheekgpemxzshcrmqhaydlp6yhms3ws7320xyxsar9",
+            "ms13cashf8jh6sdrkpyrsp5ut94pj8ktehhw2hfvyrj48704",
+        ];
+        assert_eq!(
+            to_hex(Share::parse(secret).unwrap().secret().expose()),
+            "ffeeddccbbaa99887766554433221100"
+        );
+        // The secret and the two shares determine the others.
+        let base: Vec<Share> = [secret, shares[0], shares[1]]
+            .iter()
+            .map(|s| Share::parse(s).unwrap())
+            .collect();
+        for (index, share) in "def".chars().zip(derived.iter()) {
+            assert_eq!(interpolate_at(&base, index).unwrap().to_string(), *share);
+        }
+        // Any three shares recover the secret.
+        let recovered = combine(&[derived[2], shares[0], derived[0]]).unwrap();
+        assert_eq!(recovered.to_string(), secret);
+        assert!(combine(&[derived[1], shares[1]]).is_err());
+    }
+
+    #[test]
+    fn vector_4_256_bits() {
+        let share = Share::parse(
+            "ms10leetsllhdmn9m42vcsamx24zrxgs3qrl7ahwvhw4fnzrhve25gvezzyqqtum9pgv99ycma",
+        )
+        .unwrap();
+        assert_eq!(
+            to_hex(share.secret().expose()),
+            "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100"
+        );
+    }
+
+    #[test]
+    fn vector_5_long_512_bits() {
+        let s = "MS100C8VSM32ZXFGUHPCHTLUPZRY9X8GF2TVDW0S3JN54KHCE6MUA7LQPZYGSFJD6AN074RXVCEMLH8WU3TK925ACDEFGHJKLMNPQRSTUVWXY06FHPV80UNDVARHRAK";
+        let share = Share::parse(s).unwrap();
+        assert_eq!(share.identifier, "0c8v");
+        assert_eq!(
+            to_hex(share.secret().expose()),
+            "dc5423251cb87175ff8110c8531d0952d8d73e1194e95b5f19d6f9df7c01111104c9baecdfea8cccc677fb9ddc8aec5553b86e528bcadfdcc201c17c638c47e9"
+        );
+        assert_eq!(share.to_string(), s.to_lowercase());
+        // Truncating it breaks the checksum.
+        assert!(Share::parse(&s[..s.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn secret_length_for_bits_rounds_up() {
+        assert_eq!(secret_length_for_bits(64), Ok(16));
+        assert_eq!(secret_length_for_bits(130), Ok(17));
+        assert_eq!(secret_length_for_bits(368), Ok(46));
+        assert!(secret_length_for_bits(369).is_err());
+        assert!(secret_length_for_bits(0).is_err());
+    }
+
+    #[test]
+    fn split_with_seeded_rng() {
+        let secret: Vec<u8> = (0..16).collect();
+        let split_seeded = || split(&mut ChaCha20Rng::seed_from_u64(7), &secret, 2, 3).unwrap();
+        assert_eq!(split_seeded(), split_seeded());
+        assert_ne!(split_seeded(), split(&mut OsRng, &secret, 2, 3).unwrap());
+    }
+
+    #[test]
+    fn invalid_checksum() {
+        assert!(Share::parse("ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlq").is_err());
+    }
+
+    #[test]
+    fn mixed_case() {
+        assert!(Share::parse("ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczLW").is_err());
+    }
+
+    #[test]
+    fn to_string_round_trip() {
+        let s = "ms10testsxxxxxxxxxxxxxxxxxxxxxxxxxx4nzvca9cmczlw";
+        assert_eq!(Share::parse(s).unwrap().to_string(), s);
+    }
+
+    #[test]
+    fn split_combine() {
+        let secret: Vec<u8> = (0..16).map(|i| i * 17).collect();
+        let shares = split(&mut OsRng, &secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        for s in shares.iter() {
+            assert!(Share::parse(s).is_ok());
+        }
+        let subset = [shares[4].clone(), shares[1].clone(), shares[2].clone()];
+        assert_eq!(combine(&subset).unwrap().secret().expose(), &secret[..]);
+        assert!(combine(&subset[..2]).is_err());
+    }
+
+    #[test]
+    fn split_unshared() {
+        let secret: Vec<u8> = (0..32).collect();
+        let shares = split(&mut OsRng, &secret, 0, 0).unwrap();
+        assert_eq!(shares.len(), 1);
+        assert_eq!(combine(&shares).unwrap().secret().expose(), &secret[..]);
+    }
+
+    #[test]
+    fn split_rejects_bad_parameters() {
+        let secret: Vec<u8> = (0..16).collect();
+        assert!(split(&mut OsRng, &secret, 1, 3).is_err());
+        assert!(split(&mut OsRng, &secret, 3, 2).is_err());
+        assert!(split(&mut OsRng, &secret[..15], 2, 3).is_err());
+    }
+}
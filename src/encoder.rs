@@ -1,30 +1,43 @@
+use crate::additive_fft::NovelBasis;
+use crate::bitslice;
 use crate::chunker::ChunkerExt;
 use crate::encoding::Encoding;
+use crate::field::{BinaryField, Field, Gf, Symbol};
 use crate::finite_field::Field256;
+use crate::lru::Lru;
 use crate::matrix::Matrix;
 use crate::matrix::{
-    cauchy_matrix, partial_cauchy_matrix, partial_vandermonde_matrix, vandermonde_matrix,
+    cauchy_matrix, cauchy_recovery, partial_cauchy_inverse, partial_vandermonde_matrix,
+    vandermonde_matrix, vandermonde_recovery,
 };
 use crate::polynomial::Polynomial;
-use std::cmp;
+use crate::secret::zeroize_vec;
+use crate::xor_schedule::{fewest_ones, XorSchedule};
+use std::convert::TryFrom;
+use std::fmt;
 use std::iter;
-
-// Reed-Solomon encoded data.
-#[derive(Debug, PartialEq)]
-pub struct RSStream {
-    // Length is used to discard padding bytes added to make the number of
-    // bytes (u8s) in codes a multiple of the encoding data chunks.
+use std::ops::Range;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+// Reed-Solomon encoded data, as symbols of the field it was encoded with (bytes for GF(2^8)). The
+// codes are shares of the data, so they are zeroized when the stream is dropped and left out of the
+// Debug output.
+#[derive(PartialEq)]
+pub struct RSStream<S: Symbol = u8> {
+    // Length is used to discard padding symbols added to make the number of
+    // symbols in codes a multiple of the encoding data chunks.
     pub length: usize,
     // How the reed-solomon data has been encoded.
     pub encoding: Encoding,
     // codes has list per chunk with the values being those within the chunk.
-    pub codes: Vec<Vec<u8>>,
+    pub codes: Vec<Vec<S>>,
     // True for [i] if there was NOT an erasure in codes[*][i]. Can be empty if there is no erasure
     // data.
     pub valid: Vec<bool>,
 }
 
-impl RSStream {
+impl<S: Symbol> RSStream<S> {
     pub fn empty(encoding: Encoding) -> Self {
         RSStream {
             // The logical length of the resulting data
@@ -36,55 +49,116 @@ impl RSStream {
     }
 }
 
-pub trait RSEncoder {
-    fn encode_bytes<F: Field256>(
+impl<S: Symbol> fmt::Debug for RSStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f
+            .debug_struct("RSStream")
+            .field("length", &self.length)
+            .field("encoding", &self.encoding)
+            .field("codes", &format!("[REDACTED; {}]", self.codes.len()))
+            .field("valid", &self.valid)
+            .finish();
+    }
+}
+
+impl<S: Symbol> Drop for RSStream<S> {
+    fn drop(&mut self) {
+        for stripe in self.codes.iter_mut() {
+            zeroize_vec(stripe);
+        }
+    }
+}
+
+// An encoder for the field F. The "bytes" are symbols of F, so for GF(2^16) fields each is a u16.
+pub trait RSEncoder<F: Field> {
+    fn encode_bytes(
         &self,
         encoding: Encoding,
         field: &F,
-        bytes: &[u8],
-    ) -> Result<RSStream, String>;
-    fn decode_bytes<F: Field256>(&self, stream: &RSStream, field: &F) -> Result<Vec<u8>, String>;
+        bytes: &[F::Symbol],
+    ) -> Result<RSStream<F::Symbol>, String>;
+    fn decode_bytes(
+        &self,
+        stream: &RSStream<F::Symbol>,
+        field: &F,
+    ) -> Result<Vec<F::Symbol>, String>;
 }
 
-// Encoder using lagrangian interpolation to construct Polynomials given a set of points. Slow.
+// Every chunk needs its own evaluation point, so a field with n elements can encode at most n
+// chunks.
+fn check_encoding<S: Symbol>(encoding: Encoding) -> Result<(), String> {
+    if encoding.total_chunks() as usize > S::ORDER {
+        return Err(format!(
+            "{} chunks need a field with more than {} elements",
+            encoding.total_chunks(),
+            S::ORDER
+        ));
+    }
+    return Ok(());
+}
+
+// Encoder using lagrangian interpolation through the points of each stripe. The x-values are the
+// same for every stripe, so the Lagrange weights for each missing x-value are computed once per
+// erasure pattern and each stripe then only takes a weighted sum per missing chunk.
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub struct LagrangeInterpolationEncoder;
 
-impl RSEncoder for LagrangeInterpolationEncoder {
+// For each target, the weights of the values at xs in the value at the target, of the polynomial
+// through those points.
+fn lagrange_weights<F: Field>(
+    xs: &[usize],
+    targets: &[usize],
+) -> Result<Vec<Vec<F::Symbol>>, String> {
+    let xs: Vec<_> = xs.iter().map(|x| F::Symbol::from_usize(*x)).collect();
+    return targets
+        .iter()
+        .map(|target| {
+            let weights = Polynomial::<F>::lagrange_weights(&xs, F::Symbol::from_usize(*target))?;
+            return Ok(weights.iter().map(|w| w.value()).collect());
+        })
+        .collect();
+}
+
+// The sum of weights[i] * ys[i].
+fn weighted_sum<F: Field>(field: &F, weights: &[F::Symbol], ys: &[F::Symbol]) -> F::Symbol {
+    return weights
+        .iter()
+        .zip(ys)
+        .fold(F::zero(), |sum, (w, y)| F::add(sum, field.mul(*w, *y)));
+}
+
+impl<F: Field> RSEncoder<F> for LagrangeInterpolationEncoder {
     // Encode a stream of bytes as a list of 8 byte data chunks along with their code chunks.
-    fn encode_bytes<F: Field256>(
+    fn encode_bytes(
         &self,
         encoding: Encoding,
         field: &F,
-        bytes: &[u8],
-    ) -> Result<RSStream, String> {
+        bytes: &[F::Symbol],
+    ) -> Result<RSStream<F::Symbol>, String> {
+        check_encoding::<F::Symbol>(encoding)?;
         if bytes.len() == 0 {
             return Ok(RSStream::empty(encoding));
         }
 
-        // The number of chunks.
-        let iterations = bytes.len() / encoding.data_chunks as usize;
+        // The polynomial for each stripe has P(i) == stripe[i] for i from 0..encoding.data_chunks,
+        // and the code chunks are its values after those.
+        let data_chunks = encoding.data_chunks as usize;
+        let xs: Vec<usize> = (0..data_chunks).collect();
+        let code_xs: Vec<usize> = (data_chunks..encoding.total_chunks() as usize).collect();
+        let weights = lagrange_weights::<F>(&xs, &code_xs)?;
 
-        // Generate our interpolated polynomial where P(i) for i from 0..encoding.data_chunks ==
-        // input[i * k] (where k is the iteration of bytes we are encoding).
-        let mut output: Vec<Vec<u8>> = Vec::with_capacity(iterations);
-        for (i, chunk) in bytes
+        let mut output: Vec<Vec<F::Symbol>> = Vec::with_capacity(bytes.len() / data_chunks + 1);
+        for chunk in bytes
             .iter()
             .cloned()
-            .chunked_with_default(encoding.data_chunks as usize, 0)
-            .enumerate()
+            .chunked_with_default(data_chunks, F::zero())
         {
-            let p = Polynomial::interpolate(&chunk[..], field);
-            output.push(Vec::with_capacity(encoding.total_chunks() as usize));
-
-            for b in 0..encoding.total_chunks() {
-                // Only evaluate the polynomial for code chunks.
-                if b < encoding.data_chunks {
-                    output[i].push(chunk[b as usize]);
-                } else {
-                    output[i].push(p.evaluate(b, field));
-                }
+            let mut stripe = Vec::with_capacity(encoding.total_chunks() as usize);
+            stripe.extend_from_slice(&chunk);
+            for w in weights.iter() {
+                stripe.push(weighted_sum(field, w, &chunk));
             }
+            output.push(stripe);
         }
 
         return Ok(RSStream {
@@ -95,13 +169,18 @@ impl RSEncoder for LagrangeInterpolationEncoder {
         });
     }
 
-    fn decode_bytes<F: Field256>(&self, stream: &RSStream, field: &F) -> Result<Vec<u8>, String> {
+    fn decode_bytes(
+        &self,
+        stream: &RSStream<F::Symbol>,
+        field: &F,
+    ) -> Result<Vec<F::Symbol>, String> {
         let RSStream {
             length,
             encoding,
             codes,
             valid,
         } = stream;
+        check_encoding::<F::Symbol>(*encoding)?;
         if *length == 0 {
             return Ok(Vec::new());
         }
@@ -138,161 +217,530 @@ impl RSEncoder for LagrangeInterpolationEncoder {
             .take(encoding.data_chunks as usize)
             .collect();
 
-        // Now, for each input row, interpolate the polynomial and then generate our data
-        // points.
+        // The weights of the valid chunks in each erased data chunk. Valid data chunks are always
+        // among the valid indices, as they come first, and are copied as they are.
+        let erased: Vec<usize> = (0..encoding.data_chunks as usize)
+            .filter(|col| !valid[*col])
+            .collect();
+        let weights = lagrange_weights::<F>(&valid_indices, &erased)?;
+
+        // Now, for each input row, generate the erased data points from the valid ones.
         let rows = (length + encoding.data_chunks as usize - 1) / encoding.data_chunks as usize;
+        let mut ys = Vec::with_capacity(valid_indices.len());
         for row in 0..rows {
-            let row = row as usize;
-            let points: Vec<_> = valid_indices
-                .iter()
-                .map(|col| (*col as u8, codes[row][*col as usize]))
-                .collect();
-            let p = Polynomial::interpolate_points(&points[..], field);
-            for col in 0..encoding.data_chunks {
-                let i = row * encoding.data_chunks as usize + col as usize;
+            ys.clear();
+            ys.extend(valid_indices.iter().map(|col| codes[row][*col]));
+            let mut erasures = erased.iter().zip(weights.iter()).peekable();
+            for col in 0..encoding.data_chunks as usize {
+                let i = row * encoding.data_chunks as usize + col;
                 // The length can be less than a multiple of encoding.data_chunks (but we zero pad
                 // so that the underlying data "exists", it's just not meaningful). Thus, break
                 // early if we get to the end.
                 if i >= *length {
                     break;
                 }
-                res.insert(i, p.evaluate(col as u8, field));
+                match erasures.peek() {
+                    Some((erased_col, w)) if **erased_col == col => {
+                        res.push(weighted_sum(field, w, &ys));
+                        erasures.next();
+                    }
+                    _ => res.push(codes[row][col]),
+                }
             }
         }
+        zeroize_vec(&mut ys);
 
         return Ok(res);
     }
 }
 
-fn encode_bytes_matrix<F: Field256>(
-    encoding: Encoding,
-    generator: &Matrix,
-    field: &F,
-    bytes: &[u8],
-) -> Result<RSStream, String> {
-    // The number of chunks.
-    let iterations = bytes.len() / encoding.data_chunks as usize;
-
-    // Generate data one "chunk" at a time (i.e. the data symbols and the code symbols).
-    let mut output: Vec<Vec<u8>> = Vec::with_capacity(iterations);
-    let mut buffer: Vec<u8> = iter::repeat(0)
-        .take(encoding.code_chunks as usize)
-        .collect();
-    for (i, chunk) in bytes
-        .iter()
-        .cloned()
-        .chunked_with_default(encoding.data_chunks as usize, 0)
-        .enumerate()
-    {
-        generator.mul_vec(&chunk, &mut buffer, field);
-
-        output.push(Vec::with_capacity(encoding.total_chunks() as usize));
-        output[i].extend(chunk.iter().take(encoding.data_chunks as usize));
-        output[i].extend(buffer.iter().take(encoding.code_chunks as usize));
+// Splits the stripes 0..stripes into `threads` contiguous ranges, runs work on each range on its
+// own thread and concatenates the results in order, so they don't depend on the number of threads.
+fn in_parallel<T: Send, W: Fn(Range<usize>) -> Vec<T> + Sync>(
+    stripes: usize,
+    threads: usize,
+    work: W,
+) -> Vec<T> {
+    let threads = threads.max(1).min(stripes);
+    if threads <= 1 {
+        return work(0..stripes);
     }
+    let per_thread = (stripes + threads - 1) / threads;
+    return thread::scope(|scope| {
+        let work = &work;
+        let handles: Vec<_> = (0..stripes)
+            .step_by(per_thread)
+            .map(|start| scope.spawn(move || work(start..(start + per_thread).min(stripes))))
+            .collect();
+        return handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("An encoding thread panicked"))
+            .collect();
+    });
+}
 
+// Splits the bytes into stripes of encoding.data_chunks bytes and multiplies them by the
+// generator. The chunks are first gathered into regions (chunk i of every stripe), so that each
+// coefficient of the generator multiplies a whole region at once with mul_regions (e.g.
+// Matrix::mul_regions). The stripes are split across `threads` threads, each with its own regions.
+fn encode_bytes_matrix<
+    M: Field,
+    R: Fn(&Matrix<M>, &[Vec<M::Symbol>], &mut [Vec<M::Symbol>]) + Sync,
+>(
+    encoding: Encoding,
+    generator: &Matrix<M>,
+    bytes: &[M::Symbol],
+    threads: usize,
+    mul_regions: R,
+) -> Result<RSStream<M::Symbol>, String> {
+    let data_chunks = encoding.data_chunks as usize;
+    let stripes = (bytes.len() + data_chunks - 1) / data_chunks;
+    let codes = in_parallel(stripes, threads, |range| {
+        let end = (range.end * data_chunks).min(bytes.len());
+        let mut data = to_regions(&bytes[range.start * data_chunks..end], data_chunks);
+        let mut code: Vec<Vec<M::Symbol>> = iter::repeat(vec![M::zero(); range.len()])
+            .take(encoding.code_chunks as usize)
+            .collect();
+        mul_regions(generator, &data, &mut code);
+        return to_stripes(&mut data, &mut code);
+    });
     return Ok(RSStream {
         length: bytes.len(),
         encoding: encoding,
-        codes: output,
+        codes: codes,
         valid: Vec::new(),
     });
 }
 
-fn decode_bytes_matrix<F: Field256>(
-    stream: &RSStream,
-    generator: &Matrix,
+// Gathers chunk i of every stripe of data_chunks bytes into region i. The length may not be a
+// multiple of data_chunks, in which case the last stripe is padded with zeros.
+fn to_regions<S: Symbol>(bytes: &[S], data_chunks: usize) -> Vec<Vec<S>> {
+    let stripes = (bytes.len() + data_chunks - 1) / data_chunks;
+    let mut data: Vec<Vec<S>> = iter::repeat(vec![S::default(); stripes])
+        .take(data_chunks)
+        .collect();
+    for (i, b) in bytes.iter().enumerate() {
+        data[i % data_chunks][i / data_chunks] = *b;
+    }
+    return data;
+}
+
+// The stripes made of the data regions followed by the code regions, which are zeroized, since the
+// data may be secret and the code chunks are shares of it.
+fn to_stripes<S: Symbol>(data: &mut [Vec<S>], code: &mut [Vec<S>]) -> Vec<Vec<S>> {
+    let stripes = data[0].len();
+    let output: Vec<Vec<S>> = (0..stripes)
+        .map(|i| {
+            data.iter()
+                .chain(code.iter())
+                .map(|region| region[i])
+                .collect()
+        })
+        .collect();
+
+    for region in data.iter_mut().chain(code.iter_mut()) {
+        zeroize_vec(region);
+    }
+    return output;
+}
+
+// The stream of the stripes from to_stripes.
+fn to_stream<S: Symbol>(
+    encoding: Encoding,
+    length: usize,
+    data: &mut [Vec<S>],
+    code: &mut [Vec<S>],
+) -> RSStream<S> {
+    return RSStream {
+        length: length,
+        encoding: encoding,
+        codes: to_stripes(data, code),
+        valid: Vec::new(),
+    };
+}
+
+// Multiplies the valid chunks of each stripe by the generator to get back the data chunks, a
+// region at a time as in encode_bytes_matrix, and likewise split across threads.
+fn decode_bytes_matrix<
+    M: Field,
+    R: Fn(&Matrix<M>, &[Vec<M::Symbol>], &mut [Vec<M::Symbol>]) + Sync,
+>(
+    stream: &RSStream<M::Symbol>,
+    generator: &Matrix<M>,
     valid_indices: &[usize],
-    field: &F,
-) -> Result<Vec<u8>, String> {
+    threads: usize,
+    mul_regions: R,
+) -> Result<Vec<M::Symbol>, String> {
     let RSStream {
         length,
         encoding,
         codes,
         valid: _,
     } = stream;
-    let mut res = Vec::with_capacity(*length);
+    let data_chunks = encoding.data_chunks as usize;
+    // The length may not be a multiple of data_chunks, so this is the ceiling of length /
+    // data_chunks.
+    let stripes = (length + data_chunks - 1) / data_chunks;
 
-    // Slow path with erasures.
-    let mut chunk: Vec<u8> = iter::repeat(0)
-        .take(encoding.data_chunks as usize)
-        .collect();
+    return Ok(in_parallel(stripes, threads, |range| {
+        let mut valid_regions: Vec<Vec<M::Symbol>> = valid_indices
+            .iter()
+            .map(|j| {
+                codes[range.clone()]
+                    .iter()
+                    .map(|stripe| stripe[*j])
+                    .collect()
+            })
+            .collect();
+        let mut data: Vec<Vec<M::Symbol>> = iter::repeat(vec![M::zero(); range.len()])
+            .take(data_chunks)
+            .collect();
+        mul_regions(generator, &valid_regions, &mut data);
+
+        // Only take as many as we need, since the last stripe may be padded.
+        let start = range.start * data_chunks;
+        let res: Vec<M::Symbol> = (start..(range.end * data_chunks).min(*length))
+            .map(|i| data[i % data_chunks][i / data_chunks - range.start])
+            .collect();
 
-    let mut buffer: Vec<u8> = iter::repeat(0)
+        for region in valid_regions.iter_mut().chain(data.iter_mut()) {
+            zeroize_vec(region);
+        }
+        return res;
+    }));
+}
+
+// The indices of the first data_chunks valid chunks, which are the ones decoded from.
+fn valid_indices(encoding: Encoding, valid: &[bool]) -> Vec<usize> {
+    return valid
+        .iter()
+        .cloned()
+        .enumerate()
+        .filter(|(_, valid)| *valid)
+        .map(|(i, _)| i)
         .take(encoding.data_chunks as usize)
         .collect();
+}
+
+// The generator matrix which gives the code chunks of a Vandermonde code from its data chunks,
+// computed in the field M.
+fn vandermonde_generator<M: Field>(encoding: Encoding) -> Result<Matrix<M>, String> {
+    let data_chunks = encoding.data_chunks as usize;
+    let inverted = vandermonde_matrix::<M>(0, data_chunks, data_chunks)?.invert_vandermonde()?;
+    let data: Vec<usize> = (0..data_chunks).collect();
+    return Ok(vandermonde_recovery(
+        data_chunks..encoding.total_chunks() as usize,
+        &data,
+        &inverted,
+    )?);
+}
+
+// The matrix which gives the data chunks of a Vandermonde code from its first data_chunks valid
+// chunks, computed in the field M. The valid data chunks are among those, so only the rows of the
+// erased ones are computed.
+fn vandermonde_decoder<M: Field>(encoding: Encoding, valid: &[bool]) -> Result<Matrix<M>, String> {
+    let data_chunks = encoding.data_chunks as usize;
+    let inverted = partial_vandermonde_matrix::<M, _>(valid.iter().cloned(), data_chunks)?
+        .invert_vandermonde()?;
+    return Ok(vandermonde_recovery(
+        0..data_chunks,
+        &valid_indices(encoding, valid),
+        &inverted,
+    )?);
+}
+
+// Encodes with the generator from vandermonde_generator.
+fn encode_bytes_vandermonde<
+    M: Field,
+    R: Fn(&Matrix<M>, &[Vec<M::Symbol>], &mut [Vec<M::Symbol>]) + Sync,
+>(
+    encoding: Encoding,
+    bytes: &[M::Symbol],
+    threads: usize,
+    mul_regions: R,
+) -> Result<RSStream<M::Symbol>, String> {
+    check_encoding::<M::Symbol>(encoding)?;
+    if bytes.len() == 0 {
+        return Ok(RSStream::empty(encoding));
+    }
+    let generator = vandermonde_generator::<M>(encoding)?;
+    return encode_bytes_matrix(encoding, &generator, bytes, threads, mul_regions);
+}
+
+// Decodes a Vandermonde code, with the decoding matrix (which only depends on the encoding and
+// which chunks are valid) from decoder, e.g. vandermonde_decoder.
+fn decode_bytes_vandermonde<
+    M: Field,
+    R: Fn(&Matrix<M>, &[Vec<M::Symbol>], &mut [Vec<M::Symbol>]) + Sync,
+    D: FnOnce(Encoding, &[bool]) -> Result<Arc<Matrix<M>>, String>,
+>(
+    stream: &RSStream<M::Symbol>,
+    threads: usize,
+    mul_regions: R,
+    decoder: D,
+) -> Result<Vec<M::Symbol>, String> {
+    let RSStream {
+        length,
+        encoding,
+        codes,
+        valid,
+    } = stream;
+    check_encoding::<M::Symbol>(*encoding)?;
+    if *length == 0 {
+        return Ok(Vec::new());
+    }
+    let valid_indices = valid_indices(*encoding, valid);
+
+    if valid_indices.len() < encoding.data_chunks as usize {
+        return Err(String::from("Too many erasures to recover"));
+    }
 
-    // Loop through each chunk and decode it. The length may not be a multiple of
-    // encoding.data_chunks, so this is equivalent to taking the ceiling of length /
-    // encoding.data_chunks.
-    let rows = (length + encoding.data_chunks as usize - 1) / encoding.data_chunks as usize;
-    for i in 0..rows {
-        for (e, j) in valid_indices.iter().cloned().enumerate() {
-            chunk[e] = codes[i as usize][j as usize];
+    // Fast path with no erasures
+    if valid
+        .iter()
+        .cloned()
+        .take(encoding.data_chunks as usize)
+        .all(|x| x)
+    {
+        let mut res = Vec::with_capacity(*length);
+        for i in 0..*length {
+            let row = i / encoding.data_chunks as usize;
+            let col = i % encoding.data_chunks as usize;
+            res.push(codes[row][col]);
         }
-        generator.mul_vec(&chunk, &mut buffer, field);
-        // Only take as many as we need until res.len() == length.
-        let remaining = cmp::min(length - res.len(), encoding.data_chunks as usize);
-        res.extend(buffer.iter().take(remaining));
+        return Ok(res);
     }
 
-    return Ok(res);
+    let generator = decoder(*encoding, valid)?;
+    return decode_bytes_matrix(stream, &generator, &valid_indices[..], threads, mul_regions);
 }
+
+// The matrix encoders can split the stripes of a stream across threads (see in_parallel), which
+// never changes their output. By default, with 0 threads as with 1, they only use the calling
+// thread. Library users choose the number with with_threads. The CLI doesn't: each round of the
+// word shards in shamir.rs is a single stripe, so threads could not speed them up.
+
 // Encoder using Vandermonde matrices to do polynomial interpolation.
 #[derive(Debug, Clone, Default)]
-pub struct VandermondeEncoder {}
+pub struct VandermondeEncoder {
+    threads: usize,
+}
 
 // Encoder using Cauchy matrices to do polynomial interpolation.
 #[derive(Debug, Clone, Default)]
-pub struct CauchyEncoder {}
+pub struct CauchyEncoder {
+    threads: usize,
+}
 
-impl RSEncoder for VandermondeEncoder {
-    fn encode_bytes<F: Field256>(
+// Encoder using the same Vandermonde matrices as VandermondeEncoder (so the codes are the same),
+// but multiplying bitsliced data (see bitslice.rs), which is faster for large amounts of data and
+// doesn't use tables. Bitslicing only pays off over whole regions, and the CLI's shards are split
+// a byte per stripe, so the CLI has no use for it: only the tests and benches construct it.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct BitslicedEncoder {
+    threads: usize,
+}
+
+impl CauchyEncoder {
+    pub fn with_threads(self, threads: usize) -> Self {
+        return CauchyEncoder { threads: threads };
+    }
+}
+
+#[allow(dead_code)]
+impl BitslicedEncoder {
+    pub fn with_threads(self, threads: usize) -> Self {
+        return BitslicedEncoder { threads: threads };
+    }
+}
+
+impl VandermondeEncoder {
+    pub fn with_threads(self, threads: usize) -> Self {
+        return VandermondeEncoder { threads: threads };
+    }
+
+    // Like encode_bytes, but the generator matrix is computed in the field M (the same field as F,
+    // but possibly a different implementation of it). The matrix only depends on the
+    // encoding, which is public, so a fast table based field can be used for it even when the data
+    // is secret and must be multiplied with a constant time field.
+    pub fn encode_bytes_with<M: Field, F: Field<Symbol = M::Symbol>>(
         &self,
         encoding: Encoding,
         field: &F,
-        bytes: &[u8],
-    ) -> Result<RSStream, String> {
+        bytes: &[M::Symbol],
+    ) -> Result<RSStream<M::Symbol>, String> {
+        return encode_bytes_vandermonde::<M, _>(encoding, bytes, self.threads, |m, r, o| {
+            m.mul_regions(r, o, field)
+        });
+    }
+
+    // Like decode_bytes, but the decoding matrix is computed in the field M.
+    pub fn decode_bytes_with<M: Field, F: Field<Symbol = M::Symbol>>(
+        &self,
+        stream: &RSStream<M::Symbol>,
+        field: &F,
+    ) -> Result<Vec<M::Symbol>, String> {
+        return decode_bytes_vandermonde::<M, _, _>(
+            stream,
+            self.threads,
+            |m, r, o| m.mul_regions(r, o, field),
+            |encoding, valid| Ok(Arc::new(vandermonde_decoder(encoding, valid)?)),
+        );
+    }
+}
+
+impl<F: Field> RSEncoder<F> for VandermondeEncoder {
+    fn encode_bytes(
+        &self,
+        encoding: Encoding,
+        field: &F,
+        bytes: &[F::Symbol],
+    ) -> Result<RSStream<F::Symbol>, String> {
+        return self.encode_bytes_with::<F, F>(encoding, field, bytes);
+    }
+
+    fn decode_bytes(
+        &self,
+        stream: &RSStream<F::Symbol>,
+        field: &F,
+    ) -> Result<Vec<F::Symbol>, String> {
+        return self.decode_bytes_with::<F, F>(stream, field);
+    }
+}
+
+// A VandermondeEncoder for one encoding which keeps its generator, and the decoding matrices of the
+// erasure patterns it most recently decoded, keyed by the valid bitmap. Decoding many streams with
+// the same chunks missing then only inverts a matrix once. As with encode_bytes_with, the matrices
+// are computed in the field M and the data multiplied in the encoder's field.
+pub struct CachedVandermondeEncoder<M: Field> {
+    encoding: Encoding,
+    generator: Matrix<M>,
+    decoders: Mutex<Lru<Vec<bool>, Arc<Matrix<M>>>>,
+    threads: usize,
+}
+
+impl<M: Field> CachedVandermondeEncoder<M> {
+    // Caches the decoding matrices of up to `capacity` erasure patterns.
+    pub fn new(encoding: Encoding, capacity: usize) -> Result<Self, String> {
+        check_encoding::<M::Symbol>(encoding)?;
+        return Ok(CachedVandermondeEncoder {
+            encoding: encoding,
+            generator: vandermonde_generator::<M>(encoding)?,
+            decoders: Mutex::new(Lru::new(capacity)),
+            threads: 0,
+        });
+    }
+
+    pub fn with_threads(self, threads: usize) -> Self {
+        return CachedVandermondeEncoder {
+            threads: threads,
+            ..self
+        };
+    }
+
+    fn check(&self, encoding: Encoding) -> Result<(), String> {
+        if encoding != self.encoding {
+            return Err(format!(
+                "The encoder is for {:?}, but got {:?}",
+                self.encoding, encoding
+            ));
+        }
+        return Ok(());
+    }
+
+    // The decoding matrix for the valid bitmap, from the cache if it is there. The cache isn't
+    // locked while inverting, so other patterns can be looked up meanwhile (and two threads missing
+    // the same pattern may both invert it).
+    fn decoder(&self, valid: &[bool]) -> Result<Arc<Matrix<M>>, String> {
+        let key = valid.to_vec();
+        if let Some(decoder) = self.lock_decoders().get(&key) {
+            return Ok(decoder.clone());
+        }
+        let decoder = Arc::new(vandermonde_decoder::<M>(self.encoding, valid)?);
+        self.lock_decoders().insert(key, decoder.clone());
+        return Ok(decoder);
+    }
+
+    fn lock_decoders(&self) -> MutexGuard<Lru<Vec<bool>, Arc<Matrix<M>>>> {
+        return self.decoders.lock().expect("The decoder cache is poisoned");
+    }
+}
+
+impl<M: Field, F: Field<Symbol = M::Symbol>> RSEncoder<F> for CachedVandermondeEncoder<M> {
+    fn encode_bytes(
+        &self,
+        encoding: Encoding,
+        field: &F,
+        bytes: &[F::Symbol],
+    ) -> Result<RSStream<F::Symbol>, String> {
+        self.check(encoding)?;
         if bytes.len() == 0 {
             return Ok(RSStream::empty(encoding));
         }
+        return encode_bytes_matrix(encoding, &self.generator, bytes, self.threads, |m, r, o| {
+            m.mul_regions(r, o, field)
+        });
+    }
 
-        let inverted = vandermonde_matrix(
-            0,
-            encoding.data_chunks as usize,
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .invert(field)?;
+    fn decode_bytes(
+        &self,
+        stream: &RSStream<F::Symbol>,
+        field: &F,
+    ) -> Result<Vec<F::Symbol>, String> {
+        self.check(stream.encoding)?;
+        return decode_bytes_vandermonde::<M, _, _>(
+            stream,
+            self.threads,
+            |m, r, o| m.mul_regions(r, o, field),
+            |_, valid| self.decoder(valid),
+        );
+    }
+}
 
-        let generator = vandermonde_matrix(
-            encoding.data_chunks as usize,
-            encoding.code_chunks as usize,
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .mul(&inverted, field);
-        return encode_bytes_matrix(encoding, &generator, field, bytes);
+impl<F: Field> RSEncoder<F> for CauchyEncoder {
+    fn encode_bytes(
+        &self,
+        encoding: Encoding,
+        field: &F,
+        bytes: &[F::Symbol],
+    ) -> Result<RSStream<F::Symbol>, String> {
+        check_encoding::<F::Symbol>(encoding)?;
+        if bytes.len() == 0 {
+            return Ok(RSStream::empty(encoding));
+        }
+
+        // The rows of the code chunks come after those of the data chunks, and any data_chunks
+        // rows of a Cauchy matrix are invertible, so the data can be recovered from any of them.
+        let data_chunks = encoding.data_chunks as usize;
+        let inverted = partial_cauchy_inverse::<F, _>(iter::repeat(true), data_chunks)?;
+        let data: Vec<usize> = (0..data_chunks).collect();
+        let generator = cauchy_recovery(
+            data_chunks..encoding.total_chunks() as usize,
+            &data,
+            &inverted,
+        )?;
+        return encode_bytes_matrix(encoding, &generator, bytes, self.threads, |m, r, o| {
+            m.mul_regions(r, o, field)
+        });
     }
 
-    fn decode_bytes<F: Field256>(&self, stream: &RSStream, field: &F) -> Result<Vec<u8>, String> {
+    fn decode_bytes(
+        &self,
+        stream: &RSStream<F::Symbol>,
+        field: &F,
+    ) -> Result<Vec<F::Symbol>, String> {
         let RSStream {
             length,
             encoding,
             codes,
             valid,
         } = stream;
+        check_encoding::<F::Symbol>(*encoding)?;
         if *length == 0 {
             return Ok(Vec::new());
         }
-        let valid_indices: Vec<usize> = valid
-            .iter()
-            .cloned()
-            .enumerate()
-            .filter(|(_, valid)| *valid)
-            .map(|(i, _)| i)
-            .take(encoding.data_chunks as usize)
-            .collect();
+        let valid_indices = valid_indices(*encoding, valid);
 
         if valid_indices.len() < encoding.data_chunks as usize {
             return Err(String::from("Too many erasures to recover"));
@@ -314,212 +762,816 @@ impl RSEncoder for VandermondeEncoder {
             return Ok(res);
         }
 
-        // Generate the inverted vandermonde matrix for the valid indices to generate polynomial
-        // coefficients.
-        let inverted = partial_vandermonde_matrix(
-            valid.iter().cloned(),
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .invert(field)?;
+        // The inverse of the Cauchy matrix of the valid chunks, and from it the rows of the erased
+        // data chunks (the valid ones are copied).
+        let inverted =
+            partial_cauchy_inverse::<F, _>(valid.iter().cloned(), encoding.data_chunks as usize)?;
+        let generator =
+            cauchy_recovery(0..encoding.data_chunks as usize, &valid_indices, &inverted)?;
+        return decode_bytes_matrix(
+            stream,
+            &generator,
+            &valid_indices[..],
+            self.threads,
+            |m, r, o| m.mul_regions(r, o, field),
+        );
+    }
+}
+
+// Bitslicing is only implemented for GF(2^8).
+impl<F: Field256> RSEncoder<F> for BitslicedEncoder {
+    fn encode_bytes(
+        &self,
+        encoding: Encoding,
+        field: &F,
+        bytes: &[u8],
+    ) -> Result<RSStream, String> {
+        return encode_bytes_vandermonde::<F, _>(encoding, bytes, self.threads, |m, r, o| {
+            bitslice::mul_regions(m, r, o, field)
+        });
+    }
+
+    fn decode_bytes(&self, stream: &RSStream, field: &F) -> Result<Vec<u8>, String> {
+        return decode_bytes_vandermonde::<F, _, _>(
+            stream,
+            self.threads,
+            |m, r, o| bitslice::mul_regions(m, r, o, field),
+            |encoding, valid| Ok(Arc::new(vandermonde_decoder(encoding, valid)?)),
+        );
+    }
+}
+
+// Encoder for Cauchy Reed-Solomon codes with only XORs (see xor_schedule.rs), for large amounts of
+// data. The code chunks are those of a Cauchy matrix under the identity, scaled to have few ones
+// in its bit matrix, so they differ from CauchyEncoder's. Chunks are split into 8 packets, so the
+// stripes are padded to a multiple of 8, which the stream's length leaves out. The CLI splits
+// secrets a byte per stripe, which would be padded eightfold, so only the tests and benches
+// construct it.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct CauchyBitmatrixEncoder {}
 
-        // Generate the data vandermonde matrix to be used with the coefficients to generate the
-        // original data.
-        let generator = vandermonde_matrix(
+#[allow(dead_code)]
+impl CauchyBitmatrixEncoder {
+    // The rows of the generator matrix under the identity.
+    fn coding_matrix<F: Field256>(encoding: Encoding) -> Result<Matrix<F>, String> {
+        let cauchy = cauchy_matrix::<F>(
             0,
+            encoding.code_chunks as usize,
             encoding.data_chunks as usize,
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .mul(&inverted, field);
-        return decode_bytes_matrix(stream, &generator, &valid_indices[..], field);
+        )?;
+        return Ok(fewest_ones(&cauchy)?);
     }
 }
 
-impl RSEncoder for CauchyEncoder {
-    fn encode_bytes<F: Field256>(
+impl<F: Field256> RSEncoder<F> for CauchyBitmatrixEncoder {
+    fn encode_bytes(
         &self,
         encoding: Encoding,
-        field: &F,
+        _field: &F,
         bytes: &[u8],
     ) -> Result<RSStream, String> {
+        check_encoding::<u8>(encoding)?;
         if bytes.len() == 0 {
             return Ok(RSStream::empty(encoding));
         }
-
-        let inverted = cauchy_matrix(
-            encoding.data_chunks as usize,
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .invert(field)?;
-
-        let generator = cauchy_matrix(
-            encoding.code_chunks as usize,
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .mul(&inverted, field);
-        return encode_bytes_matrix(encoding, &generator, field, bytes);
+        let schedule = XorSchedule::new(&Self::coding_matrix::<F>(encoding)?);
+        let mut data = to_regions(bytes, encoding.data_chunks as usize);
+        let stripes = (data[0].len() + 7) / 8 * 8;
+        for region in data.iter_mut() {
+            region.resize(stripes, 0);
+        }
+        let mut code: Vec<Vec<u8>> = iter::repeat(vec![0; stripes])
+            .take(encoding.code_chunks as usize)
+            .collect();
+        schedule.apply(&data, &mut code);
+        return Ok(to_stream(encoding, bytes.len(), &mut data, &mut code));
     }
 
-    fn decode_bytes<F: Field256>(&self, stream: &RSStream, field: &F) -> Result<Vec<u8>, String> {
+    fn decode_bytes(&self, stream: &RSStream, _field: &F) -> Result<Vec<u8>, String> {
         let RSStream {
             length,
             encoding,
-            codes: _,
+            codes,
             valid,
         } = stream;
+        check_encoding::<u8>(*encoding)?;
         if *length == 0 {
             return Ok(Vec::new());
         }
+        let data_chunks = encoding.data_chunks as usize;
         let valid_indices: Vec<usize> = valid
             .iter()
             .cloned()
             .enumerate()
             .filter(|(_, valid)| *valid)
             .map(|(i, _)| i)
-            .take(encoding.data_chunks as usize)
+            .take(data_chunks)
+            .collect();
+
+        if valid_indices.len() < data_chunks {
+            return Err(String::from("Too many erasures to recover"));
+        }
+
+        // Fast path with no erasures
+        if valid.iter().cloned().take(data_chunks).all(|x| x) {
+            let mut res = Vec::with_capacity(*length);
+            for i in 0..*length {
+                res.push(codes[i / data_chunks][i % data_chunks]);
+            }
+            return Ok(res);
+        }
+
+        let stripes = codes.len();
+        if stripes % 8 != 0 {
+            return Err(format!(
+                "Got {} stripes, but they are padded to a multiple of 8",
+                stripes
+            ));
+        }
+
+        // The rows of the generator for the valid chunks, whose inverse gives the data from them.
+        // Only the rows of the erased data chunks are needed.
+        let coding = Self::coding_matrix::<F>(*encoding)?;
+        let identity = Matrix::<F>::identity(data_chunks);
+        let rows: Vec<Vec<Gf<F>>> = valid_indices
+            .iter()
+            .map(|i| match i.checked_sub(data_chunks) {
+                None => identity.mat[*i].clone(),
+                Some(j) => coding.mat[j].clone(),
+            })
             .collect();
+        let inverted = Matrix::<F>::try_from(rows)?.invert()?;
+        let erased: Vec<usize> = (0..data_chunks).filter(|i| !valid[*i]).collect();
+        let decoding = Matrix::<F>::try_from(
+            erased
+                .iter()
+                .map(|i| inverted.mat[*i].clone())
+                .collect::<Vec<_>>(),
+        )?;
+
+        let mut valid_regions: Vec<Vec<u8>> = valid_indices
+            .iter()
+            .map(|j| codes.iter().map(|stripe| stripe[*j]).collect())
+            .collect();
+        let mut recovered: Vec<Vec<u8>> =
+            iter::repeat(vec![0; stripes]).take(erased.len()).collect();
+        XorSchedule::new(&decoding).apply(&valid_regions, &mut recovered);
+
+        let mut regions: Vec<Option<&Vec<u8>>> = vec![None; data_chunks];
+        for (i, region) in erased.iter().zip(recovered.iter()) {
+            regions[*i] = Some(region);
+        }
+        let res: Vec<u8> = (0..*length)
+            .map(|i| match regions[i % data_chunks] {
+                None => codes[i / data_chunks][i % data_chunks],
+                Some(region) => region[i / data_chunks],
+            })
+            .collect();
+
+        for region in valid_regions.iter_mut().chain(recovered.iter_mut()) {
+            zeroize_vec(region);
+        }
+        return Ok(res);
+    }
+}
+
+// Encoder using the additive FFT (see additive_fft.rs), which takes O(n log n) per stripe of n
+// chunks where the matrix encoders take O(k n) for k data chunks, so it suits wide stripes. It
+// needs the subspaces of a binary field. The data chunks are the values of a polynomial at 0..k
+// and the code chunks are its values from the power of two at or after k, with the points in
+// between being zeros which aren't stored, so the codes differ from VandermondeEncoder's. The CLI
+// splits secrets over at most 65536 shards, where the cached Vandermonde matrices are fast enough,
+// so only the tests and benches construct it.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct AdditiveFftEncoder {}
+
+#[allow(dead_code)]
+impl AdditiveFftEncoder {
+    // The number of points the data polynomial is interpolated on, which is the first point of
+    // the code chunks.
+    fn span<S: Symbol>(encoding: Encoding) -> Result<usize, String> {
+        check_encoding::<S>(encoding)?;
+        let span = (encoding.data_chunks as usize).next_power_of_two();
+        if span + encoding.code_chunks as usize > S::ORDER {
+            return Err(format!(
+                "The additive FFT needs {} points for {} chunks, but the field has {} elements",
+                span + encoding.code_chunks as usize,
+                encoding.total_chunks(),
+                S::ORDER
+            ));
+        }
+        return Ok(span);
+    }
+
+    // The point chunk i is the value at.
+    fn point(encoding: Encoding, span: usize, i: usize) -> usize {
+        let data_chunks = encoding.data_chunks as usize;
+        if i < data_chunks {
+            return i;
+        }
+        return span + i - data_chunks;
+    }
+}
+
+impl<F: BinaryField> RSEncoder<F> for AdditiveFftEncoder {
+    fn encode_bytes(
+        &self,
+        encoding: Encoding,
+        field: &F,
+        bytes: &[F::Symbol],
+    ) -> Result<RSStream<F::Symbol>, String> {
+        let span = Self::span::<F::Symbol>(encoding)?;
+        if bytes.len() == 0 {
+            return Ok(RSStream::empty(encoding));
+        }
+        let code_chunks = encoding.code_chunks as usize;
+        let basis = NovelBasis::<F>::new(span.trailing_zeros() as usize);
+
+        // The coefficients of the polynomial with the data chunks, and then zeros, as its values on
+        // the first span points.
+        let mut data = to_regions(bytes, encoding.data_chunks as usize);
+        let stripes = data[0].len();
+        let mut coefficients = data.clone();
+        coefficients.resize(span, vec![F::zero(); stripes]);
+        basis.ifft(&mut coefficients, 0, field);
+
+        // Evaluate it on the following cosets of span points until there are enough code chunks.
+        let mut code = Vec::with_capacity(code_chunks);
+        let mut offset = span;
+        while code.len() < code_chunks {
+            let mut values = coefficients.clone();
+            basis.fft(&mut values, offset, field);
+            let needed = span.min(code_chunks - code.len());
+            for region in values[needed..].iter_mut() {
+                zeroize_vec(region);
+            }
+            values.truncate(needed);
+            code.extend(values);
+            offset += span;
+        }
+        for region in coefficients.iter_mut() {
+            zeroize_vec(region);
+        }
+        return Ok(to_stream(encoding, bytes.len(), &mut data, &mut code));
+    }
+
+    // With the erasures E, and the valid chunks and zeros as the values of the data polynomial P
+    // elsewhere, the values of the error locator L(x), the product of x - e over E, times P are
+    // known everywhere, since L is zero on E. LP has degree less than the number of points, so it
+    // can be interpolated with the inverse FFT. Its derivative is L'P + LP', which is L'(e)P(e) at
+    // e in E, so P(e) is (LP)'(e) / L'(e).
+    fn decode_bytes(
+        &self,
+        stream: &RSStream<F::Symbol>,
+        field: &F,
+    ) -> Result<Vec<F::Symbol>, String> {
+        let RSStream {
+            length,
+            encoding,
+            codes,
+            valid,
+        } = stream;
+        let span = Self::span::<F::Symbol>(*encoding)?;
+        if *length == 0 {
+            return Ok(Vec::new());
+        }
+        let data_chunks = encoding.data_chunks as usize;
+        let is_valid = |i: usize| valid.get(i).cloned().unwrap_or(false);
+        let valid_chunks: Vec<usize> = (0..encoding.total_chunks() as usize)
+            .filter(|i| is_valid(*i))
+            .collect();
+        if valid_chunks.len() < data_chunks {
+            return Err(String::from("Too many erasures to recover"));
+        }
+
+        // Fast path with no data erasures
+        if (0..data_chunks).all(is_valid) {
+            let mut res = Vec::with_capacity(*length);
+            for i in 0..*length {
+                res.push(codes[i / data_chunks][i % data_chunks]);
+            }
+            return Ok(res);
+        }
+
+        let points = (span + encoding.code_chunks as usize).next_power_of_two();
+        let basis = NovelBasis::<F>::new(points.trailing_zeros() as usize);
+        let mut known = vec![false; points];
+        for i in data_chunks..span {
+            known[i] = true;
+        }
+        for i in valid_chunks.iter() {
+            known[Self::point(*encoding, span, *i)] = true;
+        }
+        let erasures: Vec<usize> = (0..points).filter(|x| !known[*x]).collect();
+        // The product of x - e over the erasures other than x.
+        let locator = |x: usize| {
+            return erasures
+                .iter()
+                .filter(|e| **e != x)
+                .map(|e| Gf::<F>::new(F::Symbol::from_usize(x ^ e)))
+                .product::<Gf<F>>();
+        };
+
+        // L times the valid chunks, at their points.
+        let stripes = (length + data_chunks - 1) / data_chunks;
+        let mut values: Vec<Vec<F::Symbol>> = iter::repeat(vec![F::zero(); stripes])
+            .take(points)
+            .collect();
+        let mut chunk = Vec::with_capacity(stripes);
+        for i in valid_chunks.iter() {
+            chunk.clear();
+            chunk.extend(codes.iter().take(stripes).map(|stripe| stripe[*i]));
+            let x = Self::point(*encoding, span, *i);
+            field.mul_add_region(locator(x).value(), &chunk, &mut values[x]);
+        }
+        zeroize_vec(&mut chunk);
+
+        basis.ifft(&mut values, 0, field);
+        basis.formal_derivative(&mut values, field);
+        basis.fft(&mut values, 0, field);
+
+        // The erased data chunks are (LP)' / L' at their points, and the valid ones are as they are.
+        let mut data: Vec<Vec<F::Symbol>> = vec![Vec::new(); data_chunks];
+        for i in (0..data_chunks).filter(|i| !is_valid(*i)) {
+            data[i] = vec![F::zero(); stripes];
+            field.mul_add_region(locator(i).inv().value(), &values[i], &mut data[i]);
+        }
+        let res: Vec<F::Symbol> = (0..*length)
+            .map(|i| {
+                let (row, col) = (i / data_chunks, i % data_chunks);
+                if is_valid(col) {
+                    return codes[row][col];
+                }
+                return data[col][row];
+            })
+            .collect();
+
+        for region in values.iter_mut().chain(data.iter_mut()) {
+            zeroize_vec(region);
+        }
+        return Ok(res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    extern crate test;
+    use super::*;
+    // TODO: Consider using Criterion
+    use crate::field::ExpLogField65536;
+    use crate::finite_field::{ClmulField, DirectField, ExpLogField, TableField};
+    use std::str::FromStr;
+    use test::Bencher;
+
+    fn encode_bytes_empty<E: RSEncoder<DirectField> + Default>() {
+        let direct = DirectField::default();
+        let encoding: Encoding = FromStr::from_str("rs=9.4").unwrap();
+        let expected = RSStream::empty(encoding.clone());
+        let encoder = E::default();
+        assert_eq!(
+            encoder.encode_bytes(encoding, &direct, &[]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn encode_bytes_empty_lagrange() {
+        encode_bytes_empty::<LagrangeInterpolationEncoder>();
+    }
+
+    #[test]
+    fn encode_bytes_empty_vandermonde() {
+        encode_bytes_empty::<VandermondeEncoder>();
+    }
+
+    #[test]
+    fn encode_bytes_empty_bitsliced() {
+        encode_bytes_empty::<BitslicedEncoder>();
+    }
+
+    #[test]
+    fn encode_bytes_empty_cauchy() {
+        encode_bytes_empty::<CauchyEncoder>();
+    }
+
+    #[test]
+    fn encode_bytes_empty_fft() {
+        encode_bytes_empty::<AdditiveFftEncoder>();
+    }
+
+    #[test]
+    fn encode_bytes_empty_cauchy_bitmatrix() {
+        encode_bytes_empty::<CauchyBitmatrixEncoder>();
+    }
+
+    // The code chunks of "DEADBEEF" with rs=4.2, which depend on the matrices.
+    const VANDERMONDE_CODE: [[u8; 2]; 2] = [[0x02, 0x1B], [0x38, 0x27]];
+    const CAUCHY_CODE: [[u8; 2]; 2] = [[0xE4, 0x00], [0x9E, 0xA9]];
+
+    fn encode_bytes_small<E: RSEncoder<DirectField> + Default>(code: [[u8; 2]; 2]) {
+        let direct = DirectField::default();
+        let bytes = "DEADBEEF".as_bytes();
+        let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
+        let expected = RSStream {
+            length: 8,
+            encoding: encoding.clone(),
+            codes: vec![
+                vec![0x44, 0x45, 0x41, 0x44, code[0][0], code[0][1]],
+                vec![0x42, 0x45, 0x45, 0x46, code[1][0], code[1][1]],
+            ],
+            valid: vec![],
+        };
+        let encoder = E::default();
+        assert_eq!(
+            encoder.encode_bytes(encoding, &direct, &bytes).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn encode_bytes_small_lagrange() {
+        encode_bytes_small::<LagrangeInterpolationEncoder>(VANDERMONDE_CODE);
+    }
+
+    #[test]
+    fn encode_bytes_small_vandermonde() {
+        encode_bytes_small::<VandermondeEncoder>(VANDERMONDE_CODE);
+    }
+
+    #[test]
+    fn encode_bytes_small_bitsliced() {
+        encode_bytes_small::<BitslicedEncoder>(VANDERMONDE_CODE);
+    }
+
+    #[test]
+    fn encode_bytes_small_cauchy() {
+        encode_bytes_small::<CauchyEncoder>(CAUCHY_CODE);
+    }
+
+    fn encode_bytes<E: RSEncoder<F> + Default, F: Field<Symbol = u8>>(
+        b: &mut Bencher,
+        size: usize,
+    ) {
+        encode_bytes_using::<E, F>(b, size, E::default());
+    }
+
+    fn encode_bytes_using<E: RSEncoder<F>, F: Field<Symbol = u8>>(
+        b: &mut Bencher,
+        size: usize,
+        encoder: E,
+    ) {
+        encode_bytes_in(b, size, encoder, F::default());
+    }
+
+    fn encode_bytes_in<E: RSEncoder<F>, F: Field<Symbol = u8>>(
+        b: &mut Bencher,
+        size: usize,
+        encoder: E,
+        direct: F,
+    ) {
+        let bytes: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
+        let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
+        b.iter(|| encoder.encode_bytes(encoding, &direct, &bytes[..]));
+    }
+
+    #[bench]
+    fn encode_bytes_4k_lagrange(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<LagrangeInterpolationEncoder, ExpLogField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_4k_vandermonde_explog(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<VandermondeEncoder, ExpLogField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_4k_vandermonde_table(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<VandermondeEncoder, TableField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_4k_cauchy_explog(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<CauchyEncoder, ExpLogField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_4k_cauchy_table(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<CauchyEncoder, TableField>(b, size);
+    }
+
+    #[bench]
+    // #[ignore]
+    fn encode_bytes_1m_vandermonde_explog(b: &mut Bencher) {
+        let size = 1 << 20;
+        encode_bytes::<VandermondeEncoder, ExpLogField>(b, size);
+    }
+
+    #[bench]
+    // #[ignore]
+    fn encode_bytes_1m_vandermonde_table(b: &mut Bencher) {
+        let size = 1 << 20;
+        encode_bytes::<VandermondeEncoder, TableField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_1m_vandermonde_table_2_threads(b: &mut Bencher) {
+        let size = 1 << 20;
+        let encoder = VandermondeEncoder::default().with_threads(2);
+        encode_bytes_using::<_, TableField>(b, size, encoder);
+    }
+
+    #[bench]
+    fn encode_bytes_1m_vandermonde_table_4_threads(b: &mut Bencher) {
+        let size = 1 << 20;
+        let encoder = VandermondeEncoder::default().with_threads(4);
+        encode_bytes_using::<_, TableField>(b, size, encoder);
+    }
+
+    #[bench]
+    fn encode_bytes_1m_vandermonde_clmul(b: &mut Bencher) {
+        let size = 1 << 20;
+        encode_bytes::<VandermondeEncoder, ClmulField>(b, size);
+    }
+
+    // SecretField without PCLMULQDQ, against which the default (and the table fields) compare.
+    #[bench]
+    fn encode_bytes_1m_vandermonde_clmul_portable(b: &mut Bencher) {
+        let size = 1 << 20;
+        let encoder = VandermondeEncoder::default();
+        encode_bytes_in(b, size, encoder, ClmulField::portable());
+    }
+
+    #[bench]
+    fn encode_bytes_1m_vandermonde_direct(b: &mut Bencher) {
+        let size = 1 << 20;
+        encode_bytes::<VandermondeEncoder, DirectField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_4k_bitsliced(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<BitslicedEncoder, TableField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_1m_bitsliced(b: &mut Bencher) {
+        let size = 1 << 20;
+        encode_bytes::<BitslicedEncoder, TableField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_1m_bitsliced_4_threads(b: &mut Bencher) {
+        let size = 1 << 20;
+        let encoder = BitslicedEncoder::default().with_threads(4);
+        encode_bytes_using::<_, TableField>(b, size, encoder);
+    }
+
+    #[bench]
+    fn encode_bytes_4k_cauchy_bitmatrix(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<CauchyBitmatrixEncoder, TableField>(b, size);
+    }
+
+    #[bench]
+    fn encode_bytes_1m_cauchy_bitmatrix(b: &mut Bencher) {
+        let size = 1 << 20;
+        encode_bytes::<CauchyBitmatrixEncoder, TableField>(b, size);
+    }
 
-        if valid_indices.len() < encoding.data_chunks as usize {
-            return Err(String::from("Too many erasures to recover"));
-        }
+    #[bench]
+    fn encode_bytes_4k_fft(b: &mut Bencher) {
+        let size = 4 << 10;
+        encode_bytes::<AdditiveFftEncoder, TableField>(b, size);
+    }
 
-        // Generate the inverted cauchy matrix for the valid indices to generate polynomial
-        // coefficients.
-        let inverted =
-            partial_cauchy_matrix(valid.iter().cloned(), encoding.data_chunks as usize, field)?
-                .invert(field)?;
+    // Stripes with hundreds of chunks, where the additive FFT takes O(n log n) per stripe and the
+    // matrices O(k n).
+    fn encode_bytes_wide<E: RSEncoder<ExpLogField65536> + Default>(b: &mut Bencher) {
+        let field = ExpLogField65536::default();
+        let symbols: Vec<u16> = (0..128 << 8).map(|_| rand::random::<u16>()).collect();
+        let encoding: Encoding = FromStr::from_str("rs=128.384").unwrap();
+        let encoder = E::default();
+        b.iter(|| encoder.encode_bytes(encoding, &field, &symbols[..]));
+    }
 
-        // Generate the data cauchy matrix to be used with the coefficients to generate the
-        // original data.
-        let generator = cauchy_matrix(
-            encoding.data_chunks as usize,
-            encoding.data_chunks as usize,
-            field,
-        )?
-        .mul(&inverted, field);
-        println!("Cauchy: {:?}", generator);
-        return decode_bytes_matrix(stream, &generator, &valid_indices[..], field);
+    #[bench]
+    fn encode_bytes_wide_vandermonde(b: &mut Bencher) {
+        encode_bytes_wide::<VandermondeEncoder>(b);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    extern crate rand;
-    extern crate test;
-    use super::*;
-    // TODO: Consider using Criterion
-    use crate::finite_field::{DirectField, ExpLogField, TableField};
-    use std::str::FromStr;
-    use test::Bencher;
+    #[bench]
+    fn encode_bytes_wide_fft(b: &mut Bencher) {
+        encode_bytes_wide::<AdditiveFftEncoder>(b);
+    }
 
-    fn encode_bytes_empty<E: RSEncoder + Default>() {
-        let direct = DirectField::default();
-        let encoding: Encoding = FromStr::from_str("rs=9.4").unwrap();
-        let expected = RSStream::empty(encoding.clone());
-        let encoder = E::default();
+    #[test]
+    fn encode_decode_bytes_with_matrix_field() {
+        let explog = ExpLogField::default();
+        let constant_time = ClmulField::portable();
+        let encoding = Encoding {
+            data_chunks: 3,
+            code_chunks: 4,
+        };
+        let bytes: Vec<u8> = (0..99).map(|_| rand::random::<u8>()).collect();
+        let encoder = VandermondeEncoder::default();
+        let mut stream = encoder
+            .encode_bytes_with::<ExpLogField, _>(encoding, &constant_time, &bytes)
+            .unwrap();
         assert_eq!(
-            encoder.encode_bytes(encoding, &direct, &[]).unwrap(),
-            expected
+            stream.codes,
+            encoder
+                .encode_bytes(encoding, &explog, &bytes)
+                .unwrap()
+                .codes
+        );
+        stream.valid = vec![false, true, false, true, true, false, false];
+        assert_eq!(
+            encoder
+                .decode_bytes_with::<ExpLogField, _>(&stream, &constant_time)
+                .unwrap(),
+            bytes
         );
     }
 
     #[test]
-    fn encode_bytes_empty_lagrange() {
-        encode_bytes_empty::<LagrangeInterpolationEncoder>();
+    fn in_parallel_keeps_order() {
+        for threads in 0..12 {
+            let stripes: Vec<usize> = in_parallel(10, threads, |range| range.collect());
+            assert_eq!(stripes, (0..10).collect::<Vec<_>>());
+        }
+        assert_eq!(in_parallel(0, 4, |range| range.collect::<Vec<_>>()), vec![]);
+    }
+
+    // The output doesn't depend on the number of threads, including more threads than stripes.
+    fn threads_dont_change_output<E: RSEncoder<DirectField>>(serial: E, parallel: &[E]) {
+        let direct = DirectField::default();
+        let encoding: Encoding = FromStr::from_str("rs=4.3").unwrap();
+        for size in &[1, 9, 1001] {
+            let bytes: Vec<u8> = (0..*size).map(|_| rand::random::<u8>()).collect();
+            let mut encoded = serial.encode_bytes(encoding, &direct, &bytes).unwrap();
+            encoded.valid = vec![false, true, true, false, true, true, false];
+            for stripe in encoded.codes.iter_mut() {
+                stripe[0] = 0;
+                stripe[3] = 0;
+            }
+            for encoder in parallel {
+                let mut stream = encoder.encode_bytes(encoding, &direct, &bytes).unwrap();
+                assert_eq!(stream.codes.len(), encoded.codes.len());
+                stream.valid = encoded.valid.clone();
+                for (stripe, expected) in stream.codes.iter_mut().zip(encoded.codes.iter()) {
+                    stripe[0] = 0;
+                    stripe[3] = 0;
+                    assert_eq!(stripe, expected);
+                }
+                assert_eq!(encoder.decode_bytes(&encoded, &direct).unwrap(), bytes);
+            }
+        }
     }
 
     #[test]
-    fn encode_bytes_empty_vandermonde() {
-        encode_bytes_empty::<VandermondeEncoder>();
+    fn threads_dont_change_output_vandermonde() {
+        threads_dont_change_output(
+            VandermondeEncoder::default(),
+            &[2, 3, 300].map(|t| VandermondeEncoder::default().with_threads(t)),
+        );
     }
 
-    fn encode_bytes_small<E: RSEncoder + Default>() {
-        let direct = DirectField::default();
-        let bytes = "DEADBEEF".as_bytes();
-        let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
-        let expected = RSStream {
-            length: 8,
-            encoding: encoding.clone(),
-            codes: vec![
-                vec![0x44, 0x45, 0x41, 0x44, 0x02, 0x1B],
-                vec![0x42, 0x45, 0x45, 0x46, 0x38, 0x27],
-            ],
-            valid: vec![],
-        };
-        let encoder = E::default();
-        assert_eq!(
-            encoder.encode_bytes(encoding, &direct, &bytes).unwrap(),
-            expected
+    #[test]
+    fn threads_dont_change_output_cauchy() {
+        threads_dont_change_output(
+            CauchyEncoder::default(),
+            &[2, 3, 300].map(|t| CauchyEncoder::default().with_threads(t)),
         );
     }
 
     #[test]
-    fn encode_bytes_small_lagrange() {
-        encode_bytes_small::<LagrangeInterpolationEncoder>();
+    fn threads_dont_change_output_bitsliced() {
+        threads_dont_change_output(
+            BitslicedEncoder::default(),
+            &[2, 3, 300].map(|t| BitslicedEncoder::default().with_threads(t)),
+        );
     }
 
     #[test]
-    fn encode_bytes_small_vandermonde() {
-        encode_bytes_small::<VandermondeEncoder>();
+    fn threads_dont_change_output_cached_vandermonde() {
+        let encoding: Encoding = FromStr::from_str("rs=4.3").unwrap();
+        let encoder = |threads| {
+            return CachedVandermondeEncoder::<DirectField>::new(encoding, 1)
+                .unwrap()
+                .with_threads(threads);
+        };
+        threads_dont_change_output(encoder(1), &[2, 3, 300].map(encoder));
     }
 
-    fn encode_bytes<E: RSEncoder + Default, F: Field256 + Default>(b: &mut Bencher, size: usize) {
-        let direct = F::default();
-        let bytes: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
-        let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
-        let encoder = E::default();
-        b.iter(|| encoder.encode_bytes(encoding, &direct, &bytes[..]));
+    #[test]
+    fn cached_vandermonde_agrees_with_vandermonde() {
+        let clmul = ClmulField::default();
+        let encoding: Encoding = FromStr::from_str("rs=3.3").unwrap();
+        let bytes: Vec<u8> = (0..23).map(|_| rand::random::<u8>()).collect();
+        let cached = CachedVandermondeEncoder::<ExpLogField>::new(encoding, 2).unwrap();
+        let mut encoded = cached.encode_bytes(encoding, &clmul, &bytes).unwrap();
+        assert_eq!(
+            encoded,
+            VandermondeEncoder::default()
+                .encode_bytes_with::<ExpLogField, _>(encoding, &clmul, &bytes)
+                .unwrap()
+        );
+        let codes = encoded.codes.clone();
+        for pattern in [0b111000, 0b101010, 0b111000, 0b010101, 0b110001].iter() {
+            encoded.valid = (0..6).map(|i| pattern & (1 << i) != 0).collect();
+            for (stripe, original) in encoded.codes.iter_mut().zip(codes.iter()) {
+                for i in 0..6 {
+                    stripe[i] = if encoded.valid[i] { original[i] } else { 0 };
+                }
+            }
+            assert_eq!(cached.decode_bytes(&encoded, &clmul).unwrap(), bytes);
+        }
+        // Four patterns were inverted, but only the last two are kept.
+        let decoders = cached.decoders.lock().unwrap();
+        assert_eq!(decoders.len(), 2);
     }
 
-    #[bench]
-    #[ignore]
-    fn encode_bytes_4k_lagrange(b: &mut Bencher) {
-        let size = 4 << 10;
-        encode_bytes::<LagrangeInterpolationEncoder, ExpLogField>(b, size);
+    #[test]
+    fn cached_vandermonde_checks_encoding() {
+        let direct = DirectField::default();
+        let encoding: Encoding = FromStr::from_str("rs=3.3").unwrap();
+        let cached = CachedVandermondeEncoder::<DirectField>::new(encoding, 2).unwrap();
+        let other: Encoding = FromStr::from_str("rs=4.2").unwrap();
+        assert!(cached.encode_bytes(other, &direct, &[1, 2, 3]).is_err());
+        let mut stream = cached.encode_bytes(encoding, &direct, &[1, 2, 3]).unwrap();
+        stream.encoding = other;
+        assert!(cached.decode_bytes(&stream, &direct).is_err());
+        let too_many: Encoding = FromStr::from_str("rs=200.100").unwrap();
+        assert!(CachedVandermondeEncoder::<DirectField>::new(too_many, 2).is_err());
+    }
+
+    // Decoding many small streams with the same chunks missing, as when restoring shards a byte at
+    // a time.
+    fn decode_many_streams<F: Field256, E: RSEncoder<F>>(b: &mut Bencher, encoder: E) {
+        let field = F::default();
+        let encoding: Encoding = FromStr::from_str("rs=32.16").unwrap();
+        let streams: Vec<RSStream> = (0..100)
+            .map(|_| {
+                let bytes: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
+                let mut encoded = encoder.encode_bytes(encoding, &field, &bytes).unwrap();
+                encoded.valid = (0..48).map(|i| i % 3 != 0).collect();
+                return encoded;
+            })
+            .collect();
+        b.iter(|| {
+            for stream in streams.iter() {
+                encoder.decode_bytes(stream, &field).unwrap();
+            }
+        });
     }
 
     #[bench]
-    fn encode_bytes_4k_vandermonde_explog(b: &mut Bencher) {
-        let size = 4 << 10;
-        encode_bytes::<VandermondeEncoder, ExpLogField>(b, size);
+    fn decode_many_streams_vandermonde(b: &mut Bencher) {
+        decode_many_streams::<ExpLogField, _>(b, VandermondeEncoder::default());
     }
 
+    // TableField's matrices are powers, so they are inverted in O(n^2) (see
+    // Matrix::invert_vandermonde), where ExpLogField's take O(n^3).
     #[bench]
-    fn encode_bytes_4k_vandermonde_table(b: &mut Bencher) {
-        let size = 4 << 10;
-        encode_bytes::<VandermondeEncoder, TableField>(b, size);
+    fn decode_many_streams_vandermonde_table(b: &mut Bencher) {
+        decode_many_streams::<TableField, _>(b, VandermondeEncoder::default());
     }
 
-    // #[bench]
-    // fn encode_bytes_4k_cauchy_explog(b: &mut Bencher) {
-    //     let size = 4 << 10;
-    //     encode_bytes::<CauchyEncoder, ExpLogField>(b, size);
-    // }
-    //
-    // #[bench]
-    // fn encode_bytes_4k_cauchy_table(b: &mut Bencher) {
-    //     let size = 4 << 10;
-    //     encode_bytes::<CauchyEncoder, TableField>(b, size);
-    // }
+    // The decoding matrix of a wide stripe, as for restoring 192 shards from 128.
+    fn vandermonde_decoder_wide<M: Field>(b: &mut Bencher) {
+        let encoding: Encoding = FromStr::from_str("rs=128.64").unwrap();
+        let valid: Vec<bool> = (0..192).map(|i| i % 3 != 0).collect();
+        b.iter(|| vandermonde_decoder::<M>(encoding, &valid).unwrap());
+    }
 
     #[bench]
-    // #[ignore]
-    fn encode_bytes_1m_vandermonde_explog(b: &mut Bencher) {
-        let size = 1 << 20;
-        encode_bytes::<VandermondeEncoder, ExpLogField>(b, size);
+    fn vandermonde_decoder_wide_explog(b: &mut Bencher) {
+        vandermonde_decoder_wide::<ExpLogField>(b);
     }
 
     #[bench]
-    // #[ignore]
-    fn encode_bytes_1m_vandermonde_table(b: &mut Bencher) {
-        let size = 1 << 20;
-        encode_bytes::<VandermondeEncoder, TableField>(b, size);
+    fn vandermonde_decoder_wide_table(b: &mut Bencher) {
+        vandermonde_decoder_wide::<TableField>(b);
+    }
+
+    #[bench]
+    fn decode_many_streams_cached_vandermonde(b: &mut Bencher) {
+        let encoding: Encoding = FromStr::from_str("rs=32.16").unwrap();
+        decode_many_streams::<ExpLogField, _>(
+            b,
+            CachedVandermondeEncoder::<ExpLogField>::new(encoding, 4).unwrap(),
+        );
     }
 
-    fn decode_bytes_no_erasures<E: RSEncoder + Default>() {
+    fn decode_bytes_no_erasures<E: RSEncoder<DirectField> + Default>() {
         let direct = DirectField::default();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
         let input = RSStream {
@@ -550,7 +1602,30 @@ mod tests {
         decode_bytes_no_erasures::<VandermondeEncoder>();
     }
 
-    fn decode_bytes_no_erasures_bench<E: RSEncoder + Default>(b: &mut Bencher, size: usize) {
+    #[test]
+    fn decode_bytes_no_erasures_bitsliced() {
+        decode_bytes_no_erasures::<BitslicedEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_no_erasures_cauchy() {
+        decode_bytes_no_erasures::<CauchyEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_no_erasures_fft() {
+        decode_bytes_no_erasures::<AdditiveFftEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_no_erasures_cauchy_bitmatrix() {
+        decode_bytes_no_erasures::<CauchyBitmatrixEncoder>();
+    }
+
+    fn decode_bytes_no_erasures_bench<E: RSEncoder<ExpLogField> + Default>(
+        b: &mut Bencher,
+        size: usize,
+    ) {
         let direct = ExpLogField::default();
         let bytes: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
         let encoder = E::default();
@@ -560,7 +1635,6 @@ mod tests {
     }
 
     #[bench]
-    #[ignore]
     fn decode_bytes_no_erasures_4k_lagrange(b: &mut Bencher) {
         decode_bytes_no_erasures_bench::<LagrangeInterpolationEncoder>(b, 4 << 10);
     }
@@ -570,7 +1644,12 @@ mod tests {
         decode_bytes_no_erasures_bench::<VandermondeEncoder>(b, 1 << 10);
     }
 
-    fn decode_bytes_code_erasure<E: RSEncoder + Default>() {
+    #[bench]
+    fn decode_bytes_no_erasures_4k_cauchy(b: &mut Bencher) {
+        decode_bytes_no_erasures_bench::<CauchyEncoder>(b, 4 << 10);
+    }
+
+    fn decode_bytes_code_erasure<E: RSEncoder<DirectField> + Default>() {
         let direct = DirectField::default();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
         let input = RSStream {
@@ -582,7 +1661,7 @@ mod tests {
             ],
             valid: vec![true, true, true, true, false, false],
         };
-        let encoder = LagrangeInterpolationEncoder {};
+        let encoder = E::default();
         let res = encoder.decode_bytes(&input, &direct);
         assert!(res.is_ok());
         assert_eq!(
@@ -601,18 +1680,40 @@ mod tests {
         decode_bytes_code_erasure::<VandermondeEncoder>();
     }
 
-    fn decode_bytes_code_erasures_bench<E: RSEncoder + Default>(b: &mut Bencher, size: usize) {
+    #[test]
+    fn decode_bytes_code_erasures_bitsliced() {
+        decode_bytes_code_erasure::<BitslicedEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_code_erasures_cauchy() {
+        decode_bytes_code_erasure::<CauchyEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_code_erasures_fft() {
+        decode_bytes_code_erasure::<AdditiveFftEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_code_erasures_cauchy_bitmatrix() {
+        decode_bytes_code_erasure::<CauchyBitmatrixEncoder>();
+    }
+
+    fn decode_bytes_code_erasures_bench<E: RSEncoder<ExpLogField> + Default>(
+        b: &mut Bencher,
+        size: usize,
+    ) {
         let direct = ExpLogField::default();
         let bytes: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
-        let encoder = LagrangeInterpolationEncoder {};
+        let encoder = E::default();
         let mut encoded = encoder.encode_bytes(encoding, &direct, &bytes[..]).unwrap();
         encoded.valid = vec![true, true, true, true, false, false];
         b.iter(|| encoder.decode_bytes(&encoded, &direct));
     }
 
     #[bench]
-    #[ignore]
     fn decode_bytes_code_erasures_4k_lagrange(b: &mut Bencher) {
         decode_bytes_code_erasures_bench::<LagrangeInterpolationEncoder>(b, 4 << 10);
     }
@@ -622,20 +1723,20 @@ mod tests {
         decode_bytes_code_erasures_bench::<VandermondeEncoder>(b, 4 << 10);
     }
 
-    // #[bench]
-    // fn decode_bytes_code_erasures_4k_cauchy(b: &mut Bencher) {
-    //     decode_bytes_code_erasures_bench::<CauchyEncoder>(b, 4 << 10);
-    // }
+    #[bench]
+    fn decode_bytes_code_erasures_4k_cauchy(b: &mut Bencher) {
+        decode_bytes_code_erasures_bench::<CauchyEncoder>(b, 4 << 10);
+    }
 
-    fn decode_bytes_data_erasure<E: RSEncoder + Default>() {
+    fn decode_bytes_data_erasure<E: RSEncoder<TableField> + Default>(code: [[u8; 2]; 2]) {
         let direct = TableField::default();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
         let input = RSStream {
             length: 8,
             encoding: encoding.clone(),
             codes: vec![
-                vec![0x00, 0x45, 0x00, 0x44, 0x02, 0x1B],
-                vec![0x00, 0x45, 0x00, 0x46, 0x38, 0x27],
+                vec![0x00, 0x45, 0x00, 0x44, code[0][0], code[0][1]],
+                vec![0x00, 0x45, 0x00, 0x46, code[1][0], code[1][1]],
             ],
             valid: vec![false, true, false, true, true, true],
         };
@@ -649,20 +1750,25 @@ mod tests {
 
     #[test]
     fn decode_bytes_data_erasure_lagrange() {
-        decode_bytes_data_erasure::<LagrangeInterpolationEncoder>();
+        decode_bytes_data_erasure::<LagrangeInterpolationEncoder>(VANDERMONDE_CODE);
     }
 
     #[test]
     fn decode_bytes_data_erasures_vandermonde() {
-        decode_bytes_data_erasure::<VandermondeEncoder>();
+        decode_bytes_data_erasure::<VandermondeEncoder>(VANDERMONDE_CODE);
     }
 
-    // #[test]
-    // fn decode_bytes_data_erasures_cauchy() {
-    //     decode_bytes_data_erasure::<CauchyEncoder>();
-    // }
+    #[test]
+    fn decode_bytes_data_erasures_bitsliced() {
+        decode_bytes_data_erasure::<BitslicedEncoder>(VANDERMONDE_CODE);
+    }
+
+    #[test]
+    fn decode_bytes_data_erasures_cauchy() {
+        decode_bytes_data_erasure::<CauchyEncoder>(CAUCHY_CODE);
+    }
 
-    fn encode_decode_bytes_data_erasure<E: RSEncoder + Default>() {
+    fn encode_decode_bytes_data_erasure<E: RSEncoder<DirectField> + Default>() {
         let direct = DirectField::default();
         let bytes: Vec<u8> = (0..23).map(|_| rand::random::<u8>()).collect();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
@@ -690,23 +1796,119 @@ mod tests {
         encode_decode_bytes_data_erasure::<VandermondeEncoder>();
     }
 
-    // #[test]
-    // fn encode_decode_bytes_data_erasures_cauchy() {
-    //     encode_decode_bytes_data_erasure::<CauchyEncoder>();
-    // }
+    #[test]
+    fn encode_decode_bytes_data_erasures_bitsliced() {
+        encode_decode_bytes_data_erasure::<BitslicedEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_bytes_data_erasures_fft() {
+        encode_decode_bytes_data_erasure::<AdditiveFftEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_bytes_data_erasures_cauchy_bitmatrix() {
+        encode_decode_bytes_data_erasure::<CauchyBitmatrixEncoder>();
+    }
+
+    // Stripes are padded to a multiple of 8, and large regions are split into several groups of
+    // packets.
+    #[test]
+    fn encode_decode_cauchy_bitmatrix_padding() {
+        let direct = DirectField::default();
+        let encoding: Encoding = FromStr::from_str("rs=10.4").unwrap();
+        let encoder = CauchyBitmatrixEncoder::default();
+        for size in &[1, 79, 80, 81, 100_003] {
+            let bytes: Vec<u8> = (0..*size).map(|_| rand::random::<u8>()).collect();
+            let mut encoded = encoder.encode_bytes(encoding, &direct, &bytes).unwrap();
+            assert_eq!(encoded.length, *size);
+            assert_eq!(encoded.codes.len(), (size + 79) / 80 * 8);
+            encoded.valid = (0..14).map(|i| i % 4 != 1).collect();
+            for stripe in encoded.codes.iter_mut() {
+                for i in (1..14).step_by(4) {
+                    stripe[i] = 0;
+                }
+            }
+            assert_eq!(encoder.decode_bytes(&encoded, &direct).unwrap(), bytes);
+            encoded.codes.pop();
+            assert!(encoder.decode_bytes(&encoded, &direct).is_err());
+        }
+    }
+
+    // Every choice of data_chunks valid chunks recovers the data.
+    fn encode_decode_every_erasure_pattern<E: RSEncoder<DirectField> + Default>() {
+        let direct = DirectField::default();
+        let bytes: Vec<u8> = (0..23).map(|_| rand::random::<u8>()).collect();
+        let encoding: Encoding = FromStr::from_str("rs=3.3").unwrap();
+        let encoder = E::default();
+        let mut encoded = encoder.encode_bytes(encoding, &direct, &bytes[..]).unwrap();
+        let codes = encoded.codes.clone();
+        for pattern in 0..(1 << 6) {
+            let valid: Vec<bool> = (0..6).map(|i| pattern & (1 << i) != 0).collect();
+            if valid.iter().filter(|v| **v).count() != 3 {
+                continue;
+            }
+            for (stripe, original) in encoded.codes.iter_mut().zip(codes.iter()) {
+                for i in 0..6 {
+                    stripe[i] = if valid[i] { original[i] } else { 0 };
+                }
+            }
+            encoded.valid = valid;
+            assert_eq!(encoder.decode_bytes(&encoded, &direct).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn encode_decode_every_erasure_pattern_lagrange() {
+        encode_decode_every_erasure_pattern::<LagrangeInterpolationEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_every_erasure_pattern_vandermonde() {
+        encode_decode_every_erasure_pattern::<VandermondeEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_every_erasure_pattern_cauchy() {
+        encode_decode_every_erasure_pattern::<CauchyEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_every_erasure_pattern_fft() {
+        encode_decode_every_erasure_pattern::<AdditiveFftEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_every_erasure_pattern_cauchy_bitmatrix() {
+        encode_decode_every_erasure_pattern::<CauchyBitmatrixEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_bytes_data_erasures_cauchy() {
+        encode_decode_bytes_data_erasure::<CauchyEncoder>();
+    }
+
+    fn decode_bytes_data_erasures_bench<E: RSEncoder<ExpLogField> + Default>(
+        b: &mut Bencher,
+        size: usize,
+    ) {
+        decode_bytes_data_erasures_bench_using(b, size, E::default());
+    }
 
-    fn decode_bytes_data_erasures_bench<E: RSEncoder + Default>(b: &mut Bencher, size: usize) {
+    fn decode_bytes_data_erasures_bench_using<E: RSEncoder<ExpLogField>>(
+        b: &mut Bencher,
+        size: usize,
+        encoder: E,
+    ) {
         let direct = ExpLogField::default();
         let bytes: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
-        let encoder = E::default();
         let mut encoded = encoder.encode_bytes(encoding, &direct, &bytes[..]).unwrap();
         encoded.valid = vec![false, true, false, true, true, true];
         b.iter(|| encoder.decode_bytes(&encoded, &direct));
     }
 
     #[bench]
-    #[ignore]
     fn decode_bytes_data_erasures_4k_lagrange(b: &mut Bencher) {
         decode_bytes_data_erasures_bench::<LagrangeInterpolationEncoder>(b, 4 << 10);
     }
@@ -716,12 +1918,127 @@ mod tests {
         decode_bytes_data_erasures_bench::<VandermondeEncoder>(b, 4 << 10);
     }
 
-    // #[bench]
-    // fn decode_bytes_data_erasures_4k_cauchy(b: &mut Bencher) {
-    //     decode_bytes_data_erasures_bench::<CauchyEncoder>(b, 4 << 10);
-    // }
+    #[bench]
+    fn decode_bytes_data_erasures_4k_bitsliced(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<BitslicedEncoder>(b, 4 << 10);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_1m_vandermonde(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<VandermondeEncoder>(b, 1 << 20);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_1m_vandermonde_4_threads(b: &mut Bencher) {
+        let encoder = VandermondeEncoder::default().with_threads(4);
+        decode_bytes_data_erasures_bench_using(b, 1 << 20, encoder);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_1m_bitsliced(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<BitslicedEncoder>(b, 1 << 20);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_4k_cauchy_bitmatrix(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<CauchyBitmatrixEncoder>(b, 4 << 10);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_1m_cauchy_bitmatrix(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<CauchyBitmatrixEncoder>(b, 1 << 20);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_4k_fft(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<AdditiveFftEncoder>(b, 4 << 10);
+    }
+
+    #[bench]
+    fn decode_bytes_data_erasures_4k_cauchy(b: &mut Bencher) {
+        decode_bytes_data_erasures_bench::<CauchyEncoder>(b, 4 << 10);
+    }
+
+    fn encode_decode_more_than_256_chunks<E: RSEncoder<ExpLogField65536> + Default>() {
+        let field = ExpLogField65536::default();
+        let symbols: Vec<u16> = (0..400).map(|_| rand::random::<u16>()).collect();
+        let encoding: Encoding = FromStr::from_str("rs=150.150").unwrap();
+        let encoder = E::default();
+        let mut encoded = encoder.encode_bytes(encoding, &field, &symbols).unwrap();
+        assert_eq!(encoded.codes[0].len(), 300);
+        // Lose every other chunk, including half of the data chunks.
+        encoded.valid = (0..300).map(|i| i % 2 == 1).collect();
+        for stripe in encoded.codes.iter_mut() {
+            for i in (0..300).step_by(2) {
+                stripe[i] = 0;
+            }
+        }
+        assert_eq!(encoder.decode_bytes(&encoded, &field).unwrap(), symbols);
+    }
+
+    #[test]
+    fn encode_decode_more_than_256_chunks_lagrange() {
+        encode_decode_more_than_256_chunks::<LagrangeInterpolationEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_more_than_256_chunks_vandermonde() {
+        encode_decode_more_than_256_chunks::<VandermondeEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_more_than_256_chunks_cauchy() {
+        encode_decode_more_than_256_chunks::<CauchyEncoder>();
+    }
+
+    #[test]
+    fn encode_decode_more_than_256_chunks_fft() {
+        encode_decode_more_than_256_chunks::<AdditiveFftEncoder>();
+    }
+
+    // Every number of erasures a wide stripe can recover from, at random.
+    #[test]
+    fn encode_decode_wide_stripes_fft() {
+        let direct = DirectField::default();
+        let bytes: Vec<u8> = (0..1000).map(|_| rand::random::<u8>()).collect();
+        let encoding: Encoding = FromStr::from_str("rs=100.100").unwrap();
+        let encoder = AdditiveFftEncoder::default();
+        let mut encoded = encoder.encode_bytes(encoding, &direct, &bytes).unwrap();
+        for erasures in (0..=100).step_by(10) {
+            let mut valid = vec![true; 200];
+            while valid.iter().filter(|v| !**v).count() < erasures {
+                valid[rand::random::<usize>() % 200] = false;
+            }
+            encoded.valid = valid;
+            assert_eq!(encoder.decode_bytes(&encoded, &direct).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn encode_bytes_too_many_chunks_for_field() {
+        let direct = DirectField::default();
+        let encoding: Encoding = FromStr::from_str("rs=200.100").unwrap();
+        let encoder = VandermondeEncoder::default();
+        assert!(encoder.encode_bytes(encoding, &direct, &[1, 2, 3]).is_err());
+        assert!(LagrangeInterpolationEncoder::default()
+            .encode_bytes(encoding, &direct, &[1, 2, 3])
+            .is_err());
+        assert!(CauchyBitmatrixEncoder::default()
+            .encode_bytes(encoding, &direct, &[1, 2, 3])
+            .is_err());
+        // The code chunks start at the power of two after the data chunks, so 128 + 100 points
+        // fit in GF(2^8) but 256 + 100 don't.
+        let encoding: Encoding = FromStr::from_str("rs=100.100").unwrap();
+        assert!(AdditiveFftEncoder::default()
+            .encode_bytes(encoding, &direct, &[1, 2, 3])
+            .is_ok());
+        let encoding: Encoding = FromStr::from_str("rs=129.100").unwrap();
+        assert!(AdditiveFftEncoder::default()
+            .encode_bytes(encoding, &direct, &[1, 2, 3])
+            .is_err());
+    }
 
-    fn decode_bytes_too_many_erasures<E: RSEncoder + Default>() {
+    fn decode_bytes_too_many_erasures<E: RSEncoder<DirectField> + Default>() {
         let direct = DirectField::default();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
         let input = RSStream {
@@ -748,7 +2065,30 @@ mod tests {
         decode_bytes_too_many_erasures::<VandermondeEncoder>();
     }
 
-    fn decode_bytes_too_many_erasures_bench<E: RSEncoder + Default>(b: &mut Bencher, size: usize) {
+    #[test]
+    fn decode_bytes_too_many_erasures_bitsliced() {
+        decode_bytes_too_many_erasures::<BitslicedEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_too_many_erasures_cauchy() {
+        decode_bytes_too_many_erasures::<CauchyEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_too_many_erasures_fft() {
+        decode_bytes_too_many_erasures::<AdditiveFftEncoder>();
+    }
+
+    #[test]
+    fn decode_bytes_too_many_erasures_cauchy_bitmatrix() {
+        decode_bytes_too_many_erasures::<CauchyBitmatrixEncoder>();
+    }
+
+    fn decode_bytes_too_many_erasures_bench<E: RSEncoder<ExpLogField> + Default>(
+        b: &mut Bencher,
+        size: usize,
+    ) {
         let direct = ExpLogField::default();
         let bytes: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
         let encoding: Encoding = FromStr::from_str("rs=4.2").unwrap();
@@ -759,7 +2099,6 @@ mod tests {
     }
 
     #[bench]
-    #[ignore]
     fn decode_bytes_too_many_erasures_4k_lagrange(b: &mut Bencher) {
         decode_bytes_too_many_erasures_bench::<LagrangeInterpolationEncoder>(b, 4 << 10);
     }
@@ -768,4 +2107,9 @@ mod tests {
     fn decode_bytes_too_many_erasures_4k_vandermonde(b: &mut Bencher) {
         decode_bytes_too_many_erasures_bench::<VandermondeEncoder>(b, 4 << 10);
     }
+
+    #[bench]
+    fn decode_bytes_too_many_erasures_4k_cauchy(b: &mut Bencher) {
+        decode_bytes_too_many_erasures_bench::<CauchyEncoder>(b, 4 << 10);
+    }
 }
@@ -1,14 +1,17 @@
 use std::result::Result;
 use std::str::FromStr;
 
+// The number of chunks of a Reed-Solomon code. A code over GF(2^8) can have at most 256 chunks in
+// total, while one over GF(2^16) can have many more, so the encoders check the total against their
+// field.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Encoding {
-    pub data_chunks: u8,
-    pub code_chunks: u8,
+    pub data_chunks: u16,
+    pub code_chunks: u16,
 }
 
 impl Encoding {
-    pub fn total_chunks(self: &Self) -> u8 {
+    pub fn total_chunks(self: &Self) -> u16 {
         return self.data_chunks + self.code_chunks;
     }
 }
@@ -23,7 +26,7 @@ impl FromStr for Encoding {
         if !s.starts_with("rs=") {
             return Err("Encodings must start with \"rs=\"");
         }
-        let chunks: Vec<Result<u8, _>> = s
+        let chunks: Vec<Result<u16, _>> = s
             .get(3..)
             .unwrap()
             .split(".")
@@ -38,7 +41,7 @@ impl FromStr for Encoding {
                         code_chunks: code,
                     })
                 } else {
-                    Err("Total number of chunks must be less than 256.")
+                    Err("Total number of chunks must be less than 65536.")
                 }
             }
             _ => Err("Chunks must be specified in the form m.n where m and n are integers."),
@@ -78,7 +81,17 @@ mod tests {
 
     #[test]
     fn from_str_invalid_encoding() {
-        let actual: Result<Encoding, _> = FromStr::from_str("rs=128.128");
+        let actual: Result<Encoding, _> = FromStr::from_str("rs=32768.32768");
         assert_eq!(actual.is_err(), true);
     }
+
+    #[test]
+    fn from_str_more_than_256_chunks() {
+        let expected = Encoding {
+            data_chunks: 200,
+            code_chunks: 300,
+        };
+        let actual: Result<Encoding, _> = FromStr::from_str("rs=200.300");
+        assert_eq!(actual.unwrap(), expected);
+    }
 }
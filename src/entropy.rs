@@ -0,0 +1,237 @@
+// User contributed entropy for generating secrets.
+//
+// By default secrets come from the operating system's random number generator. For air-gapped
+// ceremonies the user can also type in dice rolls or arbitrary text, which is mixed with the OS
+// randomness using HKDF-SHA256 (RFC 5869), so the secret is at least as strong as the stronger of
+// the two. In the dice only mode the OS RNG is never used: the secret and the shards are drawn from
+// a generator seeded from the dice rolls alone, which must then carry at least as much entropy as
+// the secret.
+//
+// Dice are rolled four at a time and each group is looked up in the dice numbers of the EFF short
+// wordlist (1111 to 6666), which gives an index below 6^4 = 1296.
+
+use crate::secret::Secret;
+use crate::shamir::gen_random_bytes;
+use hmac::{Hmac, KeyInit, Mac};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::include_bytes;
+use std::str::FromStr;
+
+const EFF_WORDLIST_RAW: &[u8] = include_bytes!("../assets/eff_short_wordlist_1.txt");
+const DICE_PER_GROUP: usize = 4;
+// Distinguishes secrets derived here from other uses of the same input.
+const HKDF_INFO: &[u8] = b"shamir secret";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntropySource {
+    // Only the OS random number generator.
+    Os,
+    // Dice rolls mixed with the OS random number generator.
+    Dice,
+    // Typed text mixed with the OS random number generator.
+    Text,
+    // Only dice rolls, without touching the OS random number generator.
+    DiceOnly,
+}
+
+impl FromStr for EntropySource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<EntropySource, Self::Err> {
+        match s {
+            "os" => Ok(EntropySource::Os),
+            "dice" => Ok(EntropySource::Dice),
+            "text" => Ok(EntropySource::Text),
+            "dice-only" => Ok(EntropySource::DiceOnly),
+            _ => Err(format!(
+                "Unknown entropy source {:?}, expected one of ['os', 'dice', 'text', 'dice-only']",
+                s
+            )),
+        }
+    }
+}
+
+// Maps the dice numbers of the EFF short wordlist (e.g. "1111") to their position in the list.
+fn load_dice_map() -> HashMap<String, u16> {
+    return String::from_utf8_lossy(EFF_WORDLIST_RAW)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .enumerate()
+        .map(|(i, dice)| (dice.to_string(), i as u16))
+        .collect();
+}
+
+// Parses dice rolls (the digits 1 to 6, with any whitespace between them) into the indices of
+// each group of four.
+pub fn parse_dice(s: &str) -> Result<Vec<u16>, String> {
+    let rolls: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if let Some(c) = rolls.chars().find(|c| !('1'..='6').contains(c)) {
+        return Err(format!("Invalid dice roll {:?}, expected 1 to 6.", c));
+    }
+    if rolls.is_empty() || rolls.len() % DICE_PER_GROUP != 0 {
+        return Err(format!(
+            "Dice must be rolled in groups of {}, got {} rolls.",
+            DICE_PER_GROUP,
+            rolls.len()
+        ));
+    }
+    let dice_map = load_dice_map();
+    return Ok((0..rolls.len())
+        .step_by(DICE_PER_GROUP)
+        .map(|i| dice_map[&rolls[i..i + DICE_PER_GROUP]])
+        .collect());
+}
+
+// The entropy of the given number of groups of dice, rounded down to whole bits.
+pub fn dice_bits(groups: usize) -> usize {
+    return (groups as f64 * (1296 as f64).log2()) as usize;
+}
+
+// The number of groups of dice needed for at least the given number of bits.
+pub fn dice_groups_for_bits(bits: usize) -> usize {
+    let mut groups = 0;
+    while dice_bits(groups) < bits {
+        groups += 1;
+    }
+    return groups;
+}
+
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    return mac.finalize().into_bytes().to_vec();
+}
+
+// HKDF-SHA256 extract and expand (RFC 5869).
+fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    assert!(length <= 255 * 32);
+    let prk = hmac_sha256(salt, &[ikm]);
+    let mut okm: Vec<u8> = Vec::with_capacity(length + 32);
+    let mut block: Vec<u8> = Vec::new();
+    let mut counter: u8 = 1;
+    while okm.len() < length {
+        block = hmac_sha256(&prk, &[&block, info, &[counter]]);
+        okm.extend(&block);
+        counter += 1;
+    }
+    okm.truncate(length);
+    return okm;
+}
+
+// Mixes the user's entropy with OS randomness. Each source is fully used, so the result is
+// unpredictable as long as either of them is.
+pub fn gen_mixed_bytes<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    length: usize,
+    user_entropy: &[u8],
+) -> Vec<u8> {
+    let os_entropy = gen_random_bytes(rng, std::cmp::max(length, 32));
+    return hkdf(&os_entropy, user_entropy, HKDF_INFO, length);
+}
+
+// A generator seeded from dice alone, which must have at least `bits` bits of entropy. Dice only
+// mode draws the secret and the shards from it, so the same dice always give the same output. The
+// seed is 256 bits, so no secret gets more entropy than that.
+pub fn dice_rng(bits: usize, dice: &[u16]) -> Result<ChaCha20Rng, String> {
+    if dice_bits(dice.len()) < bits {
+        return Err(format!(
+            "{} bits of entropy need at least {} groups of dice, got {}.",
+            bits,
+            dice_groups_for_bits(bits),
+            dice.len()
+        ));
+    }
+    let ikm = Secret::new(dice.iter().flat_map(|d| d.to_be_bytes().to_vec()).collect());
+    let okm = Secret::new(hkdf(&[], ikm.expose(), HKDF_INFO, 32));
+    let mut seed = [0; 32];
+    seed.copy_from_slice(okm.expose());
+    let rng = ChaCha20Rng::from_seed(seed);
+    seed.iter_mut().for_each(|b| *b = 0);
+    return Ok(rng);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shamir::shamir_secret;
+    use crate::{codex32, slip39};
+    use rand::rngs::OsRng;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        return (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+    }
+
+    #[test]
+    fn hkdf_rfc5869_vector() {
+        // Test case 1 from RFC 5869.
+        let okm = hkdf(
+            &from_hex("000102030405060708090a0b0c"),
+            &[0x0b; 22],
+            &from_hex("f0f1f2f3f4f5f6f7f8f9"),
+            42,
+        );
+        assert_eq!(
+            okm,
+            from_hex(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+            )
+        );
+    }
+
+    #[test]
+    fn parse_dice_groups() {
+        assert_eq!(parse_dice("1111 1112\n6666"), Ok(vec![0, 1, 1295]));
+        assert!(parse_dice("1117").is_err());
+        assert!(parse_dice("111").is_err());
+        assert!(parse_dice("").is_err());
+    }
+
+    #[test]
+    fn dice_groups() {
+        assert_eq!(dice_bits(1), 10);
+        assert_eq!(dice_groups_for_bits(128), 13);
+        assert!(dice_bits(dice_groups_for_bits(256)) >= 256);
+    }
+
+    #[test]
+    fn dice_only_is_deterministic() {
+        let dice = parse_dice(&"1234".repeat(13)).unwrap();
+        assert!(dice_rng(128, &dice[..12]).is_err());
+        let output = |dice: &[u16]| {
+            let mut rng = dice_rng(128, dice).unwrap();
+            let secret = gen_random_bytes(&mut rng, 16);
+            let words: Vec<String> = shamir_secret(&mut rng, &secret, 4, 2)
+                .unwrap()
+                .iter()
+                .map(|phrase| phrase.expose_str().to_string())
+                .collect();
+            let groups = [slip39::Group {
+                threshold: 2,
+                count: 3,
+            }];
+            let slip39 = slip39::generate_mnemonics(&mut rng, 1, &groups, &secret, b"", true, 0);
+            let codex32 = codex32::split(&mut rng, &secret, 2, 3);
+            return (secret, words, slip39.unwrap(), codex32.unwrap());
+        };
+        assert_eq!(output(&dice), output(&dice));
+        let mut other = dice.clone();
+        other[0] += 1;
+        assert_ne!(output(&dice).0, output(&other).0);
+    }
+
+    #[test]
+    fn mixed_uses_os_randomness() {
+        let a = gen_mixed_bytes(&mut OsRng, 48, b"the same text");
+        let b = gen_mixed_bytes(&mut OsRng, 48, b"the same text");
+        assert_eq!(a.len(), 48);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,559 @@
+// Fields with a choice of symbol size. Field256 (see finite_field.rs) is GF(2^8), whose 256
+// elements limit a code to 256 evaluation points, and so to 255 shares or 256 blocks per stripe.
+// `Field` is generic over the symbol type, so GF(2^16), with u16 symbols, can be used wherever more
+// are needed, as can the prime fields of prime_field.rs. Every Field256 is a Field with u8 symbols.
+//
+// Field256 and Field have methods with the same names, so only one of them should be imported
+// where concrete fields are used, or calls to them become ambiguous.
+
+use crate::finite_field::{
+    Field256, GenericClmulField, GenericDirectField, GenericExpLogField, GenericTableField,
+};
+use std::fmt;
+use std::iter;
+use std::marker::PhantomData;
+use std::ops;
+use std::sync::OnceLock;
+
+// The representation of a field element, such as a u8 for GF(2^8). Symbols are plain values, so they
+// can be shared across the threads encoding a stream.
+pub trait Symbol: Copy + Default + Eq + fmt::Debug + fmt::Display + From<u8> + Send + Sync {
+    // The number of elements of the field (or usize::MAX if there are more), which is also how
+    // many distinct evaluation points (and so chunks) a code over it can have.
+    const ORDER: usize;
+
+    // Truncates x to the width of the symbol.
+    fn from_usize(x: usize) -> Self;
+    fn to_usize(self) -> usize;
+}
+
+impl Symbol for u8 {
+    const ORDER: usize = 1 << 8;
+
+    fn from_usize(x: usize) -> Self {
+        return x as u8;
+    }
+
+    fn to_usize(self) -> usize {
+        return self as usize;
+    }
+}
+
+impl Symbol for u16 {
+    const ORDER: usize = 1 << 16;
+
+    fn from_usize(x: usize) -> Self {
+        return x as u16;
+    }
+
+    fn to_usize(self) -> usize {
+        return self as usize;
+    }
+}
+
+// A finite field whose elements are Symbols. The operations are the same as Field256's, but since
+// not every field has characteristic 2, addition must be implemented as well as mul(). Like Field256s,
+// fields have no state, so they can be shared across threads.
+pub trait Field: Default + Copy + fmt::Debug + Eq + Send + Sync {
+    type Symbol: Symbol;
+
+    // Additive identity.
+    fn zero() -> Self::Symbol {
+        return Self::Symbol::from(0);
+    }
+    // Multiplicative identity.
+    fn one() -> Self::Symbol {
+        return Self::Symbol::from(1);
+    }
+
+    fn add(x: Self::Symbol, y: Self::Symbol) -> Self::Symbol;
+    fn sub(x: Self::Symbol, y: Self::Symbol) -> Self::Symbol;
+    fn neg(x: Self::Symbol) -> Self::Symbol;
+
+    fn mul(&self, x: Self::Symbol, y: Self::Symbol) -> Self::Symbol;
+    fn div(&self, x: Self::Symbol, y: Self::Symbol) -> Self::Symbol {
+        return self.mul(x, self.inv(y));
+    }
+
+    // Returns x ^ y, by repeated squaring since y can be large.
+    fn exp(&self, x: Self::Symbol, y: Self::Symbol) -> Self::Symbol {
+        let mut result = Self::one();
+        let mut square = x;
+        let mut e = y.to_usize();
+        while e > 0 {
+            if e & 1 == 1 {
+                result = self.mul(result, square);
+            }
+            square = self.mul(square, square);
+            e >>= 1;
+        }
+        return result;
+    }
+
+    // The non-zero elements form a group of order ORDER - 1, so x ^ (ORDER - 2) * x == 1.
+    fn inv(&self, x: Self::Symbol) -> Self::Symbol {
+        return self.exp(x, Self::Symbol::from_usize(Self::Symbol::ORDER - 2));
+    }
+
+    // Sets dst[i] += c * src[i].
+    fn mul_add_region(&self, c: Self::Symbol, src: &[Self::Symbol], dst: &mut [Self::Symbol]) {
+        assert_eq!(src.len(), dst.len());
+        for (s, d) in src.iter().zip(dst.iter_mut()) {
+            *d = Self::add(*d, self.mul(c, *s));
+        }
+    }
+}
+
+// Every GF(2^8) implementation, with its own (possibly faster) versions of each operation.
+impl<F: Field256> Field for F {
+    type Symbol = u8;
+
+    fn zero() -> u8 {
+        return <F as Field256>::zero();
+    }
+    fn one() -> u8 {
+        return <F as Field256>::one();
+    }
+    fn add(x: u8, y: u8) -> u8 {
+        return <F as Field256>::add(x, y);
+    }
+    fn sub(x: u8, y: u8) -> u8 {
+        return <F as Field256>::sub(x, y);
+    }
+    fn neg(x: u8) -> u8 {
+        return <F as Field256>::neg(x);
+    }
+    fn mul(&self, x: u8, y: u8) -> u8 {
+        return Field256::mul(self, x, y);
+    }
+    fn div(&self, x: u8, y: u8) -> u8 {
+        return Field256::div(self, x, y);
+    }
+    fn exp(&self, x: u8, y: u8) -> u8 {
+        return Field256::exp(self, x, y);
+    }
+    fn inv(&self, x: u8) -> u8 {
+        return Field256::inv(self, x);
+    }
+    fn mul_add_region(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        Field256::mul_add_region(self, c, src, dst);
+    }
+}
+
+// The polynomial x^16 + x^12 + x^3 + x + 1, which has x as a generator.
+const IRREDUCIBLE_65536: u16 = 0x100B;
+
+// GF(2^16) implementation that does computations directly, like DirectField.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct DirectField65536;
+
+// Addition in GF(2^16) is XOR, as in GF(2^8).
+impl Field for DirectField65536 {
+    type Symbol = u16;
+
+    fn add(x: u16, y: u16) -> u16 {
+        return x ^ y;
+    }
+    fn sub(x: u16, y: u16) -> u16 {
+        return x ^ y;
+    }
+    fn neg(x: u16) -> u16 {
+        return x;
+    }
+
+    fn mul(&self, x: u16, y: u16) -> u16 {
+        let mut result = 0;
+        let mut a = x;
+        let mut b = y;
+        // "Russian peasant" multiplication, always doing all 16 rounds so that, like SecretField,
+        // it can be used on secret data.
+        for _ in 0..16 {
+            result ^= (b & 1).wrapping_neg() & a;
+            b >>= 1;
+            a = (a << 1) ^ (((a & 0x8000) >> 15).wrapping_neg() & IRREDUCIBLE_65536);
+        }
+        return result;
+    }
+}
+
+struct ExpLogTables65536 {
+    exp: Vec<u16>,
+    log: Vec<u16>,
+}
+
+// The tables take 384KiB, so unlike GenericExpLogField's they are built on first use rather than
+// at compile time (which would put them in the binary).
+fn exp_log_tables_65536() -> &'static ExpLogTables65536 {
+    static TABLES: OnceLock<ExpLogTables65536> = OnceLock::new();
+    return TABLES.get_or_init(|| {
+        let direct = DirectField65536::default();
+        let mut tables = ExpLogTables65536 {
+            exp: vec![0; 2 * 65535],
+            log: vec![0; 65536],
+        };
+        let mut x: u16 = 1;
+        for i in 0..65535 {
+            tables.exp[i] = x;
+            tables.exp[i + 65535] = x;
+            tables.log[x as usize] = i as u16;
+            x = direct.mul(x, 2);
+        }
+        return tables;
+    });
+}
+
+// GF(2^16) implementation using exponent and log tables. Like the GF(2^8) table based fields, its
+// memory accesses depend on the values, so it should not be used on secret data.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ExpLogField65536;
+
+impl Field for ExpLogField65536 {
+    type Symbol = u16;
+
+    fn add(x: u16, y: u16) -> u16 {
+        return x ^ y;
+    }
+    fn sub(x: u16, y: u16) -> u16 {
+        return x ^ y;
+    }
+    fn neg(x: u16) -> u16 {
+        return x;
+    }
+
+    fn mul(&self, x: u16, y: u16) -> u16 {
+        if x == 0 || y == 0 {
+            return 0;
+        }
+        let tables = exp_log_tables_65536();
+        return tables.exp[tables.log[x as usize] as usize + tables.log[y as usize] as usize];
+    }
+
+    fn div(&self, x: u16, y: u16) -> u16 {
+        if x == 0 {
+            return 0;
+        } else if y == 0 {
+            panic!("Cannot divide by zero!");
+        }
+        let tables = exp_log_tables_65536();
+        return tables.exp
+            [tables.log[x as usize] as usize + 65535 - tables.log[y as usize] as usize];
+    }
+
+    fn inv(&self, x: u16) -> u16 {
+        if x == 0 {
+            return 0;
+        }
+        let tables = exp_log_tables_65536();
+        return tables.exp[65535 - tables.log[x as usize] as usize];
+    }
+
+    fn exp(&self, x: u16, y: u16) -> u16 {
+        if y == 0 {
+            return 1;
+        }
+        if x == 0 {
+            return 0;
+        }
+        let tables = exp_log_tables_65536();
+        return tables.exp[(tables.log[x as usize] as usize * y as usize) % 65535];
+    }
+
+    // Looks up the log of c once, rather than for every element.
+    fn mul_add_region(&self, c: u16, src: &[u16], dst: &mut [u16]) {
+        assert_eq!(src.len(), dst.len());
+        if c == 0 {
+            return;
+        }
+        let tables = exp_log_tables_65536();
+        let logc = tables.log[c as usize] as usize;
+        for (s, d) in src.iter().zip(dst.iter_mut()) {
+            if *s != 0 {
+                *d ^= tables.exp[logc + tables.log[*s as usize] as usize];
+            }
+        }
+    }
+}
+
+// A field of characteristic 2 whose symbols are its elements as polynomials over GF(2), one bit per
+// coefficient, so that addition is XOR of the symbols and the symbols 0..2^k are a subspace over
+// GF(2) for every k. The additive FFT (see additive_fft.rs) evaluates on these subspaces. Ring's
+// addition isn't XOR, so it isn't one.
+pub trait BinaryField: Field {}
+
+impl<const POLYNOMIAL: u16> BinaryField for GenericDirectField<POLYNOMIAL> {}
+impl<const POLYNOMIAL: u16> BinaryField for GenericClmulField<POLYNOMIAL> {}
+impl<const POLYNOMIAL: u16, const GEN: u8> BinaryField for GenericExpLogField<POLYNOMIAL, GEN> {}
+impl<const POLYNOMIAL: u16> BinaryField for GenericTableField<POLYNOMIAL> {}
+impl BinaryField for DirectField65536 {}
+impl BinaryField for ExpLogField65536 {}
+
+// An element of the field F, with F's arithmetic as operators. The field is part of the type, so
+// elements of different fields can't be mixed, and a + b can't accidentally be integer addition of
+// the underlying symbols.
+pub struct Gf<F: Field>(F::Symbol, PhantomData<F>);
+
+impl<F: Field> Gf<F> {
+    pub fn new(x: F::Symbol) -> Self {
+        return Gf(x, PhantomData);
+    }
+
+    pub fn zero() -> Self {
+        return Self::new(F::zero());
+    }
+
+    pub fn one() -> Self {
+        return Self::new(F::one());
+    }
+
+    // The underlying symbol.
+    pub fn value(self) -> F::Symbol {
+        return self.0;
+    }
+
+    pub fn is_zero(self) -> bool {
+        return self.0 == F::zero();
+    }
+
+    pub fn inv(self) -> Self {
+        return Self::new(F::default().inv(self.0));
+    }
+
+    // self ^ e, as computed by F::exp (which for ExpLogField is not actual exponentiation, but is
+    // what its Vandermonde matrices have always been built with).
+    pub fn pow(self, e: F::Symbol) -> Self {
+        return Self::new(F::default().exp(self.0, e));
+    }
+}
+
+impl<F: Field> Clone for Gf<F> {
+    fn clone(&self) -> Self {
+        return *self;
+    }
+}
+
+impl<F: Field> Copy for Gf<F> {}
+
+impl<F: Field> PartialEq for Gf<F> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.0 == other.0;
+    }
+}
+
+impl<F: Field> Eq for Gf<F> {}
+
+impl<F: Field> Default for Gf<F> {
+    fn default() -> Self {
+        return Self::zero();
+    }
+}
+
+impl<F: Field> fmt::Debug for Gf<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "Gf({})", self.0);
+    }
+}
+
+impl<F: Field> fmt::Display for Gf<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}
+
+impl<F: Field> From<u8> for Gf<F> {
+    fn from(x: u8) -> Self {
+        return Self::new(F::Symbol::from(x));
+    }
+}
+
+impl<F: Field> ops::Add for Gf<F> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        return Self::new(F::add(self.0, other.0));
+    }
+}
+
+impl<F: Field> ops::Sub for Gf<F> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        return Self::new(F::sub(self.0, other.0));
+    }
+}
+
+impl<F: Field> ops::Neg for Gf<F> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        return Self::new(F::neg(self.0));
+    }
+}
+
+impl<F: Field> ops::Mul for Gf<F> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        return Self::new(F::default().mul(self.0, other.0));
+    }
+}
+
+// Panics when dividing by zero, for the fields whose div() does.
+impl<F: Field> ops::Div for Gf<F> {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        return Self::new(F::default().div(self.0, other.0));
+    }
+}
+
+impl<F: Field> ops::AddAssign for Gf<F> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<F: Field> ops::SubAssign for Gf<F> {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl<F: Field> ops::MulAssign for Gf<F> {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl<F: Field> ops::DivAssign for Gf<F> {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+impl<F: Field> iter::Sum for Gf<F> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        return iter.fold(Self::zero(), |acc, x| acc + x);
+    }
+}
+
+impl<F: Field> iter::Product for Gf<F> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        return iter.fold(Self::one(), |acc, x| acc * x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    use super::*;
+    use crate::finite_field::{DirectField, ExpLogField, Ring};
+
+    #[test]
+    fn field256_is_field() {
+        let direct = DirectField::default();
+        let explog = ExpLogField::default();
+        for i in 0..=255 {
+            for j in 0..=255 {
+                assert_eq!(Field::mul(&direct, i, j), Field256::mul(&direct, i, j));
+                assert_eq!(Field::exp(&explog, i, j), Field256::exp(&explog, i, j));
+            }
+        }
+        assert_eq!(<Ring as Field>::add(3, 5), 8);
+    }
+
+    #[test]
+    fn gf_operators() {
+        type G = Gf<DirectField>;
+        let direct = DirectField::default();
+        for i in 0..=255 {
+            for j in 1..=255 {
+                let (x, y) = (G::new(i), G::new(j));
+                assert_eq!((x + y).value(), <DirectField as Field>::add(i, j));
+                assert_eq!((x - y).value(), <DirectField as Field>::sub(i, j));
+                assert_eq!((x * y).value(), Field::mul(&direct, i, j));
+                assert_eq!((x / y) * y, x);
+                assert_eq!(y * y.inv(), G::one());
+                assert_eq!(-x, x);
+            }
+        }
+        let mut x = G::new(7);
+        x += G::new(1);
+        x *= G::new(2);
+        assert_eq!(x, G::new(6) * G::new(2));
+    }
+
+    #[test]
+    fn gf_sum_and_product() {
+        type G = Gf<DirectField>;
+        let xs: Vec<G> = (1..=4).map(G::new).collect();
+        // 1 ^ 2 ^ 3 ^ 4
+        assert_eq!(xs.iter().cloned().sum::<G>(), G::new(4));
+        assert_eq!(
+            xs.iter().cloned().product::<G>(),
+            G::new(1) * G::new(2) * G::new(3) * G::new(4)
+        );
+        assert_eq!(Vec::<G>::new().into_iter().sum::<G>(), G::zero());
+        assert_eq!(Vec::<G>::new().into_iter().product::<G>(), G::one());
+    }
+
+    #[test]
+    fn gf_uses_its_field() {
+        // The same bytes add and multiply differently in Ring.
+        assert_eq!((Gf::<Ring>::new(3) + Gf::new(5)).value(), 8);
+        assert_eq!((Gf::<DirectField>::new(3) + Gf::new(5)).value(), 6);
+        assert_eq!((Gf::<Ring>::new(3) * Gf::new(5)).value(), 15);
+        assert_eq!((Gf::<DirectField>::new(3) * Gf::new(5)).value(), 15);
+        assert_eq!((Gf::<DirectField>::new(3) * Gf::new(7)).value(), 9);
+        assert_eq!(Gf::<DirectField>::new(2).pow(3), Gf::new(8));
+        assert_eq!(Gf::<DirectField65536>::new(0x100).pow(2), Gf::new(0x100B));
+    }
+
+    #[test]
+    fn generator_65536() {
+        // x generates all 65535 non-zero elements, so the polynomial is primitive.
+        let direct = DirectField65536::default();
+        let mut seen = vec![false; 65536];
+        let mut x: u16 = 1;
+        for _ in 0..65535 {
+            assert!(!seen[x as usize]);
+            seen[x as usize] = true;
+            x = direct.mul(x, 2);
+        }
+        assert_eq!(x, 1);
+    }
+
+    fn field_65536_axioms_for<T: Field<Symbol = u16>>() {
+        let field = T::default();
+        for _ in 0..10000 {
+            let x: u16 = rand::random();
+            let y: u16 = rand::random::<u16>().max(1);
+            assert_eq!(field.mul(x, y), field.mul(y, x));
+            assert_eq!(field.mul(x, T::one()), x);
+            assert_eq!(field.div(field.mul(x, y), y), x);
+            assert_eq!(field.mul(y, field.inv(y)), T::one());
+            assert_eq!(field.exp(y, 3), field.mul(y, field.mul(y, y)));
+        }
+        let src: Vec<u16> = (0..100).map(|_| rand::random()).collect();
+        let dst: Vec<u16> = (0..100).map(|_| rand::random()).collect();
+        let c: u16 = rand::random();
+        let mut out = dst.clone();
+        field.mul_add_region(c, &src, &mut out);
+        for i in 0..100 {
+            assert_eq!(out[i], dst[i] ^ field.mul(c, src[i]));
+        }
+    }
+
+    #[test]
+    fn field_65536_axioms() {
+        field_65536_axioms_for::<DirectField65536>();
+        field_65536_axioms_for::<ExpLogField65536>();
+    }
+
+    #[test]
+    fn field_65536_implementations_agree() {
+        let direct = DirectField65536::default();
+        let explog = ExpLogField65536::default();
+        for _ in 0..10000 {
+            let x: u16 = rand::random();
+            let y: u16 = rand::random();
+            assert_eq!(direct.mul(x, y), explog.mul(x, y));
+            assert_eq!(direct.inv(x), explog.inv(x));
+            assert_eq!(direct.exp(x, y), explog.exp(x, y));
+        }
+    }
+}
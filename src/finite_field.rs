@@ -1,7 +1,9 @@
+use crate::region;
 use std::default;
+use std::fmt;
 
-// The AES polynomial, without the leading bit (we shift it out before reducing).
-const IRREDUCIBLE: u8 = 0b00011011;
+// The AES polynomial, x^8 + x^4 + x^3 + x + 1.
+pub const AES_POLYNOMIAL: u16 = 0b100011011;
 
 // An element of GF(2^8) which, when raised to powers 0..255, generates every element of the field.
 const GENERATOR: u8 = 0b11;
@@ -16,7 +18,9 @@ const GENERATOR: u8 = 0b11;
 // 0 or 1. Thus, we can represent each polynomial with an 8 bit integer.
 //
 // The only function that must be implemented is mul(), but others can be implemented for speed.
-pub trait Field256 {
+// Fields must be cheap to create and copy (any tables are built at compile time), since Gf
+// creates its field whenever it needs it, and they can be shared across threads.
+pub trait Field256: Default + Copy + fmt::Debug + Eq + Send + Sync {
     // Additive identity.
     fn zero() -> u8 {
         return 0;
@@ -53,11 +57,18 @@ pub trait Field256 {
         return self.mul(x, self.inv(y));
     }
 
-    // Returns x ^ y. May use self to speed up the computation.
+    // Returns x ^ y, by repeated squaring, so that a row of a Vandermonde matrix takes O(n log n)
+    // multiplications rather than O(n^2). May use self to speed up the computation.
     fn exp(&self, x: u8, y: u8) -> u8 {
         let mut result = Self::one();
-        for _ in 0..y {
-            result = self.mul(result, x);
+        let mut square = x;
+        let mut e = y;
+        while e > 0 {
+            if e & 1 == 1 {
+                result = self.mul(result, square);
+            }
+            square = self.mul(square, square);
+            e >>= 1;
         }
         return result;
     }
@@ -74,6 +85,23 @@ pub trait Field256 {
         assert!(false, "No multiplicative inv for {:?}", x);
         return Self::zero();
     }
+
+    // Sets dst[i] = c * src[i]. May be overridden to multiply several elements at once.
+    fn mul_slice(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        assert_eq!(src.len(), dst.len());
+        for (s, d) in src.iter().zip(dst.iter_mut()) {
+            *d = self.mul(c, *s);
+        }
+    }
+
+    // Sets dst[i] += c * src[i]. This is the inner loop of multiplying a matrix by whole regions of
+    // data, so fields override it with region::mul_add_region (or another vectorized version).
+    fn mul_add_region(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        assert_eq!(src.len(), dst.len());
+        for (s, d) in src.iter().zip(dst.iter_mut()) {
+            *d = Self::add(*d, self.mul(c, *s));
+        }
+    }
 }
 
 // Fake Field implementation that does normal integer arithmetic, but inv() is not well defined.
@@ -94,12 +122,31 @@ impl Field256 for Ring {
     }
 }
 
-// Field implementation that does computations directly.
+// The polynomial (including its x^8 bit) without the leading bit, since it is shifted out before
+// reducing. Using it checks at compile time that the polynomial is irreducible, as the tables of
+// the table fields do.
+const fn irreducible(polynomial: u16) -> u8 {
+    assert!(
+        is_irreducible(polynomial),
+        "The polynomial must be irreducible"
+    );
+    return polynomial as u8;
+}
+
+// Field implementation that does computations directly, modulo the given polynomial (including
+// its x^8 bit). ClmulField does the same multiplication with carry-less multiplication
+// instructions where available.
 #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
-pub struct DirectField;
+pub struct GenericDirectField<const POLYNOMIAL: u16>;
 
-impl Field256 for DirectField {
-    // TODO: Use CLMUL or similar intrinsics with std::arch.
+impl<const POLYNOMIAL: u16> GenericDirectField<POLYNOMIAL> {
+    const IRREDUCIBLE: u8 = irreducible(POLYNOMIAL);
+}
+
+// The AES polynomial, like the other fields by default.
+pub type DirectField = GenericDirectField<AES_POLYNOMIAL>;
+
+impl<const POLYNOMIAL: u16> Field256 for GenericDirectField<POLYNOMIAL> {
     fn mul(&self, x: u8, y: u8) -> u8 {
         let mut result = Self::zero();
         let mut a = x;
@@ -116,46 +163,336 @@ impl Field256 for DirectField {
             }
             // If b would have a "carry" when doubling it, reduce it via the irreducible
             // polynomial.
-            a = (a << 1) ^ (((a & 0b10000000) >> 7).wrapping_neg() & IRREDUCIBLE);
+            a = (a << 1) ^ (((a & 0b10000000) >> 7).wrapping_neg() & Self::IRREDUCIBLE);
         }
         return result;
     }
+
+    fn mul_add_region(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        region::mul_add_region(self, c, src, dst);
+    }
 }
 
-pub struct ExpLogField {
-    exp: [u8; 512],
-    log: [u8; 256],
+// Field implementation using carry-less multiplication, which is the polynomial multiplication of
+// GF(2)[x] without the reduction. On x86_64 CPUs with PCLMULQDQ it is done by the CPU, otherwise a
+// portable (branchless) version is used. Either way the product is reduced modulo the given
+// polynomial (including its x^8 bit) with shifts and XORs, so the running time does not depend on
+// the values it operates on.
+//
+// Elements are packed into 16 bit lanes of a u64, which leaves room for the 15 bit product of two
+// elements, so one carry-less multiplication multiplies four elements by the same constant.
+//
+// It is SecretField because its portable version already is a branchless shift-and-XOR multiply,
+// so a separate scalar constant-time field would only be a second implementation to audit, and
+// the PCLMULQDQ version gives the same products in as few instructions as the CPU allows. The
+// encode_bytes_1m_vandermonde benches compare it with the table fields and DirectField.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GenericClmulField<const POLYNOMIAL: u16> {
+    pclmulqdq: bool,
 }
 
-impl default::Default for ExpLogField {
+// The AES polynomial, like the other fields by default.
+pub type ClmulField = GenericClmulField<AES_POLYNOMIAL>;
+
+impl<const POLYNOMIAL: u16> default::Default for GenericClmulField<POLYNOMIAL> {
+    // Uses PCLMULQDQ if the CPU supports it.
     fn default() -> Self {
-        // TODO: Consider using e.g. lazy_static! to initialize the tables once and have all
-        // implementations refer to them.
-        let direct = DirectField::default();
-        let mut x = Self::one();
-        let mut res = Self {
-            exp: [0; 512],
-            log: [0; 256],
+        #[cfg(target_arch = "x86_64")]
+        let pclmulqdq = is_x86_feature_detected!("pclmulqdq");
+        #[cfg(not(target_arch = "x86_64"))]
+        let pclmulqdq = false;
+        return GenericClmulField {
+            pclmulqdq: pclmulqdq,
         };
-        for i in 0..=255 {
-            res.exp[i] = x;
-            res.exp[i + 255] = x;
-            res.log[x as usize] = i as u8;
-            x = direct.mul(x, GENERATOR);
+    }
+}
+
+// The low bit of each 16 bit lane, and the bits of an element in each lane.
+const LANES_LOW: u64 = 0x0001_0001_0001_0001;
+const LANES_BYTE: u64 = 0x00ff_00ff_00ff_00ff;
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn clmul_pclmulqdq(a: u64, b: u8) -> u64 {
+    use std::arch::x86_64::{_mm_clmulepi64_si128, _mm_cvtsi128_si64, _mm_cvtsi64_si128};
+    let product = _mm_clmulepi64_si128(
+        _mm_cvtsi64_si128(a as i64),
+        _mm_cvtsi64_si128(b as i64),
+        0x00,
+    );
+    // The lanes keep the product below 64 bits, so the high half is always zero.
+    return _mm_cvtsi128_si64(product) as u64;
+}
+
+// Packs up to four elements into 16 bit lanes.
+fn to_lanes(elements: &[u8]) -> u64 {
+    return elements
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, x)| acc | (*x as u64) << (16 * i));
+}
+
+fn clmul_portable(a: u64, b: u8) -> u64 {
+    let mut result = 0;
+    for i in 0..8 {
+        result ^= ((b as u64 >> i) & 1).wrapping_neg() & (a << i);
+    }
+    return result;
+}
+
+// The rounds reduce_lanes needs for the polynomial. Each round multiplies the bits above the
+// eighth by the polynomial without its x^8 bit, of degree d, so a product of degree m becomes one
+// of degree at most m - 8 + d, starting from the 14 of the product of two elements.
+const fn reduction_rounds(polynomial: u16) -> usize {
+    let d = degree(irreducible(polynomial) as u16);
+    let mut m = 14;
+    let mut rounds = 0;
+    while m >= 8 {
+        m = m - 8 + d;
+        rounds += 1;
+    }
+    return rounds;
+}
+
+// Reduces each 15 bit lane modulo the polynomial. Each round multiplies the bits above the eighth
+// by the polynomial without its x^8 bit (with a shift for each of its bits, which only depend on
+// the polynomial, not on the data) and adds them back. The AES polynomial (x^4 + x^3 + x + 1 below
+// x^8) takes four shifts and two rounds.
+fn reduce_lanes<const POLYNOMIAL: u16>(product: u64) -> u64 {
+    let mut p = product;
+    for _ in 0..GenericClmulField::<POLYNOMIAL>::ROUNDS {
+        let high = (p >> 8) & (LANES_LOW * 0x7f);
+        let mut reduced = p & LANES_BYTE;
+        for bit in 0..8 {
+            if (POLYNOMIAL >> bit) & 1 == 1 {
+                reduced ^= high << bit;
+            }
         }
+        p = reduced;
+    }
+    return p;
+}
 
-        return res;
+impl<const POLYNOMIAL: u16> GenericClmulField<POLYNOMIAL> {
+    const ROUNDS: usize = reduction_rounds(POLYNOMIAL);
+
+    // Always uses the portable carry-less multiplication.
+    #[allow(unused)]
+    pub fn portable() -> Self {
+        return GenericClmulField { pclmulqdq: false };
+    }
+
+    // Multiplies each 16 bit lane of `lanes` by `c`.
+    fn mul_lanes(&self, lanes: u64, c: u8) -> u64 {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.pclmulqdq {
+                return reduce_lanes::<POLYNOMIAL>(unsafe { clmul_pclmulqdq(lanes, c) });
+            }
+        }
+        return reduce_lanes::<POLYNOMIAL>(clmul_portable(lanes, c));
     }
 }
 
-impl Field256 for ExpLogField {
+impl<const POLYNOMIAL: u16> Field256 for GenericClmulField<POLYNOMIAL> {
+    fn mul(&self, x: u8, y: u8) -> u8 {
+        return self.mul_lanes(x as u64, y) as u8;
+    }
+
+    // Every non-zero element satisfies x^255 == 1, so x^254 is its inverse. It is computed as
+    // x^2 * x^4 * ... * x^128 with a fixed sequence of multiplications, and maps 0 to 0 (so dividing
+    // by zero gives zero rather than panicking).
+    fn inv(&self, x: u8) -> u8 {
+        let mut square = x;
+        let mut result = Self::one();
+        for _ in 1..8 {
+            square = self.mul(square, square);
+            result = self.mul(result, square);
+        }
+        return result;
+    }
+
+    // Four elements at a time.
+    fn mul_slice(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        assert_eq!(src.len(), dst.len());
+        for (s, d) in src.chunks(4).zip(dst.chunks_mut(4)) {
+            let product = self.mul_lanes(to_lanes(s), c);
+            for (i, x) in d.iter_mut().enumerate() {
+                *x = (product >> (16 * i)) as u8;
+            }
+        }
+    }
+
+    // Not region::mul_add_region, since its scalar fallback indexes tables with the data.
+    fn mul_add_region(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        assert_eq!(src.len(), dst.len());
+        for (s, d) in src.chunks(4).zip(dst.chunks_mut(4)) {
+            let product = self.mul_lanes(to_lanes(s), c);
+            for (i, x) in d.iter_mut().enumerate() {
+                *x ^= (product >> (16 * i)) as u8;
+            }
+        }
+    }
+}
+
+// The field secret bytes are split and restored with. Its running time must not depend on the
+// values it operates on: the table based fields leak their operands through memory access timing,
+// and the default inv() stops searching as soon as it finds the inverse. ClmulField is the one
+// field that qualifies, so secret data always uses it (and the table fields only ever see the
+// public encoding matrices, or non-secret data).
+pub type SecretField = ClmulField;
+
+// Multiplies x and y modulo the given polynomial (including its x^8 bit), as DirectField::mul does.
+// This is a const fn so that the tables below can be computed at compile time.
+const fn mul_mod(polynomial: u16, x: u8, y: u8) -> u8 {
+    let mut result = 0;
+    let mut a = x;
+    let mut b = y;
+    let mut i = 0;
+    while i < 8 {
+        result ^= (b & 1).wrapping_neg() & a;
+        b >>= 1;
+        a = (a << 1) ^ (((a & 0b10000000) >> 7).wrapping_neg() & polynomial as u8);
+        i += 1;
+    }
+    return result;
+}
+
+// The degree of a (non-zero) polynomial over GF(2).
+const fn degree(x: u16) -> u32 {
+    return 15 - x.leading_zeros();
+}
+
+// The remainder of dividing polynomial x by polynomial y (which must not be zero).
+const fn rem(x: u16, y: u16) -> u16 {
+    let mut r = x;
+    while r != 0 && degree(r) >= degree(y) {
+        r ^= y << (degree(r) - degree(y));
+    }
+    return r;
+}
+
+// Returns whether the polynomial of degree 8 is irreducible, that is, it has no factors other than
+// 1 and itself (like a prime number), which makes arithmetic modulo it a field. Any factorization
+// would have a factor of degree 4 or less, so only those need to be tried.
+pub const fn is_irreducible(polynomial: u16) -> bool {
+    if degree(polynomial) != 8 {
+        return false;
+    }
+    // All polynomials of degree 1 to 4.
+    let mut divisor = 0b10;
+    while divisor < 0b100000 {
+        if rem(polynomial, divisor) == 0 {
+            return false;
+        }
+        divisor += 1;
+    }
+    return true;
+}
+
+// Returns whether the powers of the generator give every non-zero element of the field, that is,
+// whether the polynomial is irreducible and the generator has order 255. Not every irreducible
+// polynomial has x (0b10) as a generator: the AES polynomial doesn't, while 0x11D does.
+pub const fn is_primitive(polynomial: u16, generator: u8) -> bool {
+    if !is_irreducible(polynomial) {
+        return false;
+    }
+    let mut x = generator;
+    let mut order = 1;
+    while x != 1 && order < 256 {
+        x = mul_mod(polynomial, x, generator);
+        order += 1;
+    }
+    return order == 255;
+}
+
+pub struct ExpLogTables {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+const fn exp_log_tables(polynomial: u16, generator: u8) -> ExpLogTables {
+    assert!(
+        is_primitive(polynomial, generator),
+        "The generator must generate the field of an irreducible polynomial"
+    );
+    let mut tables = ExpLogTables {
+        exp: [0; 512],
+        log: [0; 256],
+    };
+    let mut x = 1;
+    let mut i = 0;
+    while i <= 255 {
+        tables.exp[i] = x;
+        tables.exp[i + 255] = x;
+        tables.log[x as usize] = i as u8;
+        x = mul_mod(polynomial, x, generator);
+        i += 1;
+    }
+    return tables;
+}
+
+pub struct MulTables {
+    inv: [u8; 256],
+    mul: [[u8; 256]; 256],
+}
+
+const fn mul_tables(polynomial: u16) -> MulTables {
+    assert!(
+        is_irreducible(polynomial),
+        "The polynomial must be irreducible"
+    );
+    let mut tables = MulTables {
+        inv: [0; 256],
+        mul: [[0; 256]; 256],
+    };
+    // Calculate the multiplication table and inverses at the same time. Each product is computed
+    // from an earlier one, i * j == 2 * (i * (j >> 1)) + i * (j & 1), since multiplying each of the
+    // 65536 pairs from scratch takes too long for the compiler.
+    let mut i = 0;
+    while i <= 255 {
+        let mut j = 1;
+        while j <= 255 {
+            let half = tables.mul[i][j >> 1];
+            let mut k = half << 1;
+            if half & 0b10000000 != 0 {
+                k ^= polynomial as u8;
+            }
+            if j & 1 == 1 {
+                k ^= i as u8;
+            }
+            tables.mul[i][j] = k;
+            if k == 1 {
+                tables.inv[i] = j as u8;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    return tables;
+}
+
+// Field implementation using exponent and log tables, for the given polynomial (including its x^8
+// bit) and generator. The tables are computed at compile time (which also checks that the
+// polynomial and generator are valid), so the field itself is free to create and copy.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct GenericExpLogField<const POLYNOMIAL: u16, const GEN: u8>;
+
+impl<const POLYNOMIAL: u16, const GEN: u8> GenericExpLogField<POLYNOMIAL, GEN> {
+    const TABLES: &'static ExpLogTables = &exp_log_tables(POLYNOMIAL, GEN);
+}
+
+// The field used by default: the AES polynomial with 0x03 as the generator.
+pub type ExpLogField = GenericExpLogField<AES_POLYNOMIAL, GENERATOR>;
+
+impl<const POLYNOMIAL: u16, const GEN: u8> Field256 for GenericExpLogField<POLYNOMIAL, GEN> {
     fn mul(&self, x: u8, y: u8) -> u8 {
         if x == 0 || y == 0 {
             return 0;
         }
-        let logx: i16 = self.log[x as usize] as i16;
-        let logy: i16 = self.log[y as usize] as i16;
-        return self.exp[(logx + logy) as usize];
+        let logx: i16 = Self::TABLES.log[x as usize] as i16;
+        let logy: i16 = Self::TABLES.log[y as usize] as i16;
+        return Self::TABLES.exp[(logx + logy) as usize];
     }
 
     fn div(&self, x: u8, y: u8) -> u8 {
@@ -164,16 +501,16 @@ impl Field256 for ExpLogField {
         } else if y == 0 {
             panic!("Cannot divide by zero!");
         }
-        let logx: i16 = self.log[x as usize] as i16;
-        let logy: i16 = self.log[y as usize] as i16;
-        return self.exp[(logx - logy + 255) as usize];
+        let logx: i16 = Self::TABLES.log[x as usize] as i16;
+        let logy: i16 = Self::TABLES.log[y as usize] as i16;
+        return Self::TABLES.exp[(logx - logy + 255) as usize];
     }
 
     fn inv(&self, x: u8) -> u8 {
         if x == 0 {
             return 0;
         }
-        return self.exp[255 - self.log[x as usize] as usize];
+        return Self::TABLES.exp[255 - Self::TABLES.log[x as usize] as usize];
     }
 
     fn exp(&self, x: u8, y: u8) -> u8 {
@@ -183,47 +520,172 @@ impl Field256 for ExpLogField {
         if x == 0 {
             return 0;
         }
-        let logx: u16 = self.log[x as usize] as u16;
-        let logy: u16 = self.log[y as usize] as u16;
-        return self.exp[((logx * logy) % 256) as usize];
+        let logx: u16 = Self::TABLES.log[x as usize] as u16;
+        let logy: u16 = Self::TABLES.log[y as usize] as u16;
+        return Self::TABLES.exp[((logx * logy) % 256) as usize];
+    }
+
+    fn mul_add_region(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        region::mul_add_region(self, c, src, dst);
     }
 }
 
-pub struct TableField {
-    inv: [u8; 256],
-    mul: [[u8; 256]; 256],
+// Field implementation using a full multiplication table for the given polynomial, computed at
+// compile time like GenericExpLogField's.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct GenericTableField<const POLYNOMIAL: u16>;
+
+impl<const POLYNOMIAL: u16> GenericTableField<POLYNOMIAL> {
+    const TABLES: &'static MulTables = &mul_tables(POLYNOMIAL);
+}
+
+pub type TableField = GenericTableField<AES_POLYNOMIAL>;
+
+impl<const POLYNOMIAL: u16> Field256 for GenericTableField<POLYNOMIAL> {
+    fn mul(&self, x: u8, y: u8) -> u8 {
+        if x == 0 || y == 0 {
+            return 0;
+        }
+        return Self::TABLES.mul[x as usize][y as usize];
+    }
+
+    fn div(&self, x: u8, y: u8) -> u8 {
+        if x == 0 {
+            return 0;
+        } else if y == 0 {
+            panic!("Cannot divide by zero!");
+        }
+        return Self::TABLES.mul[x as usize][Self::TABLES.inv[y as usize] as usize];
+    }
+
+    fn inv(&self, x: u8) -> u8 {
+        if x == 0 {
+            return 0;
+        }
+        return Self::TABLES.inv[x as usize];
+    }
+
+    fn mul_add_region(&self, c: u8, src: &[u8], dst: &mut [u8]) {
+        region::mul_add_region(self, c, src, dst);
+    }
+}
+
+// The polynomial x^5 + x^3 + 1 used by bech32 (and thus codex32), without the leading bit.
+const IRREDUCIBLE_32: u8 = 0b01001;
+
+// A finite field with 32 elements, GF(2^5). This is the same as `Field256` except that elements are
+// polynomials of length 5, represented by the low 5 bits of a u8. It is used for codex32 (BIP-93)
+// shares, where each bech32 character is an element of the field.
+pub trait Field32 {
+    // Additive identity.
+    fn zero() -> u8 {
+        return 0;
+    }
+    // Multiplicative identity.
+    fn one() -> u8 {
+        return 1;
+    }
+
+    // As with GF(2^8), addition and subtraction are both XOR and every element is its own
+    // additive inverse.
+    fn add(x: u8, y: u8) -> u8 {
+        return x ^ y;
+    }
+    fn sub(x: u8, y: u8) -> u8 {
+        return x ^ y;
+    }
+    fn neg(x: u8) -> u8 {
+        return x;
+    }
+
+    // May use self to speed up the computation.
+    fn mul(&self, x: u8, y: u8) -> u8;
+    fn div(&self, x: u8, y: u8) -> u8 {
+        return self.mul(x, self.inv(y));
+    }
+
+    // Returns x ^ y, by repeated squaring, so that a row of a Vandermonde matrix takes O(n log n)
+    // multiplications rather than O(n^2). May use self to speed up the computation.
+    fn exp(&self, x: u8, y: u8) -> u8 {
+        let mut result = Self::one();
+        let mut square = x;
+        let mut e = y;
+        while e > 0 {
+            if e & 1 == 1 {
+                result = self.mul(result, square);
+            }
+            square = self.mul(square, square);
+            e >>= 1;
+        }
+        return result;
+    }
+
+    // Returns the multiplicative inverse of an element by brute-force search of the 31 non-zero
+    // elements.
+    fn inv(&self, x: u8) -> u8 {
+        for i in 1..32 {
+            if self.mul(i, x) == Self::one() {
+                return i;
+            }
+        }
+        assert!(false, "No multiplicative inv for {:?}", x);
+        return Self::zero();
+    }
 }
 
-impl default::Default for TableField {
+// GF(2^5) implementation that does computations directly.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct DirectField32;
+
+impl Field32 for DirectField32 {
+    fn mul(&self, x: u8, y: u8) -> u8 {
+        let mut result = Self::zero();
+        let mut a = x;
+        let mut b = y;
+        // "Russian peasant" multiplication, as in DirectField but reducing on the fifth bit.
+        for _ in 0..5 {
+            result ^= (b & 1).wrapping_neg() & a;
+            b >>= 1;
+            a = ((a << 1) & 0b11111) ^ (((a & 0b10000) >> 4).wrapping_neg() & IRREDUCIBLE_32);
+        }
+        return result;
+    }
+}
+
+// GF(2^5) implementation using exponent and log tables. Because 31 is prime, every element other
+// than 0 and 1 is a generator; we use x.
+pub struct ExpLogField32 {
+    exp: [u8; 62],
+    log: [u8; 32],
+}
+
+impl default::Default for ExpLogField32 {
     fn default() -> Self {
-        // TODO: Consider using e.g. lazy_static! to initialize the tables once and have all
-        // implementations refer to them.
-        let direct = DirectField::default();
+        let direct = DirectField32::default();
+        let mut x = Self::one();
         let mut res = Self {
-            inv: [0; 256],
-            mul: [[0; 256]; 256],
+            exp: [0; 62],
+            log: [0; 32],
         };
-        // Calculate the multiplication table and inverses at the same time.
-        for i in 0..=255 {
-            for j in 0..=255 {
-                let k = direct.mul(i, j);
-                res.mul[i as usize][j as usize] = k;
-                if k == 1 {
-                    res.inv[i as usize] = j
-                }
-            }
+        for i in 0..31 {
+            res.exp[i] = x;
+            res.exp[i + 31] = x;
+            res.log[x as usize] = i as u8;
+            x = direct.mul(x, 0b10);
         }
 
         return res;
     }
 }
 
-impl Field256 for TableField {
+impl Field32 for ExpLogField32 {
     fn mul(&self, x: u8, y: u8) -> u8 {
         if x == 0 || y == 0 {
             return 0;
         }
-        return self.mul[x as usize][y as usize];
+        let logx = self.log[x as usize] as usize;
+        let logy = self.log[y as usize] as usize;
+        return self.exp[logx + logy];
     }
 
     fn div(&self, x: u8, y: u8) -> u8 {
@@ -232,14 +694,16 @@ impl Field256 for TableField {
         } else if y == 0 {
             panic!("Cannot divide by zero!");
         }
-        return self.mul[x as usize][self.inv[y as usize] as usize];
+        let logx = self.log[x as usize] as usize;
+        let logy = self.log[y as usize] as usize;
+        return self.exp[logx + 31 - logy];
     }
 
     fn inv(&self, x: u8) -> u8 {
         if x == 0 {
             return 0;
         }
-        return self.inv[x as usize];
+        return self.exp[31 - self.log[x as usize] as usize];
     }
 }
 
@@ -283,6 +747,7 @@ mod tests {
         one_multiplicative_identity_for::<DirectField>();
         one_multiplicative_identity_for::<ExpLogField>();
         one_multiplicative_identity_for::<TableField>();
+        one_multiplicative_identity_for::<ClmulField>();
     }
 
     fn mul_commutative_for<T: Field256 + Default>() {
@@ -299,6 +764,7 @@ mod tests {
         mul_commutative_for::<DirectField>();
         mul_commutative_for::<ExpLogField>();
         mul_commutative_for::<TableField>();
+        mul_commutative_for::<ClmulField>();
     }
 
     fn inv_closed_for<T: Field256 + Default>() {
@@ -313,6 +779,7 @@ mod tests {
         inv_closed_for::<DirectField>();
         inv_closed_for::<ExpLogField>();
         inv_closed_for::<TableField>();
+        inv_closed_for::<ClmulField>();
     }
 
     fn inv_identity_for<T: Field256 + Default>() {
@@ -327,6 +794,7 @@ mod tests {
         inv_identity_for::<DirectField>();
         inv_identity_for::<ExpLogField>();
         inv_identity_for::<TableField>();
+        inv_identity_for::<ClmulField>();
     }
 
     fn mul_generator_for<T: Field256 + Default>() {
@@ -348,6 +816,7 @@ mod tests {
         mul_generator_for::<DirectField>();
         mul_generator_for::<ExpLogField>();
         mul_generator_for::<TableField>();
+        mul_generator_for::<ClmulField>();
     }
 
     fn mul_div_inverse_for<T: Field256 + Default>() {
@@ -391,4 +860,185 @@ mod tests {
     fn mul_div_inverse_table_field() {
         mul_div_inverse_for::<TableField>();
     }
+
+    #[test]
+    fn mul_div_inverse_clmul_field() {
+        mul_div_inverse_for::<ClmulField>();
+    }
+
+    #[test]
+    fn clmul_field_agrees_with_direct_field() {
+        let direct = DirectField::default();
+        for clmul in &[ClmulField::default(), ClmulField::portable()] {
+            for i in 0..=255 {
+                for j in 0..=255 {
+                    assert_eq!(clmul.mul(i, j), direct.mul(i, j));
+                }
+            }
+            assert_eq!(clmul.inv(0), 0);
+        }
+    }
+
+    fn mul_add_region_for<T: Field256 + Default>() {
+        let field = T::default();
+        let direct = DirectField::default();
+        let src: Vec<u8> = (0..=255).collect();
+        let dst: Vec<u8> = (0..=255).rev().collect();
+        for c in 0..=255 {
+            let mut out = dst.clone();
+            field.mul_add_region(c, &src[..255], &mut out[..255]);
+            for i in 0..255 {
+                assert_eq!(out[i], dst[i] ^ direct.mul(c, src[i]));
+            }
+            assert_eq!(out[255], dst[255]);
+        }
+    }
+
+    #[test]
+    fn mul_add_region() {
+        mul_add_region_for::<DirectField>();
+        mul_add_region_for::<ExpLogField>();
+        mul_add_region_for::<TableField>();
+        mul_add_region_for::<ClmulField>();
+    }
+
+    #[test]
+    fn mul_slice_agrees_with_mul() {
+        let direct = DirectField::default();
+        let src: Vec<u8> = (0..=255).collect();
+        // Not a multiple of four, to cover the last partial group.
+        let src = &src[..255];
+        for clmul in &[ClmulField::default(), ClmulField::portable()] {
+            for c in 0..=255 {
+                let mut dst = vec![0; src.len()];
+                let mut expected = vec![0; src.len()];
+                clmul.mul_slice(c, src, &mut dst);
+                direct.mul_slice(c, src, &mut expected);
+                assert_eq!(dst, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn irreducible_polynomials() {
+        assert!(is_irreducible(AES_POLYNOMIAL));
+        assert!(is_irreducible(STORAGE_POLYNOMIAL));
+        // x^8 + 1 == (x + 1)^8
+        assert!(!is_irreducible(0b100000001));
+        // (x^4 + x + 1)^2
+        assert!(!is_irreducible(0b100010001 ^ 0b100));
+        // Not of degree 8.
+        assert!(!is_irreducible(0b10011));
+    }
+
+    // The polynomial x^8 + x^4 + x^3 + x^2 + 1, which most Reed-Solomon storage libraries use (with
+    // x as the generator).
+    const STORAGE_POLYNOMIAL: u16 = 0b100011101;
+
+    #[test]
+    fn primitive_generators() {
+        assert!(is_primitive(AES_POLYNOMIAL, GENERATOR));
+        assert!(!is_primitive(AES_POLYNOMIAL, 0b10));
+        assert!(is_primitive(STORAGE_POLYNOMIAL, 0b10));
+        assert!(!is_primitive(STORAGE_POLYNOMIAL, 0));
+        assert!(!is_primitive(STORAGE_POLYNOMIAL, 1));
+    }
+
+    type StorageExpLogField = GenericExpLogField<STORAGE_POLYNOMIAL, 0b10>;
+    type StorageTableField = GenericTableField<STORAGE_POLYNOMIAL>;
+
+    #[test]
+    fn storage_polynomial_fields() {
+        mul_commutative_for::<StorageExpLogField>();
+        inv_identity_for::<StorageExpLogField>();
+        inv_identity_for::<StorageTableField>();
+        mul_div_inverse_for::<StorageExpLogField>();
+        mul_div_inverse_for::<StorageTableField>();
+        let exp_log = StorageExpLogField::default();
+        let table = StorageTableField::default();
+        for i in 0..=255 {
+            for j in 0..=255 {
+                assert_eq!(exp_log.mul(i, j), mul_mod(STORAGE_POLYNOMIAL, i, j));
+                assert_eq!(table.mul(i, j), mul_mod(STORAGE_POLYNOMIAL, i, j));
+            }
+        }
+        // 2 * 0x80 overflows and is reduced by the polynomial.
+        assert_eq!(table.mul(2, 0x80), 0x1d);
+    }
+
+    #[test]
+    fn storage_polynomial_branchless_fields() {
+        type StorageDirectField = GenericDirectField<STORAGE_POLYNOMIAL>;
+        type StorageClmulField = GenericClmulField<STORAGE_POLYNOMIAL>;
+        mul_div_inverse_for::<StorageDirectField>();
+        mul_div_inverse_for::<StorageClmulField>();
+        let direct = StorageDirectField::default();
+        for clmul in &[StorageClmulField::default(), StorageClmulField::portable()] {
+            for i in 0..=255 {
+                for j in 0..=255 {
+                    assert_eq!(direct.mul(i, j), mul_mod(STORAGE_POLYNOMIAL, i, j));
+                    assert_eq!(clmul.mul(i, j), mul_mod(STORAGE_POLYNOMIAL, i, j));
+                }
+            }
+        }
+        // A polynomial whose bits below x^8 have degree 7 needs the most rounds to reduce.
+        const HIGH_POLYNOMIAL: u16 = 0b111000011;
+        assert!(is_irreducible(HIGH_POLYNOMIAL));
+        assert_eq!(reduction_rounds(AES_POLYNOMIAL), 2);
+        assert_eq!(reduction_rounds(HIGH_POLYNOMIAL), 7);
+        let clmul = GenericClmulField::<HIGH_POLYNOMIAL>::portable();
+        for i in 0..=255 {
+            for j in 0..=255 {
+                assert_eq!(clmul.mul(i, j), mul_mod(HIGH_POLYNOMIAL, i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn aes_tables_agree_with_direct_field() {
+        let direct = DirectField::default();
+        let exp_log = ExpLogField::default();
+        let table = TableField::default();
+        for i in 0..=255 {
+            for j in 0..=255 {
+                assert_eq!(exp_log.mul(i, j), direct.mul(i, j));
+                assert_eq!(table.mul(i, j), direct.mul(i, j));
+            }
+        }
+    }
+
+    fn field32_axioms_for<T: Field32 + Default>() {
+        let field = T::default();
+        for i in 0..32 {
+            assert_eq!(field.mul(T::one(), i), i);
+            assert_eq!(field.mul(i, T::zero()), T::zero());
+            for j in 0..32 {
+                assert!(field.mul(i, j) < 32);
+                assert_eq!(field.mul(i, j), field.mul(j, i));
+            }
+        }
+        for i in 1..32 {
+            assert_eq!(field.mul(i, field.inv(i)), T::one());
+            for j in 1..32 {
+                assert_eq!(field.div(field.mul(i, j), j), i);
+            }
+        }
+    }
+
+    #[test]
+    fn field32_axioms() {
+        field32_axioms_for::<DirectField32>();
+        field32_axioms_for::<ExpLogField32>();
+    }
+
+    #[test]
+    fn field32_implementations_agree() {
+        let direct = DirectField32::default();
+        let exp_log = ExpLogField32::default();
+        for i in 0..32 {
+            for j in 0..32 {
+                assert_eq!(direct.mul(i, j), exp_log.mul(i, j));
+            }
+        }
+    }
 }
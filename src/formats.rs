@@ -0,0 +1,309 @@
+// Textual representations of secrets, for reading a secret to split and for printing a restored
+// one.
+
+use crate::bip39;
+use crate::words;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SecretFormat {
+    // Words from the built in 256 word list, one per byte.
+    Words,
+    // A BIP-39 mnemonic, for which the secret is the mnemonic's entropy.
+    Bip39,
+    // Lower case hexadecimal.
+    Hex,
+    // Standard base64 (RFC 4648) with padding.
+    Base64,
+    // Base58 with the Bitcoin alphabet (without a checksum).
+    Base58,
+    // The bytes themselves. These cannot be printed, so they are written to (or read from) a
+    // file instead.
+    Raw,
+}
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn to_hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() % 2 != 0 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(String::from(
+            "Hex secrets must have an even number of hexadecimal digits.",
+        ));
+    }
+    return Ok((0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect());
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    return s;
+}
+
+fn from_base64(s: &str) -> Result<Vec<u8>, String> {
+    let trimmed = s.trim_end_matches('=');
+    if s.len() % 4 != 0 || s.len() - trimmed.len() > 2 {
+        return Err(String::from("Invalid base64 length or padding."));
+    }
+    let mut bytes = Vec::with_capacity(s.len() / 4 * 3);
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+    for c in trimmed.bytes() {
+        let value = match BASE64_ALPHABET.iter().position(|x| *x == c) {
+            Some(value) => value as u32,
+            None => return Err(format!("Invalid base64 character {:?}.", c as char)),
+        };
+        acc = (acc << 6) | value;
+        acc_bits += 6;
+        if acc_bits >= 8 {
+            acc_bits -= 8;
+            bytes.push((acc >> acc_bits) as u8);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+    // The bits left over from the last character must be zero, so that every secret has exactly
+    // one encoding.
+    if acc != 0 {
+        return Err(String::from(
+            "Invalid base64, the trailing bits are not zero.",
+        ));
+    }
+    return Ok(bytes);
+}
+
+// Base58 treats the bytes as a big endian number, with each leading zero byte written as '1'.
+fn to_base58(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    // Little endian base58 digits of the number.
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for byte in &bytes[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    return std::iter::repeat('1')
+        .take(zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|d| BASE58_ALPHABET[*d as usize] as char),
+        )
+        .collect();
+}
+
+fn from_base58(s: &str) -> Result<Vec<u8>, String> {
+    let zeros = s.chars().take_while(|c| *c == '1').count();
+    // Little endian bytes of the number.
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len());
+    for c in s[zeros..].bytes() {
+        let mut carry = match BASE58_ALPHABET.iter().position(|x| *x == c) {
+            Some(value) => value as u32,
+            None => return Err(format!("Invalid base58 character {:?}.", c as char)),
+        };
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    return Ok(std::iter::repeat(0)
+        .take(zeros)
+        .chain(bytes.into_iter().rev())
+        .collect());
+}
+
+impl FromStr for SecretFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SecretFormat, Self::Err> {
+        match s {
+            "words" => Ok(SecretFormat::Words),
+            "bip39" => Ok(SecretFormat::Bip39),
+            "hex" => Ok(SecretFormat::Hex),
+            "base64" => Ok(SecretFormat::Base64),
+            "base58" => Ok(SecretFormat::Base58),
+            "raw" => Ok(SecretFormat::Raw),
+            _ => Err(format!(
+                "Unknown secret format {:?}, expected one of ['words', 'bip39', 'hex', 'base64', 'base58', 'raw']",
+                s
+            )),
+        }
+    }
+}
+
+impl SecretFormat {
+    // Formats the secret as text. Raw secrets are not text, so they must be handled by the caller
+    // (as must parsing them in decode).
+    pub fn encode(self: &Self, secret: &[u8]) -> Result<String, String> {
+        match self {
+            SecretFormat::Words => {
+                let wordlist = words::load_static_word_list();
+                let wordlist: Vec<&str> = wordlist.iter().map(|w| w.as_str()).collect();
+                return Ok(words::to_words(secret.iter().cloned(), &wordlist).join(" "));
+            }
+            SecretFormat::Bip39 => return bip39::entropy_to_mnemonic(secret),
+            SecretFormat::Hex => return Ok(to_hex(secret)),
+            SecretFormat::Base64 => return Ok(to_base64(secret)),
+            SecretFormat::Base58 => return Ok(to_base58(secret)),
+            SecretFormat::Raw => return Err(String::from("Raw secrets cannot be printed.")),
+        }
+    }
+
+    pub fn decode(self: &Self, s: &str) -> Result<Vec<u8>, String> {
+        match self {
+            SecretFormat::Words => {
+                let wordlist = words::load_static_word_list();
+                let wordmap = words::words_map(&wordlist[..]);
+                let mut secret = Vec::new();
+                for word in s.split_whitespace() {
+                    match wordmap.get(word) {
+                        Some(b) => secret.push(*b),
+                        None => return Err(format!("Unknown word {:?}.", word)),
+                    }
+                }
+                return Ok(secret);
+            }
+            SecretFormat::Bip39 => return bip39::mnemonic_to_entropy(s),
+            SecretFormat::Hex => return from_hex(s.trim()),
+            SecretFormat::Base64 => return from_base64(s.trim()),
+            SecretFormat::Base58 => return from_base58(s.trim()),
+            SecretFormat::Raw => return Err(String::from("Raw secrets cannot be parsed.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let secret: Vec<u8> = (0..32).map(|i| i * 7).collect();
+        for format in &[
+            SecretFormat::Words,
+            SecretFormat::Bip39,
+            SecretFormat::Hex,
+            SecretFormat::Base64,
+            SecretFormat::Base58,
+        ] {
+            let encoded = format.encode(&secret).unwrap();
+            assert_eq!(format.decode(&encoded).unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn round_trip_lengths() {
+        for length in 0..10 {
+            let secret: Vec<u8> = (0..length).map(|i: u32| (i * 100) as u8).collect();
+            for format in &[
+                SecretFormat::Hex,
+                SecretFormat::Base64,
+                SecretFormat::Base58,
+            ] {
+                let encoded = format.encode(&secret).unwrap();
+                assert_eq!(format.decode(&encoded).unwrap(), secret);
+            }
+        }
+    }
+
+    #[test]
+    fn base64_vectors() {
+        // From RFC 4648.
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors.iter() {
+            assert_eq!(
+                SecretFormat::Base64.encode(plain.as_bytes()).unwrap(),
+                *encoded
+            );
+            assert_eq!(
+                SecretFormat::Base64.decode(encoded).unwrap(),
+                plain.as_bytes()
+            );
+        }
+        assert!(SecretFormat::Base64.decode("Zm9").is_err());
+        assert!(SecretFormat::Base64.decode("Zm9!").is_err());
+        // "Zh==" and "Zm9vYh==" differ from "Zg==" and "Zm9vYg==" only in the unused bits.
+        assert!(SecretFormat::Base64.decode("Zh==").is_err());
+        assert!(SecretFormat::Base64.decode("Zm9vYh==").is_err());
+        assert!(SecretFormat::Base64.decode("Zm9=").is_err());
+    }
+
+    #[test]
+    fn base58_vectors() {
+        let vectors: [(&[u8], &str); 4] = [
+            (b"", ""),
+            (&[0, 0, 0x28, 0x7f, 0xb4, 0xcd], "11233QC4"),
+            (b"Hello World!", "2NEpo7TZRRrLZSi2U"),
+            (
+                b"The quick brown fox jumps over the lazy dog.",
+                "USm3fpXnKG5EUBx2ndxBDMPVciP5hGey2Jh4NDv6gmeo1LkMeiKrLJUUBk6Z",
+            ),
+        ];
+        for (plain, encoded) in vectors.iter() {
+            assert_eq!(SecretFormat::Base58.encode(plain).unwrap(), *encoded);
+            assert_eq!(SecretFormat::Base58.decode(encoded).unwrap(), *plain);
+        }
+        assert!(SecretFormat::Base58.decode("0OIl").is_err());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(
+            SecretFormat::Hex.encode(&[0xde, 0xad, 0x01]).unwrap(),
+            "dead01"
+        );
+        assert_eq!(
+            SecretFormat::Hex.decode("0xDEAD01").unwrap(),
+            vec![0xde, 0xad, 0x01]
+        );
+        assert!(SecretFormat::Hex.decode("dea").is_err());
+        assert!(SecretFormat::Hex.decode("0x0xdead01").is_err());
+    }
+
+    #[test]
+    fn words_unknown_word() {
+        assert!(SecretFormat::Words.decode("acid notaword").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+// A small least recently used cache. It is meant for a handful of expensive values (like decoding
+// matrices), so entries are kept in a Vec from the most to the least recently used and looked up
+// linearly.
+
+pub struct Lru<K: PartialEq, V> {
+    capacity: usize,
+    entries: Vec<(K, V)>,
+}
+
+impl<K: PartialEq, V> Lru<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        return Lru {
+            capacity: capacity,
+            entries: Vec::with_capacity(capacity),
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    // The value for the key, which becomes the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index);
+        self.entries.insert(0, entry);
+        return Some(&self.entries[0].1);
+    }
+
+    // Adds the value as the most recently used, evicting the least recently used if the cache is
+    // full.
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(index) = self.entries.iter().position(|(k, _)| *k == key) {
+            self.entries.remove(index);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.truncate(self.capacity - 1);
+        self.entries.insert(0, (key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        assert_eq!(lru.get(&1), Some(&"a"));
+        // 2 is now the least recently used.
+        lru.insert(3, "c");
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.get(&2), None);
+        assert_eq!(lru.get(&1), Some(&"a"));
+        assert_eq!(lru.get(&3), Some(&"c"));
+        // Inserting a key again replaces its value.
+        lru.insert(3, "d");
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.get(&3), Some(&"d"));
+    }
+
+    #[test]
+    fn zero_capacity_caches_nothing() {
+        let mut lru = Lru::new(0);
+        lru.insert(1, "a");
+        assert_eq!(lru.len(), 0);
+        assert_eq!(lru.get(&1), None);
+    }
+}
@@ -1,19 +1,47 @@
 #![feature(test)]
 
+// Only used by AdditiveFftEncoder, which the CLI doesn't construct.
+#[allow(dead_code)]
+mod additive_fft;
+mod bip39;
+// Only used by BitslicedEncoder, which the CLI doesn't construct.
+#[allow(dead_code)]
+mod bitslice;
 mod chunker;
+mod codex32;
 mod encoder;
 mod encoding;
+mod entropy;
+mod field;
 mod finite_field;
+mod formats;
+mod lru;
 mod matrix;
 mod polynomial;
+mod prime_field;
+mod region;
+mod secret;
 mod shamir;
+mod slip39;
 mod words;
+// Only used by CauchyBitmatrixEncoder, which the CLI doesn't construct.
+#[allow(dead_code)]
+mod xor_schedule;
 
+use crate::encoding::Encoding;
+use crate::entropy::EntropySource;
+use crate::formats::SecretFormat;
+use crate::secret::{zeroize_string, Phrase, Secret};
 use crate::shamir::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::convert::TryFrom;
 use std::env::args;
 use std::io;
 use std::io::Write;
 use std::iter::FromIterator;
+use std::str::FromStr;
 
 enum ExitCode {
     Success = 0,
@@ -21,6 +49,8 @@ enum ExitCode {
     WrongCommand,
     WrongShards,
     UnrecognizedArgument,
+    InvalidArgument,
+    RestoreFailed,
 }
 
 fn exit(code: ExitCode) -> ! {
@@ -50,11 +80,62 @@ fn main() {
     exit(ExitCode::Success);
 }
 
+// How shards are represented.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    // Phrases of words from the built in 256 word list, see `shamir`.
+    Words,
+    // SLIP-0039 mnemonics, see `slip39`.
+    Slip39,
+    // codex32 (BIP-93) strings, see `codex32`.
+    Codex32,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, Self::Err> {
+        match s {
+            "words" => Ok(Format::Words),
+            "slip39" => Ok(Format::Slip39),
+            "codex32" => Ok(Format::Codex32),
+            _ => Err(format!(
+                "Unknown format {:?}, expected one of ['words', 'slip39', 'codex32']",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct Options {
     total: Option<usize>,
     required: Option<usize>,
     words: Option<usize>,
+    // Generate a random secret with at least this many bits of entropy, instead of using --words
+    // or the format's default length.
+    bits: Option<usize>,
+    // Where the randomness for a generated secret comes from.
+    entropy: Option<EntropySource>,
+    // Derive all randomness from this seed instead of the OS, for tests and reproducing bugs.
+    seed: Option<u64>,
+    format: Option<Format>,
+    // The encoder shards are split with. It is recorded in the shards, so restoring only checks it.
+    encoder: Option<ShardEncoder>,
+    // The field shards are split over, by default the smallest binary field with enough points. A
+    // prime field splits the secret as a single scalar of it, such as a private key for its curve.
+    field: Option<ShardField>,
+    passphrase: Option<String>,
+    // SLIP-39 groups, as a comma separated list of "required/total" member counts.
+    groups: Option<Vec<slip39::Group>>,
+    group_threshold: Option<usize>,
+    // When generating, split an existing secret (read from stdin) in this format instead of a
+    // random one.
+    from: Option<SecretFormat>,
+    // How to print the secret, both when restoring and when generating.
+    secret_format: Option<SecretFormat>,
+    // The file raw secrets are read from or written to.
+    secret_file: Option<String>,
 }
 
 impl Default for Options {
@@ -63,10 +144,40 @@ impl Default for Options {
             total: None,
             required: None,
             words: None,
+            bits: None,
+            entropy: None,
+            seed: None,
+            format: None,
+            encoder: None,
+            field: None,
+            passphrase: None,
+            groups: None,
+            group_threshold: None,
+            from: None,
+            secret_format: None,
+            secret_file: None,
         }
     }
 }
 
+// Parses SLIP-39 groups of the form "2/3,3/5".
+fn parse_groups(s: &str) -> Result<Vec<slip39::Group>, String> {
+    return s
+        .split(",")
+        .map(|group| {
+            let parts: Vec<Result<u8, _>> =
+                group.trim().split("/").map(|x| x.parse::<u8>()).collect();
+            match parts[..] {
+                [Ok(threshold), Ok(count)] => Ok(slip39::Group { threshold, count }),
+                _ => Err(format!(
+                    "Groups must be of the form required/total, got {:?}",
+                    group
+                )),
+            }
+        })
+        .collect();
+}
+
 fn parse_options(args: &Vec<String>) -> Options {
     let mut options: Options = Default::default();
     let mut index = 0;
@@ -96,6 +207,82 @@ fn parse_options(args: &Vec<String>) -> Options {
                         .expect("Could not parse the --words option"),
                 );
             }
+            "--bits" => {
+                options.bits = Some(
+                    args[index + 1]
+                        .parse::<usize>()
+                        .expect("Could not parse the --bits option"),
+                );
+            }
+            "--entropy" => match args[index + 1].parse::<EntropySource>() {
+                Ok(source) => options.entropy = Some(source),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--seed" => {
+                options.seed = Some(
+                    args[index + 1]
+                        .parse::<u64>()
+                        .expect("Could not parse the --seed option"),
+                );
+            }
+            "--format" => match args[index + 1].parse::<Format>() {
+                Ok(format) => options.format = Some(format),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--encoder" => match args[index + 1].parse::<ShardEncoder>() {
+                Ok(encoder) => options.encoder = Some(encoder),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--field" => match args[index + 1].parse::<ShardField>() {
+                Ok(field) => options.field = Some(field),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--passphrase" => {
+                options.passphrase = Some(args[index + 1].clone());
+            }
+            "--groups" => match parse_groups(&args[index + 1]) {
+                Ok(groups) => options.groups = Some(groups),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--from" => match args[index + 1].parse::<SecretFormat>() {
+                Ok(format) => options.from = Some(format),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--secret-format" => match args[index + 1].parse::<SecretFormat>() {
+                Ok(format) => options.secret_format = Some(format),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            },
+            "--secret-file" => {
+                options.secret_file = Some(args[index + 1].clone());
+            }
+            "--group-threshold" => {
+                options.group_threshold = Some(
+                    args[index + 1]
+                        .parse::<usize>()
+                        .expect("Could not parse the --group-threshold option"),
+                );
+            }
             _ => {
                 println!("Unrecognized argument {}", args[index]);
                 exit(ExitCode::UnrecognizedArgument);
@@ -103,7 +290,50 @@ fn parse_options(args: &Vec<String>) -> Options {
         }
         index += 2;
     }
-    if options.total.is_some() && options.required.is_some() && options.total <= options.required {
+    if (options.from == Some(SecretFormat::Raw) || options.secret_format == Some(SecretFormat::Raw))
+        && options.secret_file.is_none()
+    {
+        println!("Raw secrets need a --secret-file to read from or write to.");
+        exit(ExitCode::InvalidArgument);
+    }
+    if options.bits.is_some() && (options.words.is_some() || options.from.is_some()) {
+        println!("--bits cannot be combined with --words or --from.");
+        exit(ExitCode::InvalidArgument);
+    }
+    if options.entropy.is_some() && options.from.is_some() {
+        println!("--entropy cannot be combined with --from.");
+        exit(ExitCode::InvalidArgument);
+    }
+    if options.entropy == Some(EntropySource::DiceOnly) && options.seed.is_some() {
+        println!("--entropy dice-only cannot be combined with --seed.");
+        exit(ExitCode::InvalidArgument);
+    }
+    if options.encoder.is_some() && options.format.unwrap_or(Format::Words) != Format::Words {
+        println!("--encoder only applies to the words format.");
+        exit(ExitCode::InvalidArgument);
+    }
+    if options.field.is_some() && options.format.unwrap_or(Format::Words) != Format::Words {
+        println!("--field only applies to the words format.");
+        exit(ExitCode::InvalidArgument);
+    }
+    if let Some(field) = options.field.filter(|field| field.scalar_bytes().is_some()) {
+        if options.words.is_some() || options.bits.is_some() {
+            println!("--words and --bits cannot be combined with --field {:?}, the secret is one of its scalars.", field);
+            exit(ExitCode::InvalidArgument);
+        }
+        if options.encoder.unwrap_or(ShardEncoder::Lagrange) != ShardEncoder::Lagrange {
+            println!(
+                "--field {:?} shards can only be split with the Lagrange encoder.",
+                field
+            );
+            exit(ExitCode::InvalidArgument);
+        }
+    }
+    if options.format.unwrap_or(Format::Words) == Format::Words
+        && options.total.is_some()
+        && options.required.is_some()
+        && options.total <= options.required
+    {
         println!("Total shards must be larger than required shards (the secret uses a shard).");
         println!("Options: {:?}", options);
         exit(ExitCode::WrongShards);
@@ -113,6 +343,56 @@ fn parse_options(args: &Vec<String>) -> Options {
 }
 
 fn generate(options: Options) {
+    match (options.seed, options.entropy) {
+        (Some(seed), _) => {
+            println!(
+                "WARNING: --seed is for debugging only, the secret and shards are predictable."
+            );
+            generate_with_rng(options, &mut ChaCha20Rng::seed_from_u64(seed))
+        }
+        (None, Some(EntropySource::DiceOnly)) => {
+            let mut rng = dice_rng(&options);
+            generate_with_rng(options, &mut rng)
+        }
+        (None, _) => generate_with_rng(options, &mut OsRng),
+    }
+}
+
+// In dice only mode the secret and the shards are all drawn from a generator seeded from the dice,
+// so they ask for enough dice for the secret before anything is generated.
+fn dice_rng(options: &Options) -> ChaCha20Rng {
+    let bits = random_length(options) * 8;
+    let input = prompt_phrase(
+        format!(
+            "Input at least {} groups of 4 dice rolls (1 to 6): ",
+            entropy::dice_groups_for_bits(bits)
+        )
+        .as_str(),
+    )
+    .expect("Could not read the entropy");
+    let rng = entropy::parse_dice(input.expose_str()).and_then(|dice| {
+        println!(
+            "Dice: {} groups, {} bits",
+            dice.len(),
+            entropy::dice_bits(dice.len())
+        );
+        return entropy::dice_rng(bits, &dice);
+    });
+    match rng {
+        Ok(rng) => return rng,
+        Err(e) => {
+            println!("Could not use the entropy: {}", e);
+            exit(ExitCode::InvalidArgument);
+        }
+    }
+}
+
+fn generate_with_rng<R: RngCore + CryptoRng>(options: Options, rng: &mut R) {
+    match options.format {
+        Some(Format::Slip39) => return generate_slip39(options, rng),
+        Some(Format::Codex32) => return generate_codex32(options, rng),
+        _ => {}
+    }
     println!("-- Generating secret and shards... --");
     let required = match options.required {
         None => {
@@ -128,20 +408,80 @@ fn generate(options: Options) {
         }
         Some(total) => total,
     };
-    let words = match options.words {
+    // The shards are over GF(2^8) unless there are more than it has points for, or --field is
+    // given. Scalars can only be interpolated, so prime fields default to the Lagrange encoder.
+    // Restoring shows the secret the way it is shown here.
+    let field = options
+        .field
+        .map_or_else(|| ShardField::for_shards(total), Ok)
+        .unwrap_or_else(|e| {
+            println!("Cannot split the secret: {}", e);
+            exit(ExitCode::InvalidArgument);
+        });
+    let encoder = match field.scalar_bytes() {
+        Some(_) => ShardEncoder::Lagrange,
+        None => ShardEncoder::Vandermonde,
+    };
+    let header = ShardHeader::new(options.encoder.unwrap_or(encoder))
+        .with_format(display_format(&options))
+        .with_field(field);
+    let secret = read_secret(&options);
+    let shards = match &secret {
+        Some(secret) => shamir_secret_with(rng, secret.expose(), total, required, header),
+        None if field.scalar_bytes().is_some() => {
+            let secret = random_scalar(&options, field, rng);
+            shamir_secret_with(rng, secret.expose(), total, required, header)
+        }
         None => {
-            println!("No --words flag, using default of 10.");
-            3
+            if options.words.is_none() && options.bits.is_none() {
+                println!("No --words flag, using default of 10.");
+            }
+            let words = random_length(&options);
+            let per_word = words_encoding(&options)
+                .and_then(|encoding| bits_per_word(words::load_static_word_list().len(), encoding));
+            match per_word {
+                Ok(per_word) => println!(
+                    "Entropy: {} bits ({} words of {} bits)",
+                    words * per_word,
+                    words,
+                    per_word
+                ),
+                Err(e) => {
+                    println!("Cannot generate the secret: {}", e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            }
+            let secret = random_bytes(&options, words, rng);
+            shamir_secret_with(rng, secret.expose(), total, required, header)
+        }
+    };
+    let shards: Vec<Phrase> = match shards {
+        Ok(shards) => shards,
+        Err(e) => {
+            println!("Cannot split the secret: {}", e);
+            exit(ExitCode::InvalidArgument);
         }
-        Some(words) => words,
     };
-    let shards: Vec<String> = shamir(total, required, words);
 
+    println!("Shards: {}, required: {}", total, required);
     for (i, s) in shards.iter().enumerate() {
         if i == 0 {
-            println!("Secret: {}", s);
+            match (&secret, display_format(&options)) {
+                (_, None) => print_sensitive("Secret", s.expose_str()),
+                (Some(secret), format) => {
+                    print_secret(secret.expose(), format, &options.secret_file)
+                }
+                (None, format) => {
+                    let secret = Secret::new(
+                        SecretFormat::Words
+                            .decode(s.expose_str())
+                            .expect("Invalid secret phrase"),
+                    );
+                    print_secret(secret.expose(), format, &options.secret_file)
+                }
+            }
         } else {
-            println!("Shard {}: {}", i, s);
+            print_sensitive(&format!("Shard {}", i), s.expose_str());
         }
     }
 }
@@ -161,7 +501,370 @@ fn prompt(msg: &str) -> io::Result<String> {
     }
 }
 
+// The most bytes a shard, secret or line of entropy typed in can have. Each is read into a buffer
+// of this size, which never grows.
+const MAX_INPUT_BYTES: usize = 16 * 1024;
+
+// Like prompt, but reads the answer into a protected buffer of MAX_INPUT_BYTES, for anything
+// secret: shards, secrets and entropy.
+fn prompt_phrase(msg: &str) -> io::Result<Phrase> {
+    print!("{}", msg);
+    io::stdout().flush()?;
+    let stdin = io::stdin();
+    let mut handle = stdin.lock();
+    match Phrase::read_line(&mut handle, MAX_INPUT_BYTES) {
+        Err(e) => {
+            println!("Could not read line: {}", e);
+            return Err(e);
+        }
+        Ok(phrase) => return Ok(phrase),
+    }
+}
+
+// Prints a secret or a shard after its label, as in "Shard 1: ...". Everything secret that is shown
+// goes through here, written straight from the buffer it is held in.
+fn print_sensitive(label: &str, text: &str) {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    writeln!(handle, "{}: {}", label, text).expect("Could not print the secret");
+}
+
+// The encoding of the shards of the words format, with the defaults of generate.
+fn words_encoding(options: &Options) -> Result<Encoding, String> {
+    let required = options.required.unwrap_or(3);
+    return shard_encoding(options.total.unwrap_or(required + 1), required);
+}
+
+// The length in bytes of the shortest random secret with at least `bits` bits of entropy. It must
+// fit the shard format and, if the secret is shown as a BIP-39 mnemonic, be a valid BIP-39 length.
+fn length_for_bits(bits: usize, options: &Options) -> usize {
+    let length = match options.format.unwrap_or(Format::Words) {
+        Format::Words => words_encoding(options).and_then(|encoding| {
+            words_for_bits(bits, words::load_static_word_list().len(), encoding)
+        }),
+        Format::Slip39 => slip39::secret_length_for_bits(bits),
+        Format::Codex32 => codex32::secret_length_for_bits(bits),
+    }
+    .and_then(|length| match display_format(options) {
+        Some(SecretFormat::Bip39) => bip39::entropy_length_for_bits(length * 8),
+        _ => Ok(length),
+    });
+    match length {
+        Ok(length) => return length,
+        Err(e) => {
+            println!(
+                "Cannot generate a secret with {} bits of entropy: {}",
+                bits, e
+            );
+            exit(ExitCode::InvalidArgument);
+        }
+    }
+}
+
+// The length in bytes of the random secret generate draws: --words (3 by default) or long enough
+// for --bits in the words format, 16 more bytes than a scalar for the prime fields (see
+// random_scalar), and 16 bytes unless --bits is given for SLIP-39 and codex32.
+fn random_length(options: &Options) -> usize {
+    let format = options.format.unwrap_or(Format::Words);
+    if let Some(length) = options.field.and_then(|field| field.scalar_bytes()) {
+        return length + 16;
+    }
+    return match (format, options.words, options.bits) {
+        (Format::Words, Some(words), _) => words,
+        (_, _, Some(bits)) => length_for_bits(bits, options),
+        (Format::Words, None, None) => 3,
+        (_, _, None) => 16,
+    };
+}
+
+// Generates a random secret long enough for --bits, or of the format's default length without it.
+fn random_secret<R: RngCore + CryptoRng>(options: &Options, rng: &mut R) -> Secret {
+    let length = random_length(options);
+    println!("Entropy: {} bits", length * 8);
+    return random_bytes(&options, length, rng);
+}
+
+// Generates a random scalar of a prime field, reduced from 16 more random bytes than it has so
+// that it is close to uniform.
+fn random_scalar<R: RngCore + CryptoRng>(
+    options: &Options,
+    field: ShardField,
+    rng: &mut R,
+) -> Secret {
+    let length = field
+        .scalar_bytes()
+        .expect("Only prime fields have scalars");
+    println!("Entropy: {} bits (a {:?} scalar)", length * 8, field);
+    let bytes = random_bytes(options, random_length(options), rng);
+    return field
+        .reduce_scalar(bytes.expose())
+        .expect("Only prime fields have scalars");
+}
+
+// Generates random bytes from the --entropy source, prompting for any dice rolls or text. In dice
+// only mode rng was seeded from the dice (see dice_rng), so it is used like the OS one.
+fn random_bytes<R: RngCore + CryptoRng>(options: &Options, length: usize, rng: &mut R) -> Secret {
+    let source = options.entropy.unwrap_or(EntropySource::Os);
+    let input = match source {
+        EntropySource::Os | EntropySource::DiceOnly => {
+            return Secret::new(gen_random_bytes(rng, length))
+        }
+        EntropySource::Text => prompt_phrase("Input some random text: "),
+        EntropySource::Dice => prompt_phrase("Input dice rolls (1 to 6) in groups of 4: "),
+    }
+    .expect("Could not read the entropy");
+    let bytes = match source {
+        EntropySource::Text => Ok(entropy::gen_mixed_bytes(
+            rng,
+            length,
+            input.expose_str().trim().as_bytes(),
+        )),
+        _ => entropy::parse_dice(input.expose_str()).and_then(|dice| {
+            println!(
+                "Dice: {} groups, {} bits",
+                dice.len(),
+                entropy::dice_bits(dice.len())
+            );
+            let dice = Secret::new(dice.iter().flat_map(|d| d.to_be_bytes().to_vec()).collect());
+            return Ok(entropy::gen_mixed_bytes(rng, length, dice.expose()));
+        }),
+    };
+    match bytes {
+        Ok(bytes) => return Secret::new(bytes),
+        Err(e) => {
+            println!("Could not use the entropy: {}", e);
+            exit(ExitCode::InvalidArgument);
+        }
+    }
+}
+
+// Reads the secret to split if --from was given, from stdin or, for raw secrets, --secret-file.
+fn read_secret(options: &Options) -> Option<Secret> {
+    let format = options.from?;
+    if format == SecretFormat::Raw {
+        let path = options.secret_file.as_ref().unwrap();
+        match std::fs::read(path) {
+            Ok(secret) => return Some(Secret::new(secret)),
+            Err(e) => {
+                println!("Could not read the secret from {}: {}", path, e);
+                exit(ExitCode::InvalidArgument);
+            }
+        }
+    }
+    let input = prompt_phrase(format!("Input the secret ({:?}): ", format).as_str())
+        .expect("Could not read the secret");
+    match format.decode(input.expose_str().trim()) {
+        Ok(secret) => return Some(Secret::new(secret)),
+        Err(e) => {
+            println!("Could not read the secret: {}", e);
+            exit(ExitCode::InvalidArgument);
+        }
+    }
+}
+
+// How to show the secret when generating: --secret-format if given, otherwise the format it was
+// read in (unless that was a file).
+fn display_format(options: &Options) -> Option<SecretFormat> {
+    if options.secret_format.is_some() {
+        return options.secret_format;
+    }
+    return options.from.filter(|f| *f != SecretFormat::Raw);
+}
+
+// Writes a raw secret to a file which only the current user can read.
+fn write_secret_file(path: &str, secret: &[u8]) -> io::Result<()> {
+    let mut open_options = std::fs::OpenOptions::new();
+    open_options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open_options.mode(0o600);
+    }
+    return open_options.open(path)?.write_all(secret);
+}
+
+// Prints the secret in the given format, or as hex if there is none. Raw secrets are written to
+// --secret-file instead.
+fn print_secret(secret: &[u8], format: Option<SecretFormat>, path: &Option<String>) {
+    match format.unwrap_or(SecretFormat::Hex) {
+        SecretFormat::Raw => {
+            let path = path.as_ref().unwrap();
+            match write_secret_file(path, secret) {
+                Ok(()) => println!("Secret: written to {}", path),
+                Err(e) => {
+                    println!("Could not write the secret to {}: {}", path, e);
+                    exit(ExitCode::InvalidArgument);
+                }
+            }
+        }
+        format => match format.encode(secret) {
+            Ok(mut s) => {
+                print_sensitive("Secret", &s);
+                zeroize_string(&mut s);
+            }
+            Err(e) => {
+                println!("Could not format the secret as {:?}: {}", format, e);
+                exit(ExitCode::InvalidArgument);
+            }
+        },
+    }
+}
+
+// Generates a random master secret (128 bits unless --bits is given) and splits it into SLIP-39
+// mnemonics. Without --groups there is a single group using --required and --total as the member
+// threshold and count.
+fn generate_slip39<R: RngCore + CryptoRng>(options: Options, rng: &mut R) {
+    println!("-- Generating SLIP-39 master secret and shares... --");
+    let master_secret = read_secret(&options).unwrap_or_else(|| random_secret(&options, rng));
+    let groups = match &options.groups {
+        Some(groups) => groups.clone(),
+        None => {
+            let required = options.required.unwrap_or(3);
+            let total = options.total.unwrap_or(required + 1);
+            vec![slip39::Group {
+                threshold: slip39_count("--required", required),
+                count: slip39_count("--total", total),
+            }]
+        }
+    };
+    let group_threshold = options.group_threshold.unwrap_or(1);
+    let group_threshold = slip39_count("--group-threshold", group_threshold);
+    let passphrase = options.passphrase.clone().unwrap_or(String::new());
+
+    let mut mnemonics = match slip39::generate_mnemonics(
+        rng,
+        group_threshold,
+        &groups,
+        master_secret.expose(),
+        passphrase.as_bytes(),
+        true,
+        0,
+    ) {
+        Ok(mnemonics) => mnemonics,
+        Err(e) => {
+            println!("Could not generate shares: {}", e);
+            exit(ExitCode::InvalidArgument);
+        }
+    };
+
+    println!("Groups: {:?}, group threshold: {}", groups, group_threshold);
+    print_secret(
+        master_secret.expose(),
+        display_format(&options),
+        &options.secret_file,
+    );
+    for (g, shares) in mnemonics.iter_mut().enumerate() {
+        for (i, s) in shares.iter().enumerate() {
+            print_sensitive(&format!("Group {} shard {}", g + 1, i + 1), s);
+        }
+        zeroize_strings(shares);
+    }
+}
+
+// A count from the command line as a SLIP-39 u8 field, exiting rather than truncating it.
+fn slip39_count(name: &str, count: usize) -> u8 {
+    return match u8::try_from(count) {
+        Ok(count) => count,
+        Err(_) => {
+            println!("{} must be at most 255 for SLIP-39, got {}", name, count);
+            exit(ExitCode::InvalidArgument);
+        }
+    };
+}
+
+// Generates a random secret (128 bits unless --bits is given) and splits it into codex32 shares
+// with threshold --required. A --required of 0 outputs the unshared secret.
+fn generate_codex32<R: RngCore + CryptoRng>(options: Options, rng: &mut R) {
+    println!("-- Generating codex32 secret and shares... --");
+    let required = options.required.unwrap_or(2);
+    let total = options.total.unwrap_or(required + 1);
+
+    let secret = read_secret(&options).unwrap_or_else(|| random_secret(&options, rng));
+    let mut shares = match codex32::split(rng, secret.expose(), required, total) {
+        Ok(shares) => shares,
+        Err(e) => {
+            println!("Could not generate shares: {}", e);
+            exit(ExitCode::InvalidArgument);
+        }
+    };
+    println!("Shards: {}, required: {}", shares.len(), required);
+    print_secret(
+        secret.expose(),
+        display_format(&options),
+        &options.secret_file,
+    );
+    for (i, s) in shares.iter().enumerate() {
+        print_sensitive(&format!("Shard {}", i + 1), s);
+    }
+    zeroize_strings(&mut shares);
+}
+
+fn read_shards_until_empty() -> Vec<Phrase> {
+    println!("Input one share per line, followed by an empty line...");
+    let mut shards: Vec<Phrase> = Vec::new();
+    loop {
+        let line = prompt_phrase(format!("Input shard {}: ", shards.len()).as_str())
+            .expect("Could not read shard");
+        if line.expose_str().trim().is_empty() {
+            break;
+        }
+        shards.push(line);
+    }
+    return shards;
+}
+
+// The text of shards read by read_shards_until_empty, for the codex32 and SLIP-39 parsers.
+fn trimmed(shards: &[Phrase]) -> Vec<&str> {
+    return shards.iter().map(|s| s.expose_str().trim()).collect();
+}
+
+// Wipes shards (or secrets) held as text once they are no longer needed.
+fn zeroize_strings(strings: &mut Vec<String>) {
+    for s in strings.iter_mut() {
+        zeroize_string(s);
+    }
+}
+
+fn restore_codex32(options: Options) {
+    println!("-- Restoring the codex32 secret... --");
+    let shards = read_shards_until_empty();
+    match codex32::combine(&trimmed(&shards)) {
+        Ok(secret) => {
+            let mut secret_share = secret.to_string();
+            print_sensitive("Secret share", &secret_share);
+            zeroize_string(&mut secret_share);
+            print_secret(
+                secret.secret().expose(),
+                options.secret_format,
+                &options.secret_file,
+            );
+        }
+        Err(e) => {
+            println!("Could not restore the secret: {}", e);
+            exit(ExitCode::RestoreFailed);
+        }
+    }
+}
+
+fn restore_slip39(options: Options) {
+    println!("-- Restoring the SLIP-39 master secret... --");
+    let mnemonics = read_shards_until_empty();
+    let passphrase = options.passphrase.unwrap_or(String::new());
+    match slip39::combine_mnemonics(&trimmed(&mnemonics), passphrase.as_bytes()) {
+        Ok(secret) => print_secret(secret.expose(), options.secret_format, &options.secret_file),
+        Err(e) => {
+            println!("Could not restore the secret: {}", e);
+            exit(ExitCode::RestoreFailed);
+        }
+    }
+}
+
 fn restore(options: Options) {
+    match options.format {
+        Some(Format::Slip39) => return restore_slip39(options),
+        Some(Format::Codex32) => return restore_codex32(options),
+        _ => {}
+    }
     println!("-- Restoring the secret... --");
     let total = match options.total {
         None => prompt("How many total shards are there?: ")
@@ -185,12 +888,54 @@ fn restore(options: Options) {
         required
     );
 
-    let mut some_shards: Vec<String> = Vec::new();
+    let mut some_shards: Vec<Phrase> = Vec::new();
     for i in 0..required {
-        let shard = prompt(format!("Input shard {}: ", i).as_str())
+        let shard = prompt_phrase(format!("Input shard {}: ", i).as_str())
             .expect(format!("Could not read shard {}", i).as_str());
         some_shards.push(shard);
     }
+    let mut headers = Vec::with_capacity(some_shards.len());
+    for shard in some_shards.iter() {
+        match shard_header(shard.expose_str()) {
+            Ok(header) => headers.push(header),
+            Err(e) => {
+                println!("Could not read the shard header: {}", e);
+                exit(ExitCode::RestoreFailed);
+            }
+        }
+    }
+    if let Some(encoder) = options.encoder {
+        if let Some(header) = headers.iter().find(|header| header.encoder != encoder) {
+            println!(
+                "Expected {:?} shards, got a {:?} shard.",
+                encoder, header.encoder
+            );
+            exit(ExitCode::RestoreFailed);
+        }
+    }
+    if let Some(field) = options.field {
+        if let Some(header) = headers.iter().find(|header| header.field != field) {
+            println!(
+                "Expected {:?} shards, got a {:?} shard.",
+                field, header.field
+            );
+            exit(ExitCode::RestoreFailed);
+        }
+    }
 
-    unshamir(&some_shards, required, total + 1);
+    // --secret-format overrides the format recorded when the shards were generated.
+    let format = options
+        .secret_format
+        .or_else(|| headers.first().and_then(|header| header.format));
+    println!("Shards: {}, required: {}", some_shards.len(), required);
+    match format {
+        None => unshamir(&some_shards, required, total + 1)
+            .map(|password| print_sensitive("Password", password.expose_str())),
+        Some(format) => unshamir_bytes(&some_shards, required, total + 1)
+            .map(|secret| print_secret(secret.expose(), Some(format), &options.secret_file)),
+    }
+    .unwrap_or_else(|e| {
+        println!("Could not restore the secret: {}", e);
+        exit(ExitCode::RestoreFailed);
+    });
 }
@@ -1,16 +1,18 @@
-use crate::finite_field::Field256;
+use crate::field::{Field, Gf, Symbol};
 use std::convert::TryFrom;
 use std::fmt::Display;
 use std::iter;
+use std::ops::Range;
 
+// A matrix of elements of the field F.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Matrix {
+pub struct Matrix<F: Field> {
     rows: usize,
     cols: usize,
-    pub mat: Vec<Vec<u8>>,
+    pub mat: Vec<Vec<Gf<F>>>,
 }
 
-impl Display for Matrix {
+impl<F: Field> Display for Matrix<F> {
     fn fmt(self: &Self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         for i in 0..self.rows {
             formatter.write_str("\n")?;
@@ -25,10 +27,10 @@ impl Display for Matrix {
     }
 }
 
-impl TryFrom<&[&[u8]]> for Matrix {
+impl<F: Field> TryFrom<&[&[F::Symbol]]> for Matrix<F> {
     type Error = &'static str;
 
-    fn try_from(elems: &[&[u8]]) -> Result<Self, Self::Error> {
+    fn try_from(elems: &[&[F::Symbol]]) -> Result<Self, Self::Error> {
         let rows = elems.len();
         if rows <= 0 {
             return Err("Cannot have a matrix with 0 rows");
@@ -42,7 +44,7 @@ impl TryFrom<&[&[u8]]> for Matrix {
 
         for (i, r) in elems.iter().enumerate() {
             for (j, c) in r.iter().enumerate() {
-                res.mat[i][j] = *c;
+                res.mat[i][j] = Gf::new(*c);
             }
         }
 
@@ -50,10 +52,10 @@ impl TryFrom<&[&[u8]]> for Matrix {
     }
 }
 
-impl TryFrom<Vec<Vec<u8>>> for Matrix {
+impl<F: Field> TryFrom<Vec<Vec<Gf<F>>>> for Matrix<F> {
     type Error = &'static str;
 
-    fn try_from(elems: Vec<Vec<u8>>) -> Result<Self, Self::Error> {
+    fn try_from(elems: Vec<Vec<Gf<F>>>) -> Result<Self, Self::Error> {
         let rows = elems.len();
         if rows <= 0 {
             return Err("Cannot have a matrix with 0 rows");
@@ -76,10 +78,10 @@ impl TryFrom<Vec<Vec<u8>>> for Matrix {
 }
 
 #[allow(unused)]
-impl Matrix {
-    pub fn zero(rows: usize, cols: usize) -> Matrix {
-        let row: Vec<u8> = iter::repeat(0).take(cols).collect();
-        let mat: Vec<Vec<u8>> = iter::repeat(row).take(rows).collect();
+impl<F: Field> Matrix<F> {
+    pub fn zero(rows: usize, cols: usize) -> Matrix<F> {
+        let row: Vec<Gf<F>> = iter::repeat(Gf::zero()).take(cols).collect();
+        let mat: Vec<Vec<Gf<F>>> = iter::repeat(row).take(rows).collect();
         return Matrix {
             rows: rows,
             cols: cols,
@@ -87,75 +89,82 @@ impl Matrix {
         };
     }
 
-    pub fn identity(n: usize) -> Matrix {
-        let row: Vec<u8> = iter::repeat(0).take(n).collect();
-        let mut mat: Vec<Vec<u8>> = iter::repeat(row).take(n).collect();
+    pub fn identity(n: usize) -> Matrix<F> {
+        let mut res = Matrix::zero(n, n);
         for i in 0..n {
-            mat[i][i] = 1;
+            res.mat[i][i] = Gf::one();
         }
-        return Matrix {
-            rows: n,
-            cols: n,
-            mat: mat,
-        };
+        return res;
     }
 
-    pub fn mul<F: Field256>(self: &Self, other: &Self, field: &F) -> Matrix {
+    pub fn mul(self: &Self, other: &Self) -> Matrix<F> {
         assert!(self.cols == other.rows);
         let mut res = Matrix::zero(self.rows, other.cols);
         // Set each element of the matrix
         for i in 0..res.rows {
             for j in 0..res.cols {
                 // Calculate a matrix element
-                for k in 0..self.cols {
-                    res.mat[i][j] =
-                        F::add(res.mat[i][j], field.mul(self.mat[i][k], other.mat[k][j]));
-                }
+                res.mat[i][j] = (0..self.cols)
+                    .map(|k| self.mat[i][k] * other.mat[k][j])
+                    .sum();
             }
         }
 
         return res;
     }
 
-    // Fast multiplication of a vector by a matrix. Takes the input vector as a Vec and the output
-    // as a reference to a mutable Vec. The output *must* be initialized with zeros and be the same
-    // size as the input vector.
-    pub fn mul_vec<F: Field256>(self: &Self, vec: &Vec<u8>, out: &mut Vec<u8>, field: &F) {
-        // assert!(self.cols == vec.len());
-        // assert!(self.rows == out.len());
+    // Multiplication of a vector by a matrix. The output must be the same size as the number of
+    // rows.
+    pub fn mul_vec(self: &Self, vec: &[Gf<F>], out: &mut [Gf<F>]) {
+        assert!(self.cols == vec.len());
+        assert!(self.rows == out.len());
 
         // Set each element of the matrix
         for i in 0..self.rows {
-            out[i] = F::zero();
+            out[i] = (0..self.cols).map(|j| self.mat[i][j] * vec[j]).sum();
+        }
+    }
+
+    // Multiplies the matrix by a vector of regions, where each region is a whole slice of elements
+    // (e.g. the same chunk of many stripes). Each coefficient then multiplies a whole region at once
+    // with Field::mul_add_region. out must have a region per row, which is overwritten.
+    //
+    // The regions are multiplied with `field` rather than F, which must be the same field (e.g. a
+    // constant time implementation of it for secret data).
+    pub fn mul_regions<D: Field<Symbol = F::Symbol>>(
+        self: &Self,
+        regions: &[Vec<F::Symbol>],
+        out: &mut [Vec<F::Symbol>],
+        field: &D,
+    ) {
+        assert!(self.cols == regions.len());
+        assert!(self.rows == out.len());
+        for i in 0..self.rows {
+            for b in out[i].iter_mut() {
+                *b = D::zero();
+            }
             for j in 0..self.cols {
-                out[i] = F::add(out[i], field.mul(self.mat[i][j], vec[j]));
+                field.mul_add_region(self.mat[i][j].value(), &regions[j], &mut out[i]);
             }
         }
     }
 
     fn swap_row(self: &mut Self, from_row: usize, to_row: usize) -> &mut Self {
-        let (mut x, mut y) = (&self.mat[to_row], &self.mat[from_row]);
-        std::mem::swap(&mut x, &mut y);
+        self.mat.swap(from_row, to_row);
         return self;
     }
 
-    fn scale_row<F: Field256>(self: &mut Self, row: usize, scale: u8, field: &F) -> &mut Self {
-        for i in 0..self.cols {
-            self.mat[row][i] = field.mul(self.mat[row][i], scale);
+    fn scale_row(self: &mut Self, row: usize, scale: Gf<F>) -> &mut Self {
+        for x in self.mat[row].iter_mut() {
+            *x *= scale;
         }
         return self;
     }
 
-    fn add_scaled_row<F: Field256>(
-        self: &mut Self,
-        from_row: usize,
-        to_row: usize,
-        scale: u8,
-        field: &F,
-    ) -> &mut Self {
+    fn add_scaled_row(self: &mut Self, from_row: usize, to_row: usize, scale: Gf<F>) -> &mut Self {
         for i in 0..self.cols {
-            self.mat[to_row][i] =
-                F::add(self.mat[to_row][i], field.mul(self.mat[from_row][i], scale));
+            let x = self.mat[from_row][i] * scale;
+            self.mat[to_row][i] += x;
         }
         return self;
     }
@@ -163,7 +172,7 @@ impl Matrix {
     fn augment_with_identity(self: &mut Self) -> &mut Self {
         for i in 0..self.rows {
             for j in 0..self.cols {
-                self.mat[i].push(if i == j { 1 } else { 0 });
+                self.mat[i].push(if i == j { Gf::one() } else { Gf::zero() });
             }
         }
         self.cols *= 2;
@@ -185,7 +194,7 @@ impl Matrix {
         };
     }
 
-    pub fn invert<F: Field256>(self: &Self, field: &F) -> Result<Self, &'static str> {
+    pub fn invert(self: &Self) -> Result<Self, &'static str> {
         let mut res = self.clone();
         res.augment_with_identity();
 
@@ -193,22 +202,22 @@ impl Matrix {
         for i in 0..self.rows {
             // Swap rows, if necessary.
             for j in i..self.rows {
-                if self.mat[j][i] != 0 {
+                if !res.mat[j][i].is_zero() {
                     res.swap_row(i, j);
                     break;
                 }
             }
             // If swapping rows did not find a row without a 0 in the row and column we're
             // operating on then the matrix must not be invertable.
-            if res.mat[i][i] == 0 {
+            if res.mat[i][i].is_zero() {
                 return Err("The matrix is singular and cannot be inverted.");
             }
-            if res.mat[i][i] != 1 {
-                res.scale_row(i, field.inv(res.mat[i][i]), field);
+            if res.mat[i][i] != Gf::one() {
+                res.scale_row(i, res.mat[i][i].inv());
             }
             for j in (i + 1)..self.rows {
-                if res.mat[j][i] != 0 {
-                    res.add_scaled_row(i, j, res.mat[j][i], field);
+                if !res.mat[j][i].is_zero() {
+                    res.add_scaled_row(i, j, -res.mat[j][i]);
                 }
             }
         }
@@ -216,7 +225,7 @@ impl Matrix {
         // Lower triangular reduction
         for i in (0..self.rows).rev() {
             for j in 0..i {
-                res.add_scaled_row(i, j, res.mat[j][i], field);
+                res.add_scaled_row(i, j, -res.mat[j][i]);
             }
         }
 
@@ -233,96 +242,290 @@ impl Matrix {
             mat: ret_mat,
         });
     }
+
+    // Inverts a matrix from vandermonde_matrix or partial_vandermonde_matrix in O(n^2) with
+    // vandermonde_inverse. That needs its rows to be the powers of their second column, which they
+    // aren't with ExpLogField, whose exp multiplies logarithms, so its matrices have no structure to
+    // exploit. They are inverted with Gauss-Jordan elimination instead, which stays O(n^3). Only
+    // shards from before versioned headers use them; new ones use TableField's (see shamir.rs).
+    pub fn invert_vandermonde(self: &Self) -> Result<Self, &'static str> {
+        if self.rows != self.cols {
+            return Err("Only square matrices can be inverted.");
+        }
+        if self.cols < 2 {
+            return self.invert();
+        }
+        let xs: Vec<Gf<F>> = self.mat.iter().map(|row| row[1]).collect();
+        let powers = self.mat.iter().zip(xs.iter()).all(|(row, x)| {
+            return row[0] == Gf::one() && (2..self.cols).all(|j| row[j] == row[j - 1] * *x);
+        });
+        if !powers {
+            return self.invert();
+        }
+        return vandermonde_inverse(&xs);
+    }
+}
+
+// The inverse of the Vandermonde matrix whose row i is the powers of xs[i], in O(n^2). Its column i
+// is the coefficients of the Lagrange polynomial which is 1 at xs[i] and 0 at the other xs, which
+// is P(x) / (x - xs[i]) divided by its value at xs[i], for P the product of x - xs[j] over all j.
+pub fn vandermonde_inverse<F: Field>(xs: &[Gf<F>]) -> Result<Matrix<F>, &'static str> {
+    let n = xs.len();
+    // The coefficients of P, lowest first.
+    let mut p = vec![Gf::<F>::zero(); n + 1];
+    p[0] = Gf::one();
+    for (k, x) in xs.iter().enumerate() {
+        for j in (0..=k).rev() {
+            let shifted = p[j];
+            p[j + 1] += shifted;
+            p[j] = -(*x * shifted);
+        }
+    }
+    let mut res = Matrix::zero(n, n);
+    let mut q = vec![Gf::<F>::zero(); n];
+    for (i, x) in xs.iter().enumerate() {
+        // Synthetic division of P by x - xs[i], and the quotient's value at xs[i].
+        let mut carry = Gf::zero();
+        for j in (0..n).rev() {
+            carry = p[j + 1] + carry * *x;
+            q[j] = carry;
+        }
+        let value = q.iter().rev().fold(Gf::zero(), |v, c| v * *x + *c);
+        if value.is_zero() {
+            return Err("The matrix is singular and cannot be inverted.");
+        }
+        let scale = value.inv();
+        for j in 0..n {
+            res.mat[j][i] = q[j] * scale;
+        }
+    }
+    return Ok(res);
 }
 
-pub fn vandermonde_matrix<F: Field256>(
+// The rows of the product of the matrix with the given rows and `inverse`, the inverse of the
+// matrix of the rows `sources`. Row i of the product gives chunk i from the source chunks, so for a
+// source it is just a row of the identity, and only the others are multiplied, in O(n^2) each.
+fn recovery_matrix<F: Field, R: Fn(usize) -> Result<Vec<Gf<F>>, &'static str>>(
+    rows: Range<usize>,
+    sources: &[usize],
+    inverse: &Matrix<F>,
+    row: R,
+) -> Result<Matrix<F>, &'static str> {
+    if inverse.rows != sources.len() || inverse.cols != sources.len() {
+        return Err("The inverse must have a row and column for each source.");
+    }
+    let matrix = rows
+        .map(|i| match sources.iter().position(|source| *source == i) {
+            Some(k) => Ok((0..sources.len())
+                .map(|j| if j == k { Gf::one() } else { Gf::zero() })
+                .collect()),
+            None => {
+                let row = row(i)?;
+                return Ok((0..inverse.cols)
+                    .map(|j| (0..inverse.rows).map(|k| row[k] * inverse.mat[k][j]).sum())
+                    .collect());
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    return Matrix::try_from(matrix);
+}
+
+// Row i of the Vandermonde matrices.
+fn vandermonde_row<F: Field>(i: usize, cols: usize) -> Result<Vec<Gf<F>>, &'static str> {
+    if i >= F::Symbol::ORDER || cols > F::Symbol::ORDER {
+        return Err("The field has too few elements for the matrix");
+    }
+    return Ok((0..cols)
+        .map(|j| Gf::new(F::Symbol::from_usize(i)).pow(F::Symbol::from_usize(j)))
+        .collect());
+}
+
+pub fn vandermonde_matrix<F: Field>(
     start: usize,
     rows: usize,
     cols: usize,
-    field: &F,
-) -> Result<Matrix, &'static str> {
-    let mut matrix = Vec::with_capacity(rows);
-    for i in start..(start + rows) {
-        let mut row = Vec::with_capacity(cols);
-        for j in 0..cols {
-            row.push(field.exp(i as u8, j as u8));
-        }
-        matrix.push(row);
-    }
+) -> Result<Matrix<F>, &'static str> {
+    let matrix = (start..start + rows)
+        .map(|i| vandermonde_row(i, cols))
+        .collect::<Result<Vec<_>, _>>()?;
     // Creating this should not ever fail.
     return Matrix::try_from(matrix);
 }
 
+// The rows of vandermonde_matrix(rows.start, rows.len(), sources.len()) times `inverse`, the
+// inverse of the Vandermonde matrix of the rows `sources`, without the O(n^3) product.
+pub fn vandermonde_recovery<F: Field>(
+    rows: Range<usize>,
+    sources: &[usize],
+    inverse: &Matrix<F>,
+) -> Result<Matrix<F>, &'static str> {
+    return recovery_matrix(rows, sources, inverse, |i| {
+        vandermonde_row(i, sources.len())
+    });
+}
+
 /// Returns a square Vandermonde matrix with potentially non-contiguous rows, each with a given
 /// number of cols. Picks which rows to generate based on an iterator of bools (only generates rows
 /// when true; generates a row for the given iterator index.
-pub fn partial_vandermonde_matrix<F: Field256, I: Iterator<Item = bool>>(
+pub fn partial_vandermonde_matrix<F: Field, I: Iterator<Item = bool>>(
     rows: I,
     cols: usize,
-    field: &F,
-) -> Result<Matrix, &'static str> {
-    let mut matrix = Vec::with_capacity(cols);
-    for (i, _) in rows.enumerate().filter(|(_, x)| *x).take(cols) {
-        let mut row = Vec::with_capacity(cols);
-        for j in 0..cols {
-            row.push(field.exp(i as u8, j as u8));
-        }
-        matrix.push(row);
-    }
+) -> Result<Matrix<F>, &'static str> {
+    let matrix = rows
+        .enumerate()
+        .filter(|(_, x)| *x)
+        .take(cols)
+        .map(|(i, _)| vandermonde_row(i, cols))
+        .collect::<Result<Vec<_>, _>>()?;
     // Creating this should not ever fail.
     return Matrix::try_from(matrix);
 }
 
-pub fn cauchy_matrix<F: Field256>(
+// The points x_i and y_j of the Cauchy matrices, whose elements are 1 / (x_i - y_j). The x_i are
+// non-zero elements of the lower half of the field and the y_j those of the upper half, so the two
+// never overlap. Returns None if the field is too small to have the point.
+fn cauchy_x<F: Field>(i: usize) -> Option<Gf<F>> {
+    if i + 1 >= F::Symbol::ORDER / 2 {
+        return None;
+    }
+    return Some(Gf::new(F::Symbol::from_usize(i + 1)));
+}
+
+fn cauchy_y<F: Field>(j: usize) -> Option<Gf<F>> {
+    if j >= F::Symbol::ORDER - F::Symbol::ORDER / 2 {
+        return None;
+    }
+    return Some(Gf::new(F::Symbol::from_usize(F::Symbol::ORDER / 2 + j)));
+}
+
+fn cauchy_ys<F: Field>(cols: usize) -> Result<Vec<Gf<F>>, &'static str> {
+    return (0..cols)
+        .map(cauchy_y)
+        .collect::<Option<Vec<Gf<F>>>>()
+        .ok_or("The field has too few elements for the matrix");
+}
+
+// The row of the Cauchy matrices for chunk i.
+fn cauchy_row<F: Field>(i: usize, ys: &[Gf<F>]) -> Result<Vec<Gf<F>>, &'static str> {
+    let x = cauchy_x::<F>(i).ok_or("The field has too few elements for the matrix")?;
+    return Ok(ys.iter().map(|y| (x - *y).inv()).collect());
+}
+
+// The rows start..start + rows of the Cauchy matrix, so that, like the Vandermonde matrices, the
+// rows of the code chunks follow those of the data chunks.
+pub fn cauchy_matrix<F: Field>(
+    start: usize,
     rows: usize,
     cols: usize,
-    field: &F,
-) -> Result<Matrix, &'static str> {
-    let mut matrix = Vec::with_capacity(rows);
-    let xs: Vec<u8> = (1..=127).collect();
-    let ys: Vec<u8> = (128..=255).collect();
-    for i in 0..rows {
-        let mut row = Vec::with_capacity(cols);
-        for j in 0..cols {
-            let x: u8 = xs[i];
-            let y: u8 = ys[j];
-            row.push(field.inv(F::sub(x, y)));
-        }
-        matrix.push(row);
-    }
+) -> Result<Matrix<F>, &'static str> {
+    let ys = cauchy_ys::<F>(cols)?;
+    let matrix = (start..start + rows)
+        .map(|i| cauchy_row(i, &ys))
+        .collect::<Result<Vec<_>, _>>()?;
     // Creating this should not ever fail.
     return Matrix::try_from(matrix);
 }
 
-pub fn partial_cauchy_matrix<F: Field256, I: Iterator<Item = bool>>(
+// Like cauchy_matrix(rows.start, rows.len(), sources.len()) times `inverse`, as
+// vandermonde_recovery.
+pub fn cauchy_recovery<F: Field>(
+    rows: Range<usize>,
+    sources: &[usize],
+    inverse: &Matrix<F>,
+) -> Result<Matrix<F>, &'static str> {
+    let ys = cauchy_ys::<F>(sources.len())?;
+    return recovery_matrix(rows, sources, inverse, |i| cauchy_row(i, &ys));
+}
+
+// Only the inverses of these are needed to encode and decode.
+#[cfg(test)]
+pub fn partial_cauchy_matrix<F: Field, I: Iterator<Item = bool>>(
     rows: I,
     cols: usize,
-    field: &F,
-) -> Result<Matrix, &'static str> {
-    let mut matrix = Vec::with_capacity(cols);
-    let xs: Vec<u8> = (1..=127).collect();
-    let ys: Vec<u8> = (128..=255).collect();
-    for (i, _) in rows.enumerate().filter(|(_, x)| *x).take(cols) {
-        let mut row = Vec::with_capacity(cols);
-        for j in 0..cols {
-            let x: u8 = xs[i];
-            let y: u8 = ys[j];
-            row.push(field.inv(F::sub(x, y)));
-        }
-        matrix.push(row);
-    }
+) -> Result<Matrix<F>, &'static str> {
+    let ys = cauchy_ys::<F>(cols)?;
+    let matrix = rows
+        .enumerate()
+        .filter(|(_, x)| *x)
+        .take(cols)
+        .map(|(i, _)| cauchy_row(i, &ys))
+        .collect::<Result<Vec<_>, _>>()?;
     // Creating this should not ever fail.
     return Matrix::try_from(matrix);
 }
 
+// The inverse of the Cauchy matrix with elements 1 / (xs[i] - ys[j]), in O(n^2). With A(z) the
+// product of z - xs[k] and B(z) that of z - ys[k], the sum over j of D[j][l] / (z - ys[j]) is the
+// rational function with poles at the ys which is 1 at xs[l] and 0 at the other xs when
+//   D[j][l] = B(xs[l]) A(ys[j]) / (A'(xs[l]) B'(ys[j]) (ys[j] - xs[l]))
+// where A'(xs[l]) is the product of xs[l] - xs[k] for k != l, and likewise for B'.
+pub fn cauchy_inverse<F: Field>(xs: &[Gf<F>], ys: &[Gf<F>]) -> Result<Matrix<F>, &'static str> {
+    let n = xs.len();
+    if ys.len() != n {
+        return Err("Only square matrices can be inverted.");
+    }
+    let product = |z: Gf<F>, points: &[Gf<F>], skip: Option<usize>| {
+        return points
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| Some(*k) != skip)
+            .map(|(_, p)| z - *p)
+            .product::<Gf<F>>();
+    };
+    let mut x_terms = Vec::with_capacity(n);
+    for (l, x) in xs.iter().enumerate() {
+        let derivative = product(*x, xs, Some(l));
+        if derivative.is_zero() {
+            return Err("The matrix is singular and cannot be inverted.");
+        }
+        x_terms.push(product(*x, ys, None) / derivative);
+    }
+    let mut y_terms = Vec::with_capacity(n);
+    for (j, y) in ys.iter().enumerate() {
+        let derivative = product(*y, ys, Some(j));
+        if derivative.is_zero() {
+            return Err("The matrix is singular and cannot be inverted.");
+        }
+        y_terms.push(product(*y, xs, None) / derivative);
+    }
+    let mut res = Matrix::zero(n, n);
+    for j in 0..n {
+        for l in 0..n {
+            let difference = ys[j] - xs[l];
+            if difference.is_zero() {
+                return Err("The Cauchy matrix has the same point in its xs and ys.");
+            }
+            res.mat[j][l] = x_terms[l] * y_terms[j] / difference;
+        }
+    }
+    return Ok(res);
+}
+
+// The inverse of partial_cauchy_matrix(rows, cols).
+pub fn partial_cauchy_inverse<F: Field, I: Iterator<Item = bool>>(
+    rows: I,
+    cols: usize,
+) -> Result<Matrix<F>, &'static str> {
+    let ys = cauchy_ys::<F>(cols)?;
+    let xs = rows
+        .enumerate()
+        .filter(|(_, x)| *x)
+        .take(cols)
+        .map(|(i, _)| cauchy_x::<F>(i).ok_or("The field has too few elements for the matrix"))
+        .collect::<Result<Vec<_>, _>>()?;
+    return cauchy_inverse(&xs, &ys);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::finite_field::{DirectField, Ring};
+    use crate::field::ExpLogField65536;
+    use crate::finite_field::{DirectField, ExpLogField, Ring};
+    use crate::prime_field::Secp256k1Field;
 
     #[test]
     fn zero() {
-        let zero = Matrix::zero(10, 5);
+        let zero = Matrix::<DirectField>::zero(10, 5);
         assert_eq!(zero.mat.len(), 10);
         assert_eq!(zero.rows, 10);
         assert_eq!(zero.cols, 5);
@@ -330,27 +533,24 @@ mod tests {
 
     #[test]
     fn identity() {
-        let id = Matrix::identity(5);
+        let id = Matrix::<DirectField>::identity(5);
         assert_eq!(id.mat.len(), 5);
         assert_eq!(id.mat[0].len(), 5);
-        assert_eq!(id.mat[0][0], 1);
-        assert_eq!(id.mat[0][1], 0);
-        assert_eq!(id.mat[1][0], 0);
+        assert_eq!(id.mat[0][0], Gf::one());
+        assert_eq!(id.mat[0][1], Gf::zero());
+        assert_eq!(id.mat[1][0], Gf::zero());
     }
 
     #[test]
     fn invert_identity_is_identity() {
-        let direct = DirectField::default();
-        let id = Matrix::identity(5);
-        let inv = id.invert(&direct).unwrap();
+        let id = Matrix::<DirectField>::identity(5);
+        let inv = id.invert().unwrap();
         assert_eq!(id, inv);
     }
 
     #[test]
     fn mat_mul_by_inv_is_identity() {
-        let direct = DirectField::default();
-
-        let a = Matrix::try_from(
+        let a = Matrix::<DirectField>::try_from(
             &[
                 &[1u8, 2u8, 3u8][..],
                 &[4u8, 5u8, 6u8][..],
@@ -358,29 +558,134 @@ mod tests {
             ][..],
         )
         .unwrap();
-        let a_inv = a.invert(&direct).unwrap();
-        assert_eq!(a.mul(&a_inv, &direct), Matrix::identity(3));
+        let a_inv = a.invert().unwrap();
+        assert_eq!(a.mul(&a_inv), Matrix::identity(3));
+    }
+
+    #[test]
+    fn invert_swaps_rows() {
+        // The first column's only non-zero element is in the last row.
+        let a = Matrix::<DirectField>::try_from(
+            &[
+                &[0u8, 1u8, 2u8][..],
+                &[0u8, 3u8, 4u8][..],
+                &[5u8, 6u8, 7u8][..],
+            ][..],
+        )
+        .unwrap();
+        let a_inv = a.invert().unwrap();
+        assert_eq!(a.mul(&a_inv), Matrix::identity(3));
+        assert!(Matrix::<DirectField>::zero(3, 3).invert().is_err());
     }
 
     #[test]
     fn mul_simple() {
         // This gives us "normal" multiplication, but inv/div is broken. That's okay for this test
         // and it makes it easier to verify the multiplication works right.
-        let ring = Ring::default();
-        let a = Matrix::try_from(&[&[1u8, 2u8, 3u8][..], &[4u8, 5u8, 6u8][..]][..]).unwrap();
-        let b = Matrix::try_from(&[&[1u8, 2u8][..], &[1u8, 2u8][..], &[1u8, 2u8][..]][..]).unwrap();
-        let res = a.mul(&b, &ring);
-        assert_eq!(res.mat[0][0], 6);
-        assert_eq!(res.mat[0][1], 12);
-        assert_eq!(res.mat[1][0], 15);
-        assert_eq!(res.mat[1][1], 30);
+        let a =
+            Matrix::<Ring>::try_from(&[&[1u8, 2u8, 3u8][..], &[4u8, 5u8, 6u8][..]][..]).unwrap();
+        let b = Matrix::<Ring>::try_from(&[&[1u8, 2u8][..], &[1u8, 2u8][..], &[1u8, 2u8][..]][..])
+            .unwrap();
+        let res = a.mul(&b);
+        assert_eq!(res.mat[0][0].value(), 6);
+        assert_eq!(res.mat[0][1].value(), 12);
+        assert_eq!(res.mat[1][0].value(), 15);
+        assert_eq!(res.mat[1][1].value(), 30);
     }
 
     #[test]
-    fn mul_id() {
+    fn mul_regions_agrees_with_mul_vec() {
         let direct = DirectField::default();
-        let m = Matrix::try_from(&[&[1u8, 2u8][..], &[3u8, 4u8][..], &[5u8, 6u8][..]][..]).unwrap();
-        assert_eq!(Matrix::identity(3).mul(&m, &direct), m);
-        assert_eq!(m.mul(&Matrix::identity(2), &direct), m);
+        let m = Matrix::<DirectField>::try_from(&[&[1u8, 2u8, 3u8][..], &[4u8, 5u8, 6u8][..]][..])
+            .unwrap();
+        let regions = vec![vec![7u8, 8u8], vec![9u8, 10u8], vec![11u8, 12u8]];
+        let mut out = vec![vec![0xff; 2]; 2];
+        m.mul_regions(&regions, &mut out, &direct);
+        for i in 0..2 {
+            let vec: Vec<Gf<DirectField>> = regions.iter().map(|r| Gf::new(r[i])).collect();
+            let mut expected = vec![Gf::zero(); 2];
+            m.mul_vec(&vec, &mut expected);
+            assert_eq!(
+                vec![out[0][i], out[1][i]],
+                vec![expected[0].value(), expected[1].value()]
+            );
+        }
+    }
+
+    #[test]
+    fn vandermonde_needs_enough_elements() {
+        assert!(vandermonde_matrix::<DirectField>(250, 6, 6).is_ok());
+        assert!(vandermonde_matrix::<DirectField>(250, 7, 7).is_err());
+        assert!(
+            partial_vandermonde_matrix::<DirectField, _>((0..300).map(|i| i >= 290), 5).is_err()
+        );
+        let m = partial_vandermonde_matrix::<ExpLogField65536, _>((0..300).map(|i| i >= 290), 5)
+            .unwrap();
+        assert_eq!(m.mul(&m.invert().unwrap()), Matrix::identity(5));
+    }
+
+    fn vandermonde_inverse_for<F: Field>() {
+        let rows = (0..40).map(|i| i % 3 != 1);
+        let m = partial_vandermonde_matrix::<F, _>(rows, 26).unwrap();
+        let inv = m.invert_vandermonde().unwrap();
+        assert_eq!(inv, m.invert().unwrap());
+        assert_eq!(m.mul(&inv), Matrix::identity(26));
+        // Only the rows which are not sources are multiplied, but they all match the product.
+        let sources: Vec<usize> = (0..40).filter(|i| i % 3 != 1).take(26).collect();
+        assert_eq!(
+            vandermonde_recovery(0..40, &sources, &inv).unwrap(),
+            vandermonde_matrix::<F>(0, 40, 26).unwrap().mul(&inv)
+        );
+        let xs: Vec<Gf<F>> = [3, 5, 3]
+            .iter()
+            .map(|x| Gf::new(F::Symbol::from_usize(*x)))
+            .collect();
+        assert!(vandermonde_inverse(&xs).is_err());
+    }
+
+    #[test]
+    fn vandermonde_inverse_is_inverse() {
+        vandermonde_inverse_for::<DirectField>();
+        vandermonde_inverse_for::<ExpLogField65536>();
+        vandermonde_inverse_for::<Secp256k1Field>();
+        // ExpLogField's matrices aren't powers, but are still inverted.
+        vandermonde_inverse_for::<ExpLogField>();
+    }
+
+    fn cauchy_inverse_for<F: Field>() {
+        let rows: Vec<bool> = (0..60).map(|i| i % 4 != 2).collect();
+        let m = partial_cauchy_matrix::<F, _>(rows.iter().cloned(), 30).unwrap();
+        let inv = partial_cauchy_inverse::<F, _>(rows.iter().cloned(), 30).unwrap();
+        assert_eq!(inv, m.invert().unwrap());
+        assert_eq!(m.mul(&inv), Matrix::identity(30));
+        let sources: Vec<usize> = (0..60).filter(|i| i % 4 != 2).take(30).collect();
+        assert_eq!(
+            cauchy_recovery(0..60, &sources, &inv).unwrap(),
+            cauchy_matrix::<F>(0, 60, 30).unwrap().mul(&inv)
+        );
+        let points: Vec<Gf<F>> = [3, 5]
+            .iter()
+            .map(|x| Gf::new(F::Symbol::from_usize(*x)))
+            .collect();
+        assert!(cauchy_inverse(&points, &points).is_err());
+        assert!(cauchy_inverse(&points[..1], &points).is_err());
+    }
+
+    #[test]
+    fn cauchy_inverse_is_inverse() {
+        cauchy_inverse_for::<DirectField>();
+        cauchy_inverse_for::<ExpLogField>();
+        cauchy_inverse_for::<ExpLogField65536>();
+        cauchy_inverse_for::<Secp256k1Field>();
+    }
+
+    #[test]
+    fn mul_id() {
+        let m = Matrix::<DirectField>::try_from(
+            &[&[1u8, 2u8][..], &[3u8, 4u8][..], &[5u8, 6u8][..]][..],
+        )
+        .unwrap();
+        assert_eq!(Matrix::identity(3).mul(&m), m);
+        assert_eq!(m.mul(&Matrix::identity(2)), m);
     }
 }
@@ -1,68 +1,119 @@
-use crate::finite_field::Field256;
+use crate::field::{Field, Gf, Symbol};
 use std::iter;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 
-// A polynomial over byte values.
+// A polynomial over the field F.
 #[derive(Debug, PartialEq, Clone)]
-pub struct Polynomial {
+pub struct Polynomial<F: Field> {
     // Term coefficients for powers of x starting at 0 (i.e. coefficients[i] is for term cx^i).
     // The last element must always be non-zero. This allows us to efficiently compute the degree
     // from the length of this list without tracking it separately.
-    coefficients: Vec<u8>,
+    coefficients: Vec<Gf<F>>,
 }
 
-impl Polynomial {
+impl<F: Field> Polynomial<F> {
     // Returns the "zero" polynomial which is defined as the polynomial with no coefficients and
     // degree -1.
-    fn zero() -> Self {
+    pub fn zero() -> Self {
         return Polynomial {
             coefficients: Vec::new(),
         };
     }
 
-    // Creates a Polynomial from a given vector of coefficients. Has degree d == coefficients.len()
-    // - 1.
-    fn from_bytes(coefficients: &[u8]) -> Self {
-        return Polynomial {
-            // coefficients: Vec::from(coefficients),
-            coefficients: Vec::from(coefficients),
-        };
+    // Returns the constant polynomial 1.
+    pub fn one() -> Self {
+        return Self::constant(Gf::one());
+    }
+
+    // Returns the constant polynomial c.
+    pub fn constant(c: Gf<F>) -> Self {
+        return Self::from_coefficients(&[c]);
+    }
+
+    // Returns the polynomial x.
+    pub fn x() -> Self {
+        return Self::from_coefficients(&[Gf::zero(), Gf::one()]);
+    }
+
+    // Creates a Polynomial from a given vector of coefficients, lowest power first. Trailing zero
+    // coefficients are dropped, so the degree is that of the highest non-zero term.
+    pub fn from_coefficients(coefficients: &[Gf<F>]) -> Self {
+        let mut coefficients = Vec::from(coefficients);
+        while coefficients.last() == Some(&Gf::zero()) {
+            coefficients.pop();
+        }
+        return Polynomial { coefficients };
+    }
+
+    // Like from_coefficients, but from the underlying symbols.
+    #[allow(unused)]
+    fn from_bytes(coefficients: &[F::Symbol]) -> Self {
+        let coefficients: Vec<_> = coefficients.iter().map(|c| Gf::new(*c)).collect();
+        return Self::from_coefficients(&coefficients);
+    }
+
+    // The coefficients, lowest power first, without trailing zeros.
+    pub fn coefficients(&self) -> &[Gf<F>] {
+        return &self.coefficients;
     }
 
     // Returns the degree of the Polynomial which is defined as -1 for the zero Polynomial and the
     // largest exponent (power) of x for any term (e.g. for `5 + x + 2x^3` it is `3`) otherwise,
     // with the constant term having exponent `0`.
-    fn degree(self: &Self) -> i64 {
+    pub fn degree(self: &Self) -> i64 {
         return self.coefficients.len() as i64 - 1;
     }
 
     // Returns whether this Polynomial is the zero Polynomial.
-    fn is_zero(self: &Self) -> bool {
+    pub fn is_zero(self: &Self) -> bool {
         return self.degree() == -1;
     }
 
+    // The coefficient of the highest power of x, or zero for the zero Polynomial.
+    pub fn leading_coefficient(&self) -> Gf<F> {
+        return self.coefficients.last().cloned().unwrap_or(Gf::zero());
+    }
+
     // Adds to polynomials together. This involves doing a pointwise sum of coefficients.
-    fn add<F: Field256>(&self, other: &Self) -> Self {
-        let shorter;
-        let longer;
-        if self.coefficients.len() > other.coefficients.len() {
-            shorter = &other.coefficients;
-            longer = &self.coefficients;
-        } else {
-            shorter = &self.coefficients;
-            longer = &other.coefficients;
-        }
+    pub fn add(&self, other: &Self) -> Self {
+        return self.zip_with(other, |x, y| x + y);
+    }
+
+    // Subtracts other from this Polynomial, pointwise like add.
+    pub fn sub(&self, other: &Self) -> Self {
+        return self.zip_with(other, |x, y| x - y);
+    }
 
-        let new_coefficients: Vec<_> = shorter
-            .into_iter()
-            .cloned()
-            .chain(iter::repeat(F::zero()))
-            .zip(longer)
-            .map(|(x, y)| F::add(x, *y))
+    // Combines the coefficients of two polynomials pointwise, padding the shorter with zeros.
+    fn zip_with<G: Fn(Gf<F>, Gf<F>) -> Gf<F>>(&self, other: &Self, g: G) -> Self {
+        let length = self.coefficients.len().max(other.coefficients.len());
+        let padded = |p: &Self| {
+            return p
+                .coefficients
+                .clone()
+                .into_iter()
+                .chain(iter::repeat(Gf::zero()))
+                .take(length);
+        };
+        let new_coefficients: Vec<_> = padded(self)
+            .zip(padded(other))
+            .map(|(x, y)| g(x, y))
             .collect();
-        return Polynomial::from_bytes(&new_coefficients);
+        return Polynomial::from_coefficients(&new_coefficients);
     }
 
-    fn mul<F: Field256>(self: Self, other: &Self, field: &F) -> Self {
+    pub fn neg(&self) -> Self {
+        let new_coefficients: Vec<_> = self.coefficients.iter().map(|c| -*c).collect();
+        return Polynomial::from_coefficients(&new_coefficients);
+    }
+
+    // Multiplies every coefficient by c.
+    pub fn scale(&self, c: Gf<F>) -> Self {
+        let new_coefficients: Vec<_> = self.coefficients.iter().map(|x| *x * c).collect();
+        return Polynomial::from_coefficients(&new_coefficients);
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
         if self.is_zero() || other.is_zero() {
             return Polynomial::zero();
         }
@@ -70,22 +121,109 @@ impl Polynomial {
         // Compute the degree of the resulting polynomial as the sum of degrees
         let degree = self.degree() + other.degree();
 
-        let mut new_coefficients: Vec<_> = iter::repeat(F::zero())
+        let mut new_coefficients: Vec<_> = iter::repeat(Gf::zero())
             .take((degree + 1) as usize)
             .collect();
         for (e1, c1) in self.coefficients.iter().enumerate() {
             for (e2, c2) in other.coefficients.iter().enumerate() {
-                let e: usize = e1 + e2;
-                let c = field.mul(*c1, *c2);
-                new_coefficients[e] = F::add(new_coefficients[e], c);
+                new_coefficients[e1 + e2] += *c1 * *c2;
+            }
+        }
+
+        return Polynomial::from_coefficients(&new_coefficients);
+    }
+
+    // Long division, returning the quotient q and remainder r such that self == q * divisor + r
+    // and r has a lower degree than divisor. Panics when dividing by the zero Polynomial.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        if divisor.is_zero() {
+            panic!("Cannot divide by the zero polynomial!");
+        }
+        if self.degree() < divisor.degree() {
+            return (Polynomial::zero(), self.clone());
+        }
+
+        let d = divisor.degree() as usize;
+        let lead_inv = divisor.leading_coefficient().inv();
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![Gf::zero(); remainder.len() - d];
+        // Cancel the highest remaining term with a multiple of the divisor, from the top down.
+        for i in (0..quotient.len()).rev() {
+            let c = remainder[i + d] * lead_inv;
+            quotient[i] = c;
+            for (j, dc) in divisor.coefficients.iter().enumerate() {
+                remainder[i + j] -= c * *dc;
+            }
+        }
+        remainder.truncate(d);
+
+        return (
+            Polynomial::from_coefficients(&quotient),
+            Polynomial::from_coefficients(&remainder),
+        );
+    }
+
+    // Divides by the leading coefficient, so that it becomes 1. The zero Polynomial is unchanged.
+    pub fn monic(&self) -> Self {
+        if self.is_zero() {
+            return Polynomial::zero();
+        }
+        return self.scale(self.leading_coefficient().inv());
+    }
+
+    // The monic greatest common divisor, by Euclid's algorithm. gcd(0, 0) is 0.
+    pub fn gcd(&self, other: &Self) -> Self {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b);
+            a = b;
+            b = r;
+        }
+        return a.monic();
+    }
+
+    // The formal derivative. The coefficient of x^i becomes i * c, meaning c added i times, so
+    // in characteristic 2 the even powers vanish.
+    pub fn derivative(&self) -> Self {
+        let new_coefficients: Vec<_> = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, c)| Self::times(*c, i))
+            .collect();
+        return Polynomial::from_coefficients(&new_coefficients);
+    }
+
+    // c added to itself n times, by doubling.
+    fn times(c: Gf<F>, n: usize) -> Gf<F> {
+        let mut result = Gf::zero();
+        let mut power = c;
+        let mut n = n;
+        while n > 0 {
+            if n & 1 == 1 {
+                result += power;
             }
+            power = power + power;
+            n >>= 1;
         }
+        return result;
+    }
 
-        return Polynomial::from_bytes(&new_coefficients);
+    // Returns the Polynomial self(other(x)), by Horner's method.
+    pub fn compose(&self, other: &Self) -> Self {
+        return self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(Polynomial::zero(), |acc, c| {
+                acc.mul(other).add(&Self::constant(*c))
+            });
     }
 
     // Computes a single term Polynomial P such that P(i) == values[i].
-    fn single_term<F: Field256>(points: &[(u8, u8)], (xi, yi): (u8, u8), field: &F) -> Self {
+    fn single_term(points: &[(Gf<F>, Gf<F>)], (xi, yi): (Gf<F>, Gf<F>)) -> Self {
         if points.len() == 0 {
             return Polynomial::zero();
         }
@@ -96,203 +234,472 @@ impl Polynomial {
         //  yi * |   | ---------
         //       |   | (xi - xj)
         //      j /= i
-        let mut term = Self::from_bytes(&[yi]);
+        let mut term = Self::from_coefficients(&[yi]);
         for (xj, _) in points.iter().filter(|(x, _)| *x != xi) {
             // Equivalent to the term:
             //
             //   (x - xj)
             //   ---------
             //   (xi - xj)
-            let xj = *xj;
-            let denominator = F::sub(xi, xj);
-            let zeroth_term = field.div(xj, denominator);
-            let first_term = field.inv(denominator);
-            let p = Self::from_bytes(&[zeroth_term, first_term]);
-            // println!("Constructing subterm xi: {:?}, xj: {:?}, denominator: {:?}, zeroth_term: {:?}, first_term: {:?}, p: {:?}", xi, xj, denominator, zeroth_term, first_term, p.clone());
-
-            term = term.mul(&p, field);
+            let denominator = xi - *xj;
+            let zeroth_term = -*xj / denominator;
+            let first_term = denominator.inv();
+            let p = Self::from_coefficients(&[zeroth_term, first_term]);
+
+            term = term.mul(&p);
         }
 
         return term;
     }
 
     #[allow(unused)]
-    fn single_term_ys<F: Field256>(ys: &[u8], i: u8, field: &F) -> Self {
-        assert!((i as usize) < ys.len());
-        let points: Vec<_> = ys.iter().enumerate().map(|(x, y)| (x as u8, *y)).collect();
-        Self::single_term(&points[..], (i, ys[i as usize]), field)
+    fn single_term_ys(ys: &[F::Symbol], i: usize) -> Self {
+        assert!(i < ys.len());
+        let points = Self::to_points(
+            ys.iter()
+                .enumerate()
+                .map(|(x, y)| (F::Symbol::from_usize(x), *y)),
+        );
+        Self::single_term(&points[..], points[i])
+    }
+
+    fn to_points<I: Iterator<Item = (F::Symbol, F::Symbol)>>(points: I) -> Vec<(Gf<F>, Gf<F>)> {
+        return points.map(|(x, y)| (Gf::new(x), Gf::new(y))).collect();
     }
 
     // Generates a polynomial from the given values. The values are (x, y) coordinate pairs.
-    pub fn interpolate_points<F: Field256>(points: &[(u8, u8)], field: &F) -> Self {
+    pub fn interpolate_points(points: &[(F::Symbol, F::Symbol)]) -> Self {
         if points.len() == 0 {
             return Self::zero();
         }
-        assert!(points.len() < 256);
+        assert!(points.len() <= F::Symbol::ORDER);
+        let points = Self::to_points(points.iter().cloned());
         return points
             .iter()
-            .map(|p| Self::single_term(points, *p, field))
-            .fold(Self::zero(), |x, y| x.add::<F>(&y));
+            .map(|p| Self::single_term(&points, *p))
+            .fold(Self::zero(), |x, y| x.add(&y));
     }
 
     // Generates a polynomial from the given values. The values are intepreted as y-values for the
     // polynomial with the x-values being their index within the vector. That is to say, for a
     // vector of n values, we would interpolate using [(0, values[0], ..., (n-1, values[n-1])].
-    pub fn interpolate<F: Field256>(ys: &[u8], field: &F) -> Self {
-        let points: Vec<_> = ys.iter().enumerate().map(|(x, y)| (x as u8, *y)).collect();
-        Self::interpolate_points(&points[..], field)
+    pub fn interpolate(ys: &[F::Symbol]) -> Self {
+        let points: Vec<_> = ys
+            .iter()
+            .enumerate()
+            .map(|(x, y)| (F::Symbol::from_usize(x), *y))
+            .collect();
+        Self::interpolate_points(&points[..])
     }
 
-    pub fn evaluate<F: Field256>(self: &Self, x: u8, field: &F) -> u8 {
-        let mut result: u8 = F::zero();
-        for (e, c) in self.coefficients.iter().enumerate() {
-            result = F::add(result, field.mul(field.exp(x, e as u8), *c));
+    // The Lagrange weights at x for the given x-values: the value at x of the polynomial through
+    // points (xs[i], ys[i]) is the sum of weights[i] * ys[i]. This takes O(k^2) for k x-values,
+    // after which each set of y-values costs O(k), so it is computed once and reused for every
+    // column of shares with the same indices. The x-values must be distinct, as no polynomial
+    // passes through two points with the same x.
+    pub fn lagrange_weights(xs: &[F::Symbol], x: F::Symbol) -> Result<Vec<Gf<F>>, String> {
+        for (i, xi) in xs.iter().enumerate() {
+            if xs[..i].contains(xi) {
+                return Err(format!("The x-value {} is repeated.", xi));
+            }
         }
+        let x = Gf::<F>::new(x);
+        return Ok(xs
+            .iter()
+            .enumerate()
+            .map(|(i, xi)| {
+                let xi = Gf::<F>::new(*xi);
+                //  ___
+                // |   | (x - xj)
+                // |   | ---------
+                // |   | (xi - xj)
+                // j /= i
+                let mut numerator = Gf::one();
+                let mut denominator = Gf::one();
+                for (_, xj) in xs.iter().enumerate().filter(|(j, _)| *j != i) {
+                    let xj = Gf::new(*xj);
+                    numerator *= x - xj;
+                    denominator *= xi - xj;
+                }
+                return numerator / denominator;
+            })
+            .collect());
+    }
 
-        return result;
+    // Evaluates the polynomial through the given (x, y) points at x, without building it.
+    pub fn interpolate_at(
+        points: &[(F::Symbol, F::Symbol)],
+        x: F::Symbol,
+    ) -> Result<F::Symbol, String> {
+        let xs: Vec<_> = points.iter().map(|(x, _)| *x).collect();
+        let result: Gf<F> = Self::lagrange_weights(&xs, x)?
+            .iter()
+            .zip(points)
+            .map(|(w, (_, y))| *w * Gf::new(*y))
+            .sum();
+        return Ok(result.value());
+    }
+
+    // Evaluates the Polynomial at x by Horner's method.
+    pub fn evaluate(self: &Self, x: F::Symbol) -> F::Symbol {
+        return self.evaluate_gf(Gf::new(x)).value();
+    }
+
+    pub fn evaluate_gf(&self, x: Gf<F>) -> Gf<F> {
+        return self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(Gf::zero(), |acc, c| acc * x + *c);
+    }
+}
+
+impl<'a, F: Field> Add<&'a Polynomial<F>> for &'a Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn add(self, other: Self) -> Polynomial<F> {
+        return Polynomial::add(self, other);
+    }
+}
+
+impl<'a, F: Field> Sub<&'a Polynomial<F>> for &'a Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn sub(self, other: Self) -> Polynomial<F> {
+        return Polynomial::sub(self, other);
+    }
+}
+
+impl<'a, F: Field> Mul<&'a Polynomial<F>> for &'a Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn mul(self, other: Self) -> Polynomial<F> {
+        return Polynomial::mul(self, other);
+    }
+}
+
+// The quotient of long division. Panics when dividing by the zero Polynomial.
+impl<'a, F: Field> Div<&'a Polynomial<F>> for &'a Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn div(self, other: Self) -> Polynomial<F> {
+        return self.div_rem(other).0;
+    }
+}
+
+// The remainder of long division. Panics when dividing by the zero Polynomial.
+impl<'a, F: Field> Rem<&'a Polynomial<F>> for &'a Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn rem(self, other: Self) -> Polynomial<F> {
+        return self.div_rem(other).1;
+    }
+}
+
+impl<'a, F: Field> Neg for &'a Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn neg(self) -> Polynomial<F> {
+        return Polynomial::neg(self);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::field::ExpLogField65536;
     use crate::finite_field::DirectField;
+    use crate::prime_field::Secp256k1Field;
+
+    type P = Polynomial<DirectField>;
 
     #[test]
     fn degree_zero() {
-        let p = Polynomial::zero();
+        let p = P::zero();
         assert_eq!(p.degree(), -1);
     }
 
     #[test]
     fn degree_constant() {
-        let p = Polynomial::from_bytes(&[1]);
+        let p = P::from_bytes(&[1]);
         assert_eq!(p.degree(), 0);
     }
 
     #[test]
     fn degree_linear() {
-        let p = Polynomial::from_bytes(&[0, 1]);
+        let p = P::from_bytes(&[0, 1]);
         assert_eq!(p.degree(), 1);
     }
 
     #[test]
     fn is_zero_true() {
-        let p = Polynomial::from_bytes(&[]);
+        let p = P::from_bytes(&[]);
         assert_eq!(p.is_zero(), true);
     }
 
     #[test]
     fn is_zero_false() {
-        let p = Polynomial::from_bytes(&[1]);
+        let p = P::from_bytes(&[1]);
         assert_eq!(p.is_zero(), false);
     }
 
     #[test]
     fn evaluate_zero() {
-        let p = Polynomial::zero();
-        let direct = DirectField::default();
-        assert_eq!(p.evaluate(5, &direct), 0);
+        let p = P::zero();
+        assert_eq!(p.evaluate(5), 0);
     }
 
     #[test]
     fn add_zero() {
-        assert_eq!(
-            Polynomial::zero().add::<DirectField>(&Polynomial::zero()),
-            Polynomial::zero()
-        );
+        assert_eq!(P::zero().add(&P::zero()), P::zero());
     }
 
     #[test]
     fn add_zero_anything() {
-        let zero = Polynomial::zero();
-        let anything = Polynomial::from_bytes(&[5, 8, 10]);
-        assert_eq!(zero.add::<DirectField>(&anything), anything);
+        let zero = P::zero();
+        let anything = P::from_bytes(&[5, 8, 10]);
+        assert_eq!(zero.add(&anything), anything);
     }
 
     #[test]
     fn mul_zero_anything() {
-        let direct = DirectField::default();
-        assert_eq!(
-            Polynomial::zero().mul(&Polynomial::zero(), &direct),
-            Polynomial::zero()
-        );
-        assert_eq!(
-            Polynomial::zero().mul(&Polynomial::from_bytes(&[1]), &direct),
-            Polynomial::zero()
-        );
-        assert_eq!(
-            Polynomial::zero().mul(&Polynomial::from_bytes(&[1, 2, 3]), &direct),
-            Polynomial::zero()
-        );
-        assert_eq!(
-            Polynomial::from_bytes(&[1, 2, 3]).mul(&Polynomial::zero(), &direct),
-            Polynomial::zero()
-        );
+        assert_eq!(P::zero().mul(&P::zero()), P::zero());
+        assert_eq!(P::zero().mul(&P::from_bytes(&[1])), P::zero());
+        assert_eq!(P::zero().mul(&P::from_bytes(&[1, 2, 3])), P::zero());
+        assert_eq!(P::from_bytes(&[1, 2, 3]).mul(&P::zero()), P::zero());
     }
 
     #[test]
     fn single_term_constant() {
-        let direct = DirectField::default();
-        let p = Polynomial::single_term_ys(&[5], 0, &direct);
-        assert_eq!(p, Polynomial::from_bytes(&[5]));
-        assert_eq!(p.evaluate(0, &direct), 5);
-        assert_eq!(p.evaluate(1, &direct), 5);
-        assert_eq!(p.evaluate(2, &direct), 5);
+        let p = P::single_term_ys(&[5], 0);
+        assert_eq!(p, P::from_bytes(&[5]));
+        assert_eq!(p.evaluate(0), 5);
+        assert_eq!(p.evaluate(1), 5);
+        assert_eq!(p.evaluate(2), 5);
     }
 
     #[test]
     fn single_term_linear() {
-        let direct = DirectField::default();
-        let p0 = Polynomial::single_term_ys(&[1, 2], 0, &direct);
-        let p1 = Polynomial::single_term_ys(&[1, 2], 1, &direct);
-        assert_eq!(p0.evaluate(0, &direct), 1);
-        assert_eq!(p1.evaluate(1, &direct), 2);
+        let p0 = P::single_term_ys(&[1, 2], 0);
+        let p1 = P::single_term_ys(&[1, 2], 1);
+        assert_eq!(p0.evaluate(0), 1);
+        assert_eq!(p1.evaluate(1), 2);
     }
 
     #[test]
     fn interpolate_same() {
-        let direct = DirectField::default();
-        let p0 = Polynomial::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF], &direct);
-        let p1 =
-            Polynomial::interpolate_points(&[(0, 0xDE), (1, 0xAD), (2, 0xBE), (3, 0xEF)], &direct);
+        let p0 = P::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let p1 = P::interpolate_points(&[(0, 0xDE), (1, 0xAD), (2, 0xBE), (3, 0xEF)]);
         assert_eq!(p0, p1);
     }
 
     #[test]
     fn evaluate_interpolated_initial_gives_initial() {
-        let direct = DirectField::default();
-        let p = Polynomial::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF], &direct);
-        assert_eq!(0xDE, p.evaluate(0, &direct));
-        assert_eq!(0xAD, p.evaluate(1, &direct));
-        assert_eq!(0xBE, p.evaluate(2, &direct));
-        assert_eq!(0xEF, p.evaluate(3, &direct));
+        let p = P::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(0xDE, p.evaluate(0));
+        assert_eq!(0xAD, p.evaluate(1));
+        assert_eq!(0xBE, p.evaluate(2));
+        assert_eq!(0xEF, p.evaluate(3));
     }
 
     #[test]
     fn evaluate_interpolated_after() {
-        let direct = DirectField::default();
-        let p0 = Polynomial::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF], &direct);
-        let p1 =
-            Polynomial::interpolate_points(&[(0, 0xDE), (1, 0xAD), (2, 0xBE), (3, 0xEF)], &direct);
-        assert_eq!(p0.evaluate(4, &direct), p1.evaluate(4, &direct));
+        let p0 = P::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let p1 = P::interpolate_points(&[(0, 0xDE), (1, 0xAD), (2, 0xBE), (3, 0xEF)]);
+        assert_eq!(p0.evaluate(4), p1.evaluate(4));
     }
 
     #[test]
     fn evaluate_forget_evaluate() {
-        let direct = DirectField::default();
-        let p0 = Polynomial::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF], &direct);
-        let e = p0.evaluate(4, &direct);
-        let p1 =
-            Polynomial::interpolate_points(&[(0, 0xDE), (1, 0xAD), (2, 0xBE), (4, e)], &direct);
+        let p0 = P::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let e = p0.evaluate(4);
+        let p1 = P::interpolate_points(&[(0, 0xDE), (1, 0xAD), (2, 0xBE), (4, e)]);
         assert_eq!(p0, p1);
     }
 
     #[test]
     fn evaluate_forget_more_evaluate() {
-        let direct = DirectField::default();
-        let p = Polynomial::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF], &direct);
-        let points: Vec<_> = (4..8).map(|x| (x, p.evaluate(x, &direct))).collect();
-        assert_eq!(p, Polynomial::interpolate_points(&points, &direct));
+        let p = P::interpolate(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let points: Vec<_> = (4..8).map(|x| (x, p.evaluate(x))).collect();
+        assert_eq!(p, P::interpolate_points(&points));
+    }
+
+    #[test]
+    fn add_cancels_to_zero() {
+        let p = P::from_bytes(&[1, 2, 3]);
+        assert_eq!(&p + &p, P::zero());
+        assert_eq!((&p - &p).degree(), -1);
+        assert_eq!(P::from_bytes(&[1, 0, 0]).degree(), 0);
+    }
+
+    #[test]
+    fn evaluate_horner() {
+        // 3 + 2x + x^2 at x = 2 is 3 ^ 4 ^ 4.
+        let p = P::from_bytes(&[3, 2, 1]);
+        assert_eq!(p.evaluate(2), 3);
+        assert_eq!(p.evaluate(0), 3);
+    }
+
+    fn div_rem_for<F: Field>(ps: &[Polynomial<F>]) {
+        for p in ps {
+            for d in ps.iter().filter(|d| !d.is_zero()) {
+                let (q, r) = p.div_rem(d);
+                assert!(r.degree() < d.degree());
+                assert_eq!(&(&q * d) + &r, *p);
+                assert_eq!(p / d, q);
+                assert_eq!(p % d, r);
+            }
+        }
+    }
+
+    fn gf<F: Field>(xs: &[u8]) -> Polynomial<F> {
+        let coefficients: Vec<_> = xs.iter().map(|x| Gf::from(*x)).collect();
+        return Polynomial::from_coefficients(&coefficients);
+    }
+
+    fn samples<F: Field>() -> Vec<Polynomial<F>> {
+        return vec![
+            Polynomial::zero(),
+            gf(&[7]),
+            gf(&[1, 1]),
+            gf(&[3, 0, 5]),
+            gf(&[9, 8, 7, 6, 5]),
+            gf(&[0, 0, 0, 200, 0, 1]),
+        ];
+    }
+
+    #[test]
+    fn div_rem() {
+        div_rem_for(&samples::<DirectField>());
+        div_rem_for(&samples::<ExpLogField65536>());
+        div_rem_for(&samples::<Secp256k1Field>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero() {
+        let _ = &P::one() / &P::zero();
+    }
+
+    fn gcd_for<F: Field>() {
+        // (x - 1)(x - 2) and (x - 1)(x - 3)(x - 4) share only x - 1.
+        let root = |r: u8| &Polynomial::<F>::x() - &Polynomial::constant(Gf::from(r));
+        let a = &root(1) * &root(2);
+        let b = &(&root(1) * &root(3)) * &root(4);
+        assert_eq!(a.gcd(&b), root(1));
+        assert_eq!(b.gcd(&a), root(1));
+        assert_eq!(a.scale(Gf::from(5)).gcd(&a), a);
+        assert_eq!(a.gcd(&Polynomial::zero()), a);
+        assert_eq!(root(2).gcd(&root(3)), Polynomial::one());
+        assert_eq!(
+            Polynomial::<F>::zero().gcd(&Polynomial::zero()),
+            Polynomial::zero()
+        );
+    }
+
+    #[test]
+    fn gcd() {
+        gcd_for::<DirectField>();
+        gcd_for::<ExpLogField65536>();
+        gcd_for::<Secp256k1Field>();
+    }
+
+    #[test]
+    fn derivative() {
+        // In characteristic 2, d/dx (1 + x + x^2 + x^3) is 1 + 3x^2, which is 1 + x^2.
+        assert_eq!(
+            P::from_bytes(&[1, 1, 1, 1]).derivative(),
+            P::from_bytes(&[1, 0, 1])
+        );
+        assert_eq!(P::from_bytes(&[5]).derivative(), P::zero());
+        // Over a prime field, d/dx (1 + x + x^2 + x^3) is 1 + 2x + 3x^2.
+        assert_eq!(
+            gf::<Secp256k1Field>(&[1, 1, 1, 1]).derivative(),
+            gf(&[1, 2, 3])
+        );
+    }
+
+    fn compose_for<F: Field>() {
+        let ps = samples::<F>();
+        for p in &ps {
+            for q in &ps {
+                let composed = p.compose(q);
+                for x in 0..5 {
+                    let x = Gf::from(x);
+                    assert_eq!(composed.evaluate_gf(x), p.evaluate_gf(q.evaluate_gf(x)));
+                }
+            }
+        }
+        assert_eq!(ps[4].compose(&Polynomial::x()), ps[4]);
+    }
+
+    #[test]
+    fn compose() {
+        compose_for::<DirectField>();
+        compose_for::<Secp256k1Field>();
+    }
+
+    fn interpolate_for<F: Field>() {
+        // An even number of points checks the signs of the terms in odd characteristic.
+        for n in 1..6 {
+            let ys: Vec<F::Symbol> = (0..n).map(|y| F::Symbol::from(3 * y + 1)).collect();
+            let p = Polynomial::<F>::interpolate(&ys);
+            assert!(p.degree() < n as i64);
+            for (x, y) in ys.iter().enumerate() {
+                assert_eq!(p.evaluate(F::Symbol::from_usize(x)), *y);
+            }
+        }
+    }
+
+    #[test]
+    fn interpolate() {
+        interpolate_for::<DirectField>();
+        interpolate_for::<ExpLogField65536>();
+        interpolate_for::<Secp256k1Field>();
+    }
+
+    #[test]
+    fn operators() {
+        let p = P::from_bytes(&[1, 2]);
+        let q = P::from_bytes(&[3]);
+        assert_eq!(&p + &q, P::from_bytes(&[2, 2]));
+        assert_eq!(&p - &q, P::from_bytes(&[2, 2]));
+        assert_eq!(&p * &q, p.scale(Gf::from(3)));
+        assert_eq!(-&p, p);
+        let one = gf::<Secp256k1Field>(&[1]);
+        assert_eq!(&-&one + &one, Polynomial::zero());
+        assert_eq!(&(&p * &q) / &q, p);
+        assert_eq!(&(&(&p * &q) + &P::one()) % &p, P::one());
+    }
+
+    fn interpolate_at_for<F: Field>() {
+        let points: Vec<(F::Symbol, F::Symbol)> = [(1, 0xDE), (2, 0xAD), (5, 0xBE), (9, 0xEF)]
+            .iter()
+            .map(|(x, y)| (F::Symbol::from(*x), F::Symbol::from(*y)))
+            .collect();
+        let p = Polynomial::<F>::interpolate_points(&points);
+        for x in 0..12 {
+            let x = F::Symbol::from(x);
+            assert_eq!(
+                Polynomial::<F>::interpolate_at(&points, x),
+                Ok(p.evaluate(x))
+            );
+        }
+        // At one of the points, only its weight is non-zero.
+        let mut xs: Vec<_> = points.iter().map(|(x, _)| *x).collect();
+        let weights = Polynomial::<F>::lagrange_weights(&xs, xs[2]);
+        assert_eq!(
+            weights,
+            Ok(vec![Gf::zero(), Gf::zero(), Gf::one(), Gf::zero()])
+        );
+        xs[3] = xs[1];
+        assert!(Polynomial::<F>::lagrange_weights(&xs, F::zero()).is_err());
+    }
+
+    #[test]
+    fn interpolate_at() {
+        interpolate_at_for::<DirectField>();
+        interpolate_at_for::<ExpLogField65536>();
+        interpolate_at_for::<Secp256k1Field>();
     }
 }
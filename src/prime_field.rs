@@ -0,0 +1,331 @@
+// Prime fields, GF(p), for sharing secrets which are scalars modulo a prime (such as elliptic curve
+// private keys) as scalars, rather than byte by byte in GF(2^8). Arithmetic is done with big
+// integers, so any prime up to MAX_BYTES bytes can be used, and the orders of the common 255 and
+// 256 bit curves are provided.
+//
+// Elements are stored in a fixed size buffer so that, like every other Symbol, they can be copied,
+// which lets Gf, Matrix and Polynomial work over these fields unchanged. The big integers used to
+// compute with them are not zeroized.
+
+use crate::field::{Field, Symbol};
+use crate::secret::zeroize_vec;
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+// The largest modulus supported, in bytes.
+pub const MAX_BYTES: usize = 64;
+
+// The prime modulus of a field. The field is a type, like the polynomial of GenericExpLogField, so
+// Gf can create it from the type alone.
+pub trait Modulus: Default + Copy + fmt::Debug + Eq + Send + Sync {
+    // The prime, in hex. It is not checked to be prime.
+    const PRIME: &'static str;
+
+    // Where the parsed prime is kept. A static in a generic function is shared by every type, so
+    // each modulus has its own.
+    fn cache() -> &'static OnceLock<BigUint>;
+
+    // The prime, parsed on first use like the GF(2^16) tables, since every operation needs it.
+    fn prime() -> &'static BigUint {
+        return Self::cache().get_or_init(|| {
+            BigUint::parse_bytes(Self::PRIME.as_bytes(), 16).expect("The prime must be hex")
+        });
+    }
+
+    // The number of bytes needed to write any element.
+    fn bytes() -> usize {
+        return ((Self::prime().bits() + 7) / 8) as usize;
+    }
+}
+
+// The order of the secp256k1 group (used by Bitcoin and Ethereum keys).
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Secp256k1Order;
+
+impl Modulus for Secp256k1Order {
+    const PRIME: &'static str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+    fn cache() -> &'static OnceLock<BigUint> {
+        static PRIME: OnceLock<BigUint> = OnceLock::new();
+        return &PRIME;
+    }
+}
+
+// The order of the NIST P-256 (secp256r1) group.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct P256Order;
+
+impl Modulus for P256Order {
+    const PRIME: &'static str = "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+    fn cache() -> &'static OnceLock<BigUint> {
+        static PRIME: OnceLock<BigUint> = OnceLock::new();
+        return &PRIME;
+    }
+}
+
+// The order of the prime order subgroup of Curve25519 (used by Ed25519 and X25519 keys),
+// 2^252 + 27742317777372353535851937790883648493.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Curve25519Order;
+
+impl Modulus for Curve25519Order {
+    const PRIME: &'static str = "1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed";
+    fn cache() -> &'static OnceLock<BigUint> {
+        static PRIME: OnceLock<BigUint> = OnceLock::new();
+        return &PRIME;
+    }
+}
+
+// A non-negative integer below 2^(8 * MAX_BYTES), little-endian. Elements of a prime field are
+// always reduced, so equal elements have equal representations.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Residue([u8; MAX_BYTES]);
+
+impl Residue {
+    fn from_biguint(x: &BigUint) -> Residue {
+        let bytes = x.to_bytes_le();
+        assert!(bytes.len() <= MAX_BYTES, "The residue is too large");
+        let mut residue = [0; MAX_BYTES];
+        residue[..bytes.len()].copy_from_slice(&bytes);
+        return Residue(residue);
+    }
+
+    fn to_biguint(&self) -> BigUint {
+        return BigUint::from_bytes_le(&self.0);
+    }
+
+    // Reads a big-endian integer, which must be less than the modulus of M.
+    pub fn from_bytes_be<M: Modulus>(bytes: &[u8]) -> Result<Residue, String> {
+        let x = BigUint::from_bytes_be(bytes);
+        if &x >= M::prime() {
+            return Err(String::from("The value must be less than the modulus"));
+        }
+        return Ok(Residue::from_biguint(&x));
+    }
+
+    // A uniformly random element modulo the prime of M. 128 bits more than needed are reduced, so
+    // the bias is negligible.
+    pub fn random<M: Modulus, R: RngCore + CryptoRng>(rng: &mut R) -> Residue {
+        let mut bytes = vec![0; M::bytes() + 16];
+        rng.fill_bytes(&mut bytes);
+        let residue = Residue::reduce_bytes_be::<M>(&bytes);
+        zeroize_vec(&mut bytes);
+        return residue;
+    }
+
+    // Reads a big-endian integer of any size modulo the prime of M, so that random bytes (at least
+    // 16 more than the prime's, as in random) give a random element.
+    pub fn reduce_bytes_be<M: Modulus>(bytes: &[u8]) -> Residue {
+        return Residue::from_biguint(&(BigUint::from_bytes_be(bytes) % M::prime()));
+    }
+
+    // Writes the integer big-endian, padded with zeros to length bytes.
+    pub fn to_bytes_be(&self, length: usize) -> Vec<u8> {
+        let bytes = self.to_biguint().to_bytes_be();
+        assert!(
+            bytes.len() <= length,
+            "The residue does not fit in {} bytes",
+            length
+        );
+        let mut padded = vec![0; length - bytes.len()];
+        padded.extend(bytes);
+        return padded;
+    }
+}
+
+impl Default for Residue {
+    fn default() -> Self {
+        return Residue([0; MAX_BYTES]);
+    }
+}
+
+impl From<u8> for Residue {
+    fn from(x: u8) -> Self {
+        let mut residue = [0; MAX_BYTES];
+        residue[0] = x;
+        return Residue(residue);
+    }
+}
+
+impl fmt::Display for Residue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.to_biguint());
+    }
+}
+
+impl fmt::Debug for Residue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "Residue({})", self.to_biguint());
+    }
+}
+
+// The fields are all far larger than any code, so the order saturates.
+impl Symbol for Residue {
+    const ORDER: usize = usize::MAX;
+
+    fn from_usize(x: usize) -> Self {
+        return Residue::from_biguint(&BigUint::from(x));
+    }
+
+    fn to_usize(self) -> usize {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.0[..8]);
+        return u64::from_le_bytes(bytes) as usize;
+    }
+}
+
+// The integers modulo the prime of M.
+pub struct PrimeField<M: Modulus>(PhantomData<M>);
+
+// The shards only need the field through shamir_scalar, so the alias is for the generic tests.
+#[cfg(test)]
+pub type Secp256k1Field = PrimeField<Secp256k1Order>;
+
+impl<M: Modulus> Clone for PrimeField<M> {
+    fn clone(&self) -> Self {
+        return *self;
+    }
+}
+
+impl<M: Modulus> Copy for PrimeField<M> {}
+
+impl<M: Modulus> PartialEq for PrimeField<M> {
+    fn eq(&self, _other: &Self) -> bool {
+        return true;
+    }
+}
+
+impl<M: Modulus> Eq for PrimeField<M> {}
+
+impl<M: Modulus> Default for PrimeField<M> {
+    fn default() -> Self {
+        return PrimeField(PhantomData);
+    }
+}
+
+impl<M: Modulus> fmt::Debug for PrimeField<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "PrimeField({:?})", M::default());
+    }
+}
+
+impl<M: Modulus> Field for PrimeField<M> {
+    type Symbol = Residue;
+
+    fn add(x: Residue, y: Residue) -> Residue {
+        return Residue::from_biguint(&((x.to_biguint() + y.to_biguint()) % M::prime()));
+    }
+
+    fn sub(x: Residue, y: Residue) -> Residue {
+        let p = M::prime();
+        return Residue::from_biguint(&((x.to_biguint() + p - y.to_biguint()) % p));
+    }
+
+    fn neg(x: Residue) -> Residue {
+        return Self::sub(Self::zero(), x);
+    }
+
+    fn mul(&self, x: Residue, y: Residue) -> Residue {
+        return Residue::from_biguint(&((x.to_biguint() * y.to_biguint()) % M::prime()));
+    }
+
+    // Panics when dividing by zero, like the other fields.
+    fn div(&self, x: Residue, y: Residue) -> Residue {
+        if y == Self::zero() {
+            panic!("Cannot divide by zero!");
+        }
+        return self.mul(x, self.inv(y));
+    }
+
+    fn exp(&self, x: Residue, y: Residue) -> Residue {
+        return Residue::from_biguint(&x.to_biguint().modpow(&y.to_biguint(), M::prime()));
+    }
+
+    // By Fermat's little theorem, x ^ (p - 2) * x == x ^ (p - 1) == 1.
+    fn inv(&self, x: Residue) -> Residue {
+        let p = M::prime();
+        let e = p - BigUint::from(2u8);
+        return Residue::from_biguint(&x.to_biguint().modpow(&e, p));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    use super::*;
+    use crate::field::Gf;
+
+    // A small prime, so that the results are easy to check.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+    struct Small;
+
+    impl Modulus for Small {
+        const PRIME: &'static str = "101";
+
+        fn cache() -> &'static OnceLock<BigUint> {
+            static PRIME: OnceLock<BigUint> = OnceLock::new();
+            return &PRIME;
+        }
+    }
+
+    type G = Gf<PrimeField<Small>>;
+
+    #[test]
+    fn small_field_arithmetic() {
+        // 0x101 == 257
+        assert_eq!(G::from(200) + G::from(100), G::from(43));
+        assert_eq!(
+            G::from(100) - G::from(200),
+            G::new(Residue::from_usize(157))
+        );
+        assert_eq!(-G::from(1), G::new(Residue::from_usize(256)));
+        assert_eq!(G::from(16) * G::from(17), G::from(15));
+        assert_eq!(G::from(2).pow(Residue::from(8)), G::one() - G::from(2));
+        for x in 1..=255 {
+            let x = G::from(x);
+            assert_eq!(x * x.inv(), G::one());
+            assert_eq!((x / G::from(3)) * G::from(3), x);
+        }
+    }
+
+    fn field_axioms_for<M: Modulus>() {
+        type G<M> = Gf<PrimeField<M>>;
+        let p = M::prime().to_bytes_be();
+        for _ in 0..20 {
+            // Random values below the prime, by clearing the top byte.
+            let mut bytes: Vec<u8> = p.iter().map(|_| rand::random::<u8>()).collect();
+            bytes[0] = 0;
+            let x = G::<M>::new(Residue::from_bytes_be::<M>(&bytes).unwrap());
+            bytes[1] ^= 0xff;
+            let y = G::<M>::new(Residue::from_bytes_be::<M>(&bytes).unwrap());
+            assert_eq!(x * y, y * x);
+            assert_eq!(x + y - y, x);
+            assert_eq!(x + -x, G::<M>::zero());
+            assert_eq!(x * y.inv() * y, x);
+            assert_eq!((x + y) * y, x * y + y * y);
+        }
+        // p - 1 is -1.
+        let minus_one = G::<M>::new(Residue::from_biguint(&(M::prime() - 1u8)));
+        assert_eq!(minus_one, -G::<M>::one());
+        assert_eq!(minus_one * minus_one, G::<M>::one());
+    }
+
+    #[test]
+    fn field_axioms() {
+        field_axioms_for::<Secp256k1Order>();
+        field_axioms_for::<P256Order>();
+        field_axioms_for::<Curve25519Order>();
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let bytes = [0x12, 0x34, 0x56];
+        let x = Residue::from_bytes_be::<Secp256k1Order>(&bytes).unwrap();
+        assert_eq!(x.to_bytes_be(32)[29..], bytes);
+        assert_eq!(x.to_bytes_be(32)[..29], [0; 29]);
+        assert!(Residue::from_bytes_be::<Secp256k1Order>(&[0xff; 32]).is_err());
+        assert!(Residue::from_bytes_be::<Small>(&[0x01, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+// Multiplication of whole regions (slices) of GF(2^8) elements by a constant.
+//
+// Multiplication by a constant c is linear over addition (XOR), so splitting each element x into
+// its high and low nibbles gives c * x == c * (high << 4) ^ c * low. Both products only have 16
+// possible values, so two 16 byte tables hold every product and each element needs two lookups.
+// 16 byte tables are exactly what the PSHUFB instruction (SSSE3, and AVX2 for 32 bytes at once)
+// looks up in, for every byte of a register in parallel.
+//
+// The tables only depend on the constant, not on the data. The SIMD lookups are done in registers,
+// so their timing doesn't depend on the data, but the scalar fallback indexes memory with it.
+
+use crate::finite_field::Field256;
+
+pub struct NibbleTables {
+    // low[i] == c * i
+    low: [u8; 16],
+    // high[i] == c * (i << 4)
+    high: [u8; 16],
+}
+
+impl NibbleTables {
+    pub fn new<F: Field256>(field: &F, c: u8) -> NibbleTables {
+        let mut tables = NibbleTables {
+            low: [0; 16],
+            high: [0; 16],
+        };
+        for i in 0..16 {
+            tables.low[i as usize] = field.mul(c, i);
+            tables.high[i as usize] = field.mul(c, i << 4);
+        }
+        return tables;
+    }
+
+    fn mul(&self, x: u8) -> u8 {
+        return self.low[(x & 0x0f) as usize] ^ self.high[(x >> 4) as usize];
+    }
+}
+
+// Sets dst[i] ^= c * src[i], using the widest instructions the CPU supports. The field must be a
+// GF(2^8) field, with XOR as its addition.
+pub fn mul_add_region<F: Field256>(field: &F, c: u8, src: &[u8], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    if c == 0 {
+        return;
+    }
+    if c == 1 {
+        for (s, d) in src.iter().zip(dst.iter_mut()) {
+            *d ^= *s;
+        }
+        return;
+    }
+    let tables = NibbleTables::new(field, c);
+    let mut done = 0;
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            done = unsafe { mul_add_avx2(&tables, src, dst) };
+        } else if is_x86_feature_detected!("ssse3") {
+            done = unsafe { mul_add_ssse3(&tables, src, dst) };
+        }
+    }
+    mul_add_scalar(&tables, &src[done..], &mut dst[done..]);
+}
+
+fn mul_add_scalar(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.iter().zip(dst.iter_mut()) {
+        *d ^= tables.mul(*s);
+    }
+}
+
+// Handles whole 16 byte blocks and returns how many bytes were done.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn mul_add_ssse3(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) -> usize {
+    use std::arch::x86_64::*;
+    let low = _mm_loadu_si128(tables.low.as_ptr() as *const __m128i);
+    let high = _mm_loadu_si128(tables.high.as_ptr() as *const __m128i);
+    let mask = _mm_set1_epi8(0x0f);
+    let blocks = src.len() / 16 * 16;
+    for i in (0..blocks).step_by(16) {
+        let s = _mm_loadu_si128(src.as_ptr().add(i) as *const __m128i);
+        let d = _mm_loadu_si128(dst.as_ptr().add(i) as *const __m128i);
+        // There is no byte shift, so shift 64 bit lanes and mask off the bits of the next byte.
+        let s_low = _mm_and_si128(s, mask);
+        let s_high = _mm_and_si128(_mm_srli_epi64(s, 4), mask);
+        let product = _mm_xor_si128(_mm_shuffle_epi8(low, s_low), _mm_shuffle_epi8(high, s_high));
+        _mm_storeu_si128(
+            dst.as_mut_ptr().add(i) as *mut __m128i,
+            _mm_xor_si128(d, product),
+        );
+    }
+    return blocks;
+}
+
+// Handles whole 32 byte blocks and returns how many bytes were done. PSHUFB looks up in each 128
+// bit half separately, so both halves get a copy of the tables.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn mul_add_avx2(tables: &NibbleTables, src: &[u8], dst: &mut [u8]) -> usize {
+    use std::arch::x86_64::*;
+    let low = _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.low.as_ptr() as *const __m128i));
+    let high = _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.high.as_ptr() as *const __m128i));
+    let mask = _mm256_set1_epi8(0x0f);
+    let blocks = src.len() / 32 * 32;
+    for i in (0..blocks).step_by(32) {
+        let s = _mm256_loadu_si256(src.as_ptr().add(i) as *const __m256i);
+        let d = _mm256_loadu_si256(dst.as_ptr().add(i) as *const __m256i);
+        let s_low = _mm256_and_si256(s, mask);
+        let s_high = _mm256_and_si256(_mm256_srli_epi64(s, 4), mask);
+        let product = _mm256_xor_si256(
+            _mm256_shuffle_epi8(low, s_low),
+            _mm256_shuffle_epi8(high, s_high),
+        );
+        _mm256_storeu_si256(
+            dst.as_mut_ptr().add(i) as *mut __m256i,
+            _mm256_xor_si256(d, product),
+        );
+    }
+    return blocks;
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    use super::*;
+    use crate::finite_field::DirectField;
+
+    // dst ^ c * src, one element at a time.
+    fn expected(c: u8, src: &[u8], dst: &[u8]) -> Vec<u8> {
+        let direct = DirectField::default();
+        return src
+            .iter()
+            .zip(dst.iter())
+            .map(|(s, d)| d ^ direct.mul(c, *s))
+            .collect();
+    }
+
+    #[test]
+    fn nibble_tables_agree_with_mul() {
+        let direct = DirectField::default();
+        for c in 0..=255 {
+            let tables = NibbleTables::new(&direct, c);
+            for x in 0..=255 {
+                assert_eq!(tables.mul(x), direct.mul(c, x));
+            }
+        }
+    }
+
+    #[test]
+    fn mul_add_region_agrees_with_mul() {
+        let direct = DirectField::default();
+        // Lengths around the 16 and 32 byte blocks, to cover the scalar remainder.
+        for length in &[0, 1, 15, 16, 17, 31, 32, 33, 100] {
+            let src: Vec<u8> = (0..*length).map(|_| rand::random::<u8>()).collect();
+            let dst: Vec<u8> = (0..*length).map(|_| rand::random::<u8>()).collect();
+            for c in 0..=255 {
+                let mut out = dst.clone();
+                mul_add_region(&direct, c, &src, &mut out);
+                assert_eq!(out, expected(c, &src, &dst));
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn simd_agrees_with_scalar() {
+        let direct = DirectField::default();
+        let src: Vec<u8> = (0..=255).collect();
+        let dst: Vec<u8> = (0..256).map(|_| rand::random::<u8>()).collect();
+        for c in 0..=255 {
+            let tables = NibbleTables::new(&direct, c);
+            if is_x86_feature_detected!("ssse3") {
+                let mut out = dst.clone();
+                assert_eq!(unsafe { mul_add_ssse3(&tables, &src, &mut out) }, 256);
+                assert_eq!(out, expected(c, &src, &dst));
+            }
+            if is_x86_feature_detected!("avx2") {
+                let mut out = dst.clone();
+                assert_eq!(unsafe { mul_add_avx2(&tables, &src, &mut out) }, 256);
+                assert_eq!(out, expected(c, &src, &dst));
+            }
+        }
+    }
+}
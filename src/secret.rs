@@ -0,0 +1,334 @@
+// Buffers for secret material.
+//
+// `Secret` holds a secret (generated, read from the user or restored), `ShareBytes` holds the
+// bytes of a share or of an intermediate value from which the secret could be computed, and
+// `Phrase` holds the text of a shard or secret phrase. All are `Protected` buffers, which are
+// zeroized when dropped, never show their contents in `Debug` output and, on Linux, are locked
+// into memory (so they are not swapped out) and excluded from core dumps. Locking is best effort:
+// it fails beyond RLIMIT_MEMLOCK, and the buffer is still zeroized.
+//
+// The buffers never grow, so their memory is never reallocated and left behind unwiped.
+
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+// Overwrites the bytes (or other integers, such as field symbols) with zeros in a way the compiler
+// will not optimize away.
+pub fn zeroize<T: Copy + Default>(bytes: &mut [T]) {
+    for b in bytes.iter_mut() {
+        unsafe { std::ptr::write_volatile(b, T::default()) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+// Zeroizes the whole allocation of the vector, including any spare capacity.
+pub fn zeroize_vec<T: Copy + Default>(bytes: &mut Vec<T>) {
+    let capacity = bytes.capacity();
+    bytes.resize(capacity, T::default());
+    zeroize(bytes);
+    bytes.clear();
+}
+
+// Zeroizes text such as a typed in secret or share.
+pub fn zeroize_string(s: &mut String) {
+    // Zeros are valid UTF-8, so the string stays valid.
+    zeroize_vec(unsafe { s.as_mut_vec() });
+}
+
+// The whole pages containing the given memory, since mlock and madvise work on pages.
+#[cfg(target_os = "linux")]
+fn page_range(ptr: *const u8, len: usize) -> (*mut libc::c_void, usize) {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = ptr as usize & !(page - 1);
+    let end = (ptr as usize + len + page - 1) & !(page - 1);
+    return (start as *mut libc::c_void, end - start);
+}
+
+#[cfg(target_os = "linux")]
+fn lock(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let (start, len) = page_range(ptr, len);
+    unsafe {
+        libc::mlock(start, len);
+        libc::madvise(start, len, libc::MADV_DONTDUMP);
+    }
+}
+
+// Pages are left out of core dumps, since they may still be shared with other secrets. Locks are
+// not counted, so this may unlock a page another buffer shares, which only weakens the protection.
+#[cfg(target_os = "linux")]
+fn unlock(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let (start, len) = page_range(ptr, len);
+    unsafe {
+        libc::munlock(start, len);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lock(_ptr: *const u8, _len: usize) {}
+
+#[cfg(not(target_os = "linux"))]
+fn unlock(_ptr: *const u8, _len: usize) {}
+
+// What a protected buffer holds, which names it in Debug output.
+pub trait Contents {
+    const NAME: &'static str;
+}
+
+pub struct SecretContents;
+
+impl Contents for SecretContents {
+    const NAME: &'static str = "Secret";
+}
+
+pub struct ShareContents;
+
+impl Contents for ShareContents {
+    const NAME: &'static str = "ShareBytes";
+}
+
+pub struct PhraseContents;
+
+impl Contents for PhraseContents {
+    const NAME: &'static str = "Phrase";
+}
+
+// A locked buffer which is zeroized and unlocked when dropped.
+pub struct Protected<C: Contents> {
+    bytes: Vec<u8>,
+    contents: PhantomData<C>,
+}
+
+pub type Secret = Protected<SecretContents>;
+pub type ShareBytes = Protected<ShareContents>;
+// The text of a shard or of a restored secret phrase.
+pub type Phrase = Protected<PhraseContents>;
+
+impl<C: Contents> Protected<C> {
+    // Takes ownership of the bytes, so they are not copied.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        lock(bytes.as_ptr(), bytes.capacity());
+        return Protected {
+            bytes: bytes,
+            contents: PhantomData,
+        };
+    }
+
+    pub fn zeroed(length: usize) -> Self {
+        return Self::new(vec![0; length]);
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        return Self::new(bytes.to_vec());
+    }
+
+    pub fn expose(&self) -> &[u8] {
+        return &self.bytes;
+    }
+
+    pub fn expose_mut(&mut self) -> &mut [u8] {
+        return &mut self.bytes;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.bytes.len();
+    }
+}
+
+impl Phrase {
+    // Takes ownership of the text, so it is not copied. The CLI reads phrases with read_line
+    // instead, so only the tests build them from text.
+    #[cfg(test)]
+    pub fn from_string(s: String) -> Phrase {
+        return Phrase::new(s.into_bytes());
+    }
+
+    // An empty phrase with room for `capacity` bytes of words.
+    pub fn with_capacity(capacity: usize) -> Phrase {
+        return Phrase::new(Vec::with_capacity(capacity));
+    }
+
+    // Appends a word, after a space unless it is the first. The phrase must have room for it, since
+    // growing would leave a copy of it behind.
+    pub fn push_word(&mut self, word: &str) {
+        let separator = if self.bytes.is_empty() { 0 } else { 1 };
+        assert!(
+            self.bytes.len() + separator + word.len() <= self.bytes.capacity(),
+            "The phrase has no room for another word"
+        );
+        if separator == 1 {
+            self.bytes.push(b' ');
+        }
+        self.bytes.extend_from_slice(word.as_bytes());
+    }
+
+    // Reads a line, without its line ending, straight into a phrase of the given capacity, so that
+    // typed in shards and secrets are never held in a String that grows. A longer line is an error.
+    // The reader's own buffer (such as stdin's) still holds what was read, which this cannot wipe.
+    pub fn read_line<R: BufRead>(reader: &mut R, capacity: usize) -> io::Result<Phrase> {
+        let mut phrase = Phrase::with_capacity(capacity);
+        loop {
+            let available = match reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let (line, done) = match available.iter().position(|b| *b == b'\n') {
+                Some(end) => (&available[..end], true),
+                None => (available, available.is_empty()),
+            };
+            if phrase.bytes.len() + line.len() > capacity {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("The line is longer than {} bytes", capacity),
+                ));
+            }
+            phrase.bytes.extend_from_slice(line);
+            let used = line.len() + if line.len() < available.len() { 1 } else { 0 };
+            reader.consume(used);
+            if done {
+                break;
+            }
+        }
+        if phrase.bytes.last() == Some(&b'\r') {
+            phrase.bytes.pop();
+        }
+        if std::str::from_utf8(&phrase.bytes).is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The line is not valid UTF-8",
+            ));
+        }
+        return Ok(phrase);
+    }
+
+    pub fn expose_str(&self) -> &str {
+        // Only whole strings and words are ever added.
+        return std::str::from_utf8(&self.bytes).expect("Phrases are UTF-8");
+    }
+}
+
+impl<C: Contents> Drop for Protected<C> {
+    fn drop(&mut self) {
+        let (ptr, capacity) = (self.bytes.as_ptr(), self.bytes.capacity());
+        zeroize_vec(&mut self.bytes);
+        unlock(ptr, capacity);
+    }
+}
+
+impl<C: Contents> Clone for Protected<C> {
+    fn clone(&self) -> Self {
+        return Self::from_slice(self.expose());
+    }
+}
+
+// Compares in constant time (for a given length).
+impl<C: Contents> PartialEq for Protected<C> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.bytes.len() == other.bytes.len()
+            && self
+                .bytes
+                .iter()
+                .zip(other.bytes.iter())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0;
+    }
+}
+
+impl<C: Contents> fmt::Debug for Protected<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}([REDACTED; {}])", C::NAME, self.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = Secret::new(vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(format!("{:?}", secret), "Secret([REDACTED; 4])");
+        let share = ShareBytes::from_slice(&[1, 2, 3]);
+        assert_eq!(format!("{:?}", share), "ShareBytes([REDACTED; 3])");
+        let phrase = Phrase::from_string(String::from("two words"));
+        assert_eq!(format!("{:?}", phrase), "Phrase([REDACTED; 9])");
+    }
+
+    #[test]
+    fn phrase_push_word() {
+        let mut phrase = Phrase::with_capacity(9);
+        phrase.push_word("two");
+        phrase.push_word("words");
+        assert_eq!(phrase.expose_str(), "two words");
+    }
+
+    #[test]
+    #[should_panic]
+    fn phrase_does_not_grow() {
+        let mut phrase = Phrase::with_capacity(8);
+        phrase.push_word("two");
+        phrase.push_word("words");
+    }
+
+    #[test]
+    fn phrase_read_line() {
+        let mut input = io::Cursor::new(&b"two words\r\nthree more words\nlast"[..]);
+        let phrase = Phrase::read_line(&mut input, 16).unwrap();
+        assert_eq!(phrase.expose_str(), "two words");
+        assert_eq!(phrase.bytes.capacity(), 16);
+        let phrase = Phrase::read_line(&mut input, 16).unwrap();
+        assert_eq!(phrase.expose_str(), "three more words");
+        assert_eq!(
+            Phrase::read_line(&mut input, 16).unwrap().expose_str(),
+            "last"
+        );
+        assert_eq!(Phrase::read_line(&mut input, 16).unwrap().expose_str(), "");
+        // Lines never grow the phrase.
+        let mut input = io::Cursor::new(&b"three more words\n"[..]);
+        assert!(Phrase::read_line(&mut input, 15).is_err());
+        let mut input = io::Cursor::new(&[0xff, b'\n'][..]);
+        assert!(Phrase::read_line(&mut input, 16).is_err());
+    }
+
+    #[test]
+    fn zeroize_vec_clears_capacity() {
+        let mut bytes: Vec<u8> = Vec::with_capacity(16);
+        bytes.extend(&[0xff; 16]);
+        bytes.truncate(4);
+        zeroize_vec(&mut bytes);
+        assert!(bytes.is_empty());
+        unsafe { bytes.set_len(16) };
+        assert_eq!(bytes, vec![0; 16]);
+    }
+
+    #[test]
+    fn zeroize_string_clears_text() {
+        let mut s = String::from("correct horse battery staple");
+        let (ptr, length) = (s.as_ptr(), s.len());
+        zeroize_string(&mut s);
+        assert!(s.is_empty());
+        assert!(unsafe { std::slice::from_raw_parts(ptr, length) }
+            .iter()
+            .all(|b| *b == 0));
+    }
+
+    #[test]
+    fn equality_and_clone() {
+        let secret = Secret::from_slice(&[1, 2, 3]);
+        assert_eq!(secret.clone(), secret);
+        assert_ne!(Secret::from_slice(&[1, 2, 4]), secret);
+        assert_ne!(Secret::from_slice(&[1, 2]), secret);
+        let mut share = ShareBytes::zeroed(2);
+        share.expose_mut()[1] = 7;
+        assert_eq!(share.expose(), &[0, 7]);
+    }
+}
@@ -1,154 +1,1252 @@
-use crate::encoder::RSEncoder;
-use crate::encoder::RSStream;
-use crate::encoder::VandermondeEncoder;
+use crate::encoder::{CachedVandermondeEncoder, RSStream};
+use crate::encoder::{CauchyEncoder, LagrangeInterpolationEncoder, RSEncoder};
 use crate::encoding::Encoding;
-use crate::finite_field::ExpLogField;
+use crate::field::{DirectField65536, ExpLogField65536};
+use crate::field::{Field, Symbol};
+use crate::finite_field::{ExpLogField, SecretField, TableField};
+use crate::formats::SecretFormat;
+use crate::polynomial::Polynomial;
+use crate::prime_field::{Curve25519Order, P256Order, Secp256k1Order};
+use crate::prime_field::{Modulus, PrimeField, Residue};
+use crate::secret::{zeroize_vec, Phrase, Secret, ShareBytes};
 use crate::words;
-use rand::Rng;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::convert::TryFrom;
 use std::iter;
+use std::str::FromStr;
 
-fn gen_random_bytes(length: usize) -> Vec<u8> {
-    let mut rng = rand::thread_rng();
-    return (0..length).map(|_| rng.gen()).collect();
+pub fn gen_random_bytes<R: RngCore + CryptoRng>(rng: &mut R, length: usize) -> Vec<u8> {
+    let mut bytes = vec![0; length];
+    rng.fill_bytes(&mut bytes);
+    return bytes;
 }
 
-pub fn shamir(shards: usize, required: usize, length: usize) -> Vec<String> {
-    assert!(shards >= required);
-    println!("Shards: {}, required: {}", shards, required);
-    let wordlist = words::load_static_word_list();
+// The encoding of a split into `shards` shards (including the secret) of which `required` are
+// needed.
+pub fn shard_encoding(shards: usize, required: usize) -> Result<Encoding, String> {
+    if required == 0 || shards < required {
+        return Err(String::from(
+            "At least one shard must be required, and no more than there are.",
+        ));
+    }
+    return match (u16::try_from(required), u16::try_from(shards - required)) {
+        (Ok(data_chunks), Ok(code_chunks)) if data_chunks.checked_add(code_chunks).is_some() => {
+            Ok(Encoding {
+                data_chunks: data_chunks,
+                code_chunks: code_chunks,
+            })
+        }
+        _ => Err(format!("Cannot split into {} shards.", shards)),
+    };
+}
+
+// The number of bits of entropy in each word of a phrase. Every word is a byte, so the wordlist
+// needs a word for every byte, and any beyond those are never used. The shards are encoded over a
+// field with a distinct point for each chunk of the encoding (see ShardField).
+pub fn bits_per_word(wordlist_len: usize, encoding: Encoding) -> Result<usize, String> {
+    ShardField::for_shards(encoding.total_chunks() as usize)?;
+    let symbols = <SecretField as Field>::Symbol::ORDER;
+    if wordlist_len < symbols {
+        return Err(format!(
+            "The wordlist must have at least {} words, but it only has {}.",
+            symbols, wordlist_len
+        ));
+    }
+    // The field is binary, so there is a power of two symbols.
+    return Ok(symbols.trailing_zeros() as usize);
+}
+
+// The number of words (after the index) needed for a secret with at least the given number of
+// bits of entropy.
+pub fn words_for_bits(
+    bits: usize,
+    wordlist_len: usize,
+    encoding: Encoding,
+) -> Result<usize, String> {
+    if bits == 0 {
+        return Err(String::from(
+            "The secret must have at least 1 bit of entropy.",
+        ));
+    }
+    let per_word = bits_per_word(wordlist_len, encoding)?;
+    return Ok((bits + per_word - 1) / per_word);
+}
+
+// The encoder a secret is split with. It is recorded in the header of each shard, so restoring only
+// needs the shards.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ShardEncoder {
+    Vandermonde,
+    Lagrange,
+    Cauchy,
+}
+
+// Starts the header of shards, which otherwise start with their index, which is never 0 (the secret
+// is chunk 0).
+const HEADER_MARKER: u8 = 0;
+// Follows the marker in versioned headers. The first Lagrange and Cauchy shards have their encoder
+// id there instead, which is always below 128.
+const HEADER_VERSION: u8 = 128 + 1;
+
+impl ShardEncoder {
+    fn id(&self) -> u8 {
+        return match self {
+            ShardEncoder::Vandermonde => 0,
+            ShardEncoder::Lagrange => 1,
+            ShardEncoder::Cauchy => 2,
+        };
+    }
+
+    fn from_id(id: u8) -> Result<ShardEncoder, String> {
+        return match id {
+            0 => Ok(ShardEncoder::Vandermonde),
+            1 => Ok(ShardEncoder::Lagrange),
+            2 => Ok(ShardEncoder::Cauchy),
+            _ => Err(format!("Unknown encoder id {}", id)),
+        };
+    }
+}
+
+impl FromStr for ShardEncoder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ShardEncoder, Self::Err> {
+        match s {
+            "vandermonde" => Ok(ShardEncoder::Vandermonde),
+            "lagrange" => Ok(ShardEncoder::Lagrange),
+            "cauchy" => Ok(ShardEncoder::Cauchy),
+            _ => Err(format!(
+                "Unknown encoder {:?}, expected one of ['lagrange', 'vandermonde', 'cauchy']",
+                s
+            )),
+        }
+    }
+}
+
+// The id of the format the secret is shown in, with 0 for the default of words.
+fn format_id(format: Option<SecretFormat>) -> u8 {
+    return match format {
+        None | Some(SecretFormat::Raw) => 0,
+        Some(SecretFormat::Words) => 1,
+        Some(SecretFormat::Bip39) => 2,
+        Some(SecretFormat::Hex) => 3,
+        Some(SecretFormat::Base64) => 4,
+        Some(SecretFormat::Base58) => 5,
+    };
+}
+
+fn format_from_id(id: u8) -> Result<Option<SecretFormat>, String> {
+    return match id {
+        0 => Ok(None),
+        1 => Ok(Some(SecretFormat::Words)),
+        2 => Ok(Some(SecretFormat::Bip39)),
+        3 => Ok(Some(SecretFormat::Hex)),
+        4 => Ok(Some(SecretFormat::Base64)),
+        5 => Ok(Some(SecretFormat::Base58)),
+        _ => Err(format!("Unknown secret format id {}", id)),
+    };
+}
+
+// The field the shards are encoded over. Every word is a byte, so GF(2^8) symbols take a word each
+// and GF(2^16) ones two, high byte first. GF(2^16) is only used when there are more shards
+// (including the secret) than GF(2^8) has points for. The prime fields are the orders of elliptic
+// curve groups, so that a private key is split as a single scalar (see shamir_scalar) rather than
+// byte by byte: each shard is its index in a word, then the scalar.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ShardField {
+    Gf256,
+    Gf65536,
+    Secp256k1,
+    P256,
+    Ed25519,
+}
+
+impl ShardField {
+    fn id(&self) -> u8 {
+        return match self {
+            ShardField::Gf256 => 0,
+            ShardField::Gf65536 => 1,
+            ShardField::Secp256k1 => 2,
+            ShardField::P256 => 3,
+            ShardField::Ed25519 => 4,
+        };
+    }
+
+    fn from_id(id: u8) -> Result<ShardField, String> {
+        return match id {
+            0 => Ok(ShardField::Gf256),
+            1 => Ok(ShardField::Gf65536),
+            2 => Ok(ShardField::Secp256k1),
+            3 => Ok(ShardField::P256),
+            4 => Ok(ShardField::Ed25519),
+            _ => Err(format!("Unknown field id {}", id)),
+        };
+    }
+
+    // The length of the secret scalars of the prime fields, or None for the binary fields, which
+    // split secrets of any length.
+    pub fn scalar_bytes(&self) -> Option<usize> {
+        return match self {
+            ShardField::Gf256 | ShardField::Gf65536 => None,
+            ShardField::Secp256k1 => Some(Secp256k1Order::bytes()),
+            ShardField::P256 => Some(P256Order::bytes()),
+            ShardField::Ed25519 => Some(Curve25519Order::bytes()),
+        };
+    }
+
+    // A scalar of a prime field from random bytes, which must be at least 16 longer than the
+    // scalar for it to be close to uniform.
+    pub fn reduce_scalar(&self, bytes: &[u8]) -> Result<Secret, String> {
+        return match self {
+            ShardField::Gf256 | ShardField::Gf65536 => {
+                Err(format!("{:?} secrets are not scalars.", self))
+            }
+            ShardField::Secp256k1 => Ok(reduce_scalar::<Secp256k1Order>(bytes)),
+            ShardField::P256 => Ok(reduce_scalar::<P256Order>(bytes)),
+            ShardField::Ed25519 => Ok(reduce_scalar::<Curve25519Order>(bytes)),
+        };
+    }
+
+    // The smallest field with a point for each of the shards.
+    pub fn for_shards(shards: usize) -> Result<ShardField, String> {
+        let largest = <DirectField65536 as Field>::Symbol::ORDER;
+        if shards <= <SecretField as Field>::Symbol::ORDER {
+            return Ok(ShardField::Gf256);
+        } else if shards <= largest {
+            return Ok(ShardField::Gf65536);
+        }
+        return Err(format!(
+            "Shard words are symbols of a field with at most {} elements, so there can be at \
+             most {} shards including the secret.",
+            largest, largest
+        ));
+    }
+
+    // The words of the index, which for the binary fields is a symbol like the others. Scalar
+    // shards have at most 256 indices, so one word is enough.
+    fn index_words(&self) -> usize {
+        return match self {
+            ShardField::Gf65536 => symbol_words::<u16>(),
+            _ => symbol_words::<u8>(),
+        };
+    }
+}
+
+impl FromStr for ShardField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ShardField, Self::Err> {
+        match s {
+            "gf256" => Ok(ShardField::Gf256),
+            "gf65536" => Ok(ShardField::Gf65536),
+            "secp256k1" => Ok(ShardField::Secp256k1),
+            "p256" => Ok(ShardField::P256),
+            "ed25519" => Ok(ShardField::Ed25519),
+            _ => Err(format!(
+                "Unknown field {:?}, expected one of ['gf256', 'gf65536', 'secp256k1', 'p256', \
+                 'ed25519']",
+                s
+            )),
+        }
+    }
+}
+
+fn reduce_scalar<M: Modulus>(bytes: &[u8]) -> Secret {
+    return Secret::new(Residue::reduce_bytes_be::<M>(bytes).to_bytes_be(M::bytes()));
+}
+
+// The bytes, and so words, of a symbol.
+fn symbol_words<S: Symbol>() -> usize {
+    return S::ORDER.trailing_zeros() as usize / 8;
+}
+
+// How the shards of a secret were split, from the words before their index. Shards are now always
+// generated with a versioned header, [HEADER_MARKER, HEADER_VERSION, encoder id, format id, field
+// id], but older ones are still restored: Vandermonde shards without any header, and Lagrange and
+// Cauchy shards with [HEADER_MARKER, encoder id]. Both are over GF(2^8).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ShardHeader {
+    pub encoder: ShardEncoder,
+    // The format to show the restored secret in, if not the default.
+    pub format: Option<SecretFormat>,
+    pub field: ShardField,
+    // Whether the header is versioned. The Vandermonde shards without one were computed with
+    // ExpLogField's matrices, whose rows are not powers (see Matrix::invert_vandermonde).
+    versioned: bool,
+}
+
+impl ShardHeader {
+    pub fn new(encoder: ShardEncoder) -> Self {
+        return ShardHeader {
+            encoder,
+            format: None,
+            field: ShardField::Gf256,
+            versioned: true,
+        };
+    }
+
+    pub fn with_field(self, field: ShardField) -> Self {
+        return ShardHeader { field, ..self };
+    }
+
+    // Records the format the secret was shown in, so that restoring shows it the same way. Raw
+    // secrets are not recorded, as writing them needs a --secret-file.
+    pub fn with_format(self, format: Option<SecretFormat>) -> Self {
+        return ShardHeader {
+            format: format.filter(|f| *f != SecretFormat::Raw),
+            ..self
+        };
+    }
+
+    // The words before the index of a shard.
+    fn words(&self) -> Vec<u8> {
+        return match (self.versioned, self.encoder) {
+            (true, encoder) => vec![
+                HEADER_MARKER,
+                HEADER_VERSION,
+                encoder.id(),
+                format_id(self.format),
+                self.field.id(),
+            ],
+            (false, ShardEncoder::Vandermonde) => vec![],
+            (false, encoder) => vec![HEADER_MARKER, encoder.id()],
+        };
+    }
+
+    // The encoder for every round of a secret.
+    fn encoder(&self, encoding: Encoding) -> Result<Box<dyn RSEncoder<SecretField>>, String> {
+        return match (self.versioned, self.encoder) {
+            // The matrices only depend on the public encoding, so they are computed with tables,
+            // while the secret bytes are only ever multiplied in constant time. TableField's rows
+            // are powers, so each decoding matrix is inverted in O(n^2). Every round has the same
+            // shards missing, so it is cached and only computed once.
+            (true, ShardEncoder::Vandermonde) => Ok(Box::new(CachedVandermondeEncoder::<
+                TableField,
+            >::new(encoding, 1)?)),
+            // Shards without a header were generated with ExpLogField's matrices, which stay
+            // O(n^3) to invert.
+            (false, ShardEncoder::Vandermonde) => Ok(Box::new(CachedVandermondeEncoder::<
+                ExpLogField,
+            >::new(encoding, 1)?)),
+            (_, ShardEncoder::Lagrange) => Ok(Box::new(LagrangeInterpolationEncoder::default())),
+            (_, ShardEncoder::Cauchy) => Ok(Box::new(CauchyEncoder::default())),
+        };
+    }
+
+    // The encoder for every round of a secret over GF(2^16), which only versioned headers have.
+    // DirectField65536 multiplies in constant time, and ExpLogField65536's matrices are powers.
+    fn encoder_65536(
+        &self,
+        encoding: Encoding,
+    ) -> Result<Box<dyn RSEncoder<DirectField65536>>, String> {
+        return match self.encoder {
+            ShardEncoder::Vandermonde => Ok(Box::new(
+                CachedVandermondeEncoder::<ExpLogField65536>::new(encoding, 1)?,
+            )),
+            ShardEncoder::Lagrange => Ok(Box::new(LagrangeInterpolationEncoder::default())),
+            ShardEncoder::Cauchy => Ok(Box::new(CauchyEncoder::default())),
+        };
+    }
+}
 
-    let encoding = Encoding {
-        data_chunks: required as u8,
-        code_chunks: (shards - required) as u8,
+// Splits a shard into its header, its index and the words after them.
+fn parse_header(phrase: &[u8]) -> Result<(ShardHeader, usize, &[u8]), String> {
+    return match phrase {
+        [HEADER_MARKER, HEADER_VERSION, id, format, field, rest @ ..] => {
+            let header = ShardHeader::new(ShardEncoder::from_id(*id)?)
+                .with_format(format_from_id(*format)?)
+                .with_field(ShardField::from_id(*field)?);
+            let width = header.field.index_words();
+            if rest.len() < width {
+                return Err("The shard header is too short".to_string());
+            }
+            Ok((header, symbol_value(&rest[..width]), &rest[width..]))
+        }
+        [HEADER_MARKER, HEADER_VERSION, ..] => Err("The shard header is too short".to_string()),
+        [HEADER_MARKER, version, ..] if *version >= 128 => {
+            Err(format!("Unknown shard header version {}", version - 128))
+        }
+        [HEADER_MARKER, id, index, rest @ ..] => {
+            let encoder = ShardEncoder::from_id(*id)?;
+            if encoder == ShardEncoder::Vandermonde {
+                return Err("Vandermonde shards have no encoder header".to_string());
+            }
+            let header = ShardHeader {
+                encoder,
+                format: None,
+                field: ShardField::Gf256,
+                versioned: false,
+            };
+            Ok((header, *index as usize, rest))
+        }
+        [HEADER_MARKER, ..] => Err("The shard header is too short".to_string()),
+        [index, rest @ ..] => {
+            let header = ShardHeader {
+                encoder: ShardEncoder::Vandermonde,
+                format: None,
+                field: ShardField::Gf256,
+                versioned: false,
+            };
+            Ok((header, *index as usize, rest))
+        }
+        [] => Err("The shard is empty".to_string()),
     };
-    let encoder = VandermondeEncoder::default();
-    let field = ExpLogField::default();
+}
+
+// The value of a symbol (or index) from its words, high byte first.
+fn symbol_value(words: &[u8]) -> usize {
+    return words
+        .iter()
+        .fold(0, |value, word| value << 8 | *word as usize);
+}
+
+// Appends the `width` words of a symbol (or index), high byte first.
+fn push_symbol(phrase: &mut Phrase, wordlist: &[String], symbol: usize, width: usize) {
+    for i in (0..width).rev() {
+        phrase.push_word(&wordlist[(symbol >> (8 * i)) & 0xff]);
+    }
+}
+
+// The header of a shard.
+pub fn shard_header(phrase: &str) -> Result<ShardHeader, String> {
+    let wordlist = words::load_static_word_list();
+    let wordmap = words::words_map(&wordlist[..]);
+    let words = words::from_words(phrase.split_whitespace(), &wordmap)?;
+    return parse_header(words.expose()).map(|(header, _, _)| header);
+}
 
-    let mut phrases: Vec<Vec<&str>> = (0..shards).map(|_| Vec::with_capacity(length)).collect();
+// Generates a random secret of `length` words and splits it into shards, using the OS random
+// number generator.
+#[allow(unused)]
+pub fn shamir(shards: usize, required: usize, length: usize) -> Result<Vec<Phrase>, String> {
+    return shamir_with_rng(&mut OsRng, shards, required, length);
+}
 
-    // The first word is the index of the shard.
+pub fn shamir_with_rng<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    shards: usize,
+    required: usize,
+    length: usize,
+) -> Result<Vec<Phrase>, String> {
+    let secret = Secret::new(gen_random_bytes(rng, length));
+    return shamir_secret(rng, secret.expose(), shards, required);
+}
+
+// Splits the given secret into shards. The first phrase is the secret itself, one word per byte,
+// and the rest are the shards, each starting with its header and its index.
+pub fn shamir_secret<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    secret: &[u8],
+    shards: usize,
+    required: usize,
+) -> Result<Vec<Phrase>, String> {
+    let header =
+        ShardHeader::new(ShardEncoder::Vandermonde).with_field(ShardField::for_shards(shards)?);
+    return shamir_secret_with(rng, secret, shards, required, header);
+}
+
+// Like shamir_secret, but with the given header, whose encoder and field the shards are split
+// with. There can be at most as many shards, including the secret, as the field has elements.
+pub fn shamir_secret_with<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    secret: &[u8],
+    shards: usize,
+    required: usize,
+    header: ShardHeader,
+) -> Result<Vec<Phrase>, String> {
+    let wordlist = words::load_static_word_list();
+
+    let encoding = shard_encoding(shards, required)?;
+    bits_per_word(wordlist.len(), encoding)?;
+
+    if header.field.scalar_bytes().is_some() {
+        check_scalar_split(header, secret.len(), shards)?;
+    }
+
+    // The secret has a word per byte, and the shards their header, then a symbol for their index
+    // and for each byte (or, for the prime fields, the scalar's bytes).
+    let width = header.field.index_words();
+    let mut phrases: Vec<Phrase> = (0..shards)
+        .map(|i| {
+            let words = match i {
+                0 => secret.len(),
+                _ => header.words().len() + width * (1 + secret.len()),
+            };
+            return Phrase::with_capacity(phrase_capacity(&wordlist, words));
+        })
+        .collect();
     for i in 1..phrases.len() {
-        phrases[i].push(&wordlist[i]);
-    }
-
-    // Remaining words are generated through repeated RS rounds.
-    for _ in 0..length {
-        // Generate a vector of random bytes to generate the RS round with.
-        let bytes = gen_random_bytes(required);
-        // Encode the random bytes to get a stream of both the input bytes and additional
-        // generated bytes.
-        let stream = encoder
-            .encode_bytes(encoding, &field, &bytes[..])
-            .expect(&format!(
-                "Encoding did not work for byte stream: {:?}",
-                &bytes
-            ));
-        // Because we only had "required" bytes, there was only one stripe of codes, so the output
-        // will be in "codes[0]".
-        for (i, byte) in stream.codes[0].iter().enumerate() {
-            phrases[i].push(&wordlist[*byte as usize]);
+        for word in header.words() {
+            phrases[i].push_word(&wordlist[word as usize]);
+        }
+        push_symbol(&mut phrases[i], &wordlist, i, width);
+    }
+
+    match header.field {
+        ShardField::Gf256 => {
+            let rs_encoder = header.encoder(encoding)?;
+            split_rounds(
+                rng,
+                rs_encoder.as_ref(),
+                encoding,
+                secret,
+                &mut phrases,
+                &wordlist,
+            )?;
+        }
+        ShardField::Gf65536 => {
+            let rs_encoder = header.encoder_65536(encoding)?;
+            split_rounds(
+                rng,
+                rs_encoder.as_ref(),
+                encoding,
+                secret,
+                &mut phrases,
+                &wordlist,
+            )?;
         }
+        ShardField::Secp256k1 => {
+            split_scalar::<Secp256k1Order, _>(rng, secret, required, &mut phrases, &wordlist)?
+        }
+        ShardField::P256 => {
+            split_scalar::<P256Order, _>(rng, secret, required, &mut phrases, &wordlist)?
+        }
+        ShardField::Ed25519 => {
+            split_scalar::<Curve25519Order, _>(rng, secret, required, &mut phrases, &wordlist)?
+        }
+    }
+    return Ok(phrases);
+}
+
+// Scalars are split by interpolation with shamir_scalar, so only as Lagrange shards, of at most
+// 256 indices, and must have exactly the length of the field's elements.
+fn check_scalar_split(header: ShardHeader, length: usize, shards: usize) -> Result<(), String> {
+    if header.encoder != ShardEncoder::Lagrange {
+        return Err(format!(
+            "{:?} shards are interpolated, so they must use the Lagrange encoder.",
+            header.field
+        ));
+    }
+    let largest = <SecretField as Field>::Symbol::ORDER;
+    if shards > largest {
+        return Err(format!(
+            "{:?} shards have a one word index, so there can be at most {} shards including the \
+             secret.",
+            header.field, largest
+        ));
+    }
+    let expected = header.field.scalar_bytes().unwrap_or(length);
+    if length != expected {
+        return Err(format!(
+            "{:?} secrets are scalars of {} bytes, but the secret has {}.",
+            header.field, expected, length
+        ));
     }
+    return Ok(());
+}
 
-    let strings: Vec<String> = phrases.into_iter().map(|words| words.join(" ")).collect();
-    return strings;
+// Splits a secret scalar into the phrases after their index. The secret phrase gets the scalar's
+// bytes, and the shard phrases those of their value of the polynomial.
+fn split_scalar<M: Modulus, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    secret: &[u8],
+    required: usize,
+    phrases: &mut [Phrase],
+    wordlist: &[String],
+) -> Result<(), String> {
+    let shards = shamir_scalar::<M, R>(rng, secret, phrases.len() - 1, required)?;
+    for byte in secret {
+        phrases[0].push_word(&wordlist[*byte as usize]);
+    }
+    for (x, y) in shards.iter() {
+        for byte in y.expose() {
+            phrases[*x].push_word(&wordlist[*byte as usize]);
+        }
+    }
+    return Ok(());
+}
+
+// Generates the rest of the words through repeated RS rounds, one for each byte of the secret. The
+// symbols to encode each round with are the byte followed by random ones, and each phrase gets the
+// symbol of its chunk (which for the secret phrase is the byte).
+fn split_rounds<F: Field, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    rs_encoder: &dyn RSEncoder<F>,
+    encoding: Encoding,
+    secret: &[u8],
+    phrases: &mut [Phrase],
+    wordlist: &[String],
+) -> Result<(), String> {
+    let field = F::default();
+    let width = symbol_words::<F::Symbol>();
+    let required = encoding.data_chunks as usize;
+    let mut random = ShareBytes::zeroed((required - 1) * width);
+    let mut symbols = vec![F::Symbol::default(); required];
+    for byte in secret {
+        symbols[0] = F::Symbol::from(*byte);
+        rng.fill_bytes(random.expose_mut());
+        for (symbol, bytes) in symbols[1..].iter_mut().zip(random.expose().chunks(width)) {
+            *symbol = F::Symbol::from_usize(symbol_value(bytes));
+        }
+        // Because we only had "required" symbols, there was only one stripe of codes, so the
+        // output will be in "codes[0]".
+        let mut stream = match rs_encoder.encode_bytes(encoding, &field, &symbols) {
+            Ok(stream) => stream,
+            Err(e) => {
+                zeroize_vec(&mut symbols);
+                return Err(e);
+            }
+        };
+        for (i, symbol) in stream.codes[0].iter().enumerate() {
+            let words = if i == 0 { 1 } else { width };
+            push_symbol(&mut phrases[i], wordlist, symbol.to_usize(), words);
+        }
+        for code in stream.codes.iter_mut() {
+            zeroize_vec(code);
+        }
+    }
+    zeroize_vec(&mut symbols);
+    return Ok(());
+}
+
+// The bytes needed for a phrase of the given number of words, so that it can be built in place
+// without ever being copied.
+fn phrase_capacity(wordlist: &[String], words: usize) -> usize {
+    let longest = wordlist.iter().map(|word| word.len()).max().unwrap_or(0);
+    return words * (longest + 1);
 }
 
 // Note that phrases is positional
-pub fn unshamir(phrases: &Vec<String>, required: usize, total: usize) -> String {
+pub fn unshamir(phrases: &[Phrase], required: usize, total: usize) -> Result<Phrase, String> {
+    let wordlist: Vec<String> = words::load_static_word_list();
+    let secret = unshamir_bytes(phrases, required, total)?;
+    let mut password = Phrase::with_capacity(phrase_capacity(&wordlist, secret.len()));
+    for b in secret.expose() {
+        password.push_word(&wordlist[*b as usize]);
+    }
+    return Ok(password);
+}
+
+// Restores the secret bytes from the shards. Lagrange shards are interpolated at the missing
+// indices with weights computed once for every round. Vandermonde shards need the inverted matrix,
+// which is also computed only once, by CachedVandermondeEncoder.
+pub fn unshamir_bytes(phrases: &[Phrase], required: usize, total: usize) -> Result<Secret, String> {
     // Map the words back to u8s
     let wordlist: Vec<String> = words::load_static_word_list();
     let wordmap = words::words_map(&wordlist[..]);
-    let words: Vec<Vec<u8>> = phrases
+    let words: Vec<ShareBytes> = phrases
         .into_iter()
-        .map(|phrase| words::from_words(phrase.trim().split(" "), &wordmap))
-        .collect();
+        .map(|phrase| words::from_words(phrase.expose_str().split_whitespace(), &wordmap))
+        .collect::<Result<_, String>>()?;
 
-    // We'll use the indices to find which shards we have.
-    let mut present: Vec<Option<Vec<u8>>> = iter::repeat(None).take(total).collect();
+    // We'll use the indices to find which shards we have, and the headers to find the encoder.
+    let mut header = None;
+    let mut present: Vec<Option<&[u8]>> = iter::repeat(None).take(total).collect();
     let mut valid: Vec<bool> = iter::repeat(false).take(total).collect();
     for phrase in words.iter() {
-        assert!(phrase.len() > 0);
-        let index = phrase[0] as usize;
-        assert!(index < total);
+        let (shard_header, index, rest) = parse_header(phrase.expose())?;
+        if header.is_some() && header != Some(shard_header) {
+            return Err(format!(
+                "The shards were split differently, {:?} and {:?}.",
+                header.unwrap(),
+                shard_header
+            ));
+        }
+        header = Some(shard_header);
+        if index >= total {
+            return Err(format!(
+                "Shard {} is out of range, there are only {} shards.",
+                index,
+                total - 1
+            ));
+        }
         valid[index] = true;
-        present[index] = Some(phrase.clone());
+        present[index] = Some(rest);
     }
+    let header = header.unwrap_or(ShardHeader::new(ShardEncoder::Vandermonde));
     println!("Valid: {:?}", valid);
 
-    // Ensure the length of the phrases is uniform. This minus one (the first word is just the
-    // index) is how many RS rounds we have to do.
-    let length: usize = words.iter().map(|x| x.len()).max().unwrap_or(0);
-    println!("Length: {}", length);
-    assert!(words.iter().all(|x| x.len() == 0 || x.len() == length));
-
-    // Skip the first word in each phrase.
-    let codes: Vec<Vec<u8>> = (1..length)
-        .map(|i| {
-            (0..present.len())
-                .map(|j| match present[j] {
-                    None => 0,
-                    Some(_) => present[j].as_ref().unwrap()[i],
-                })
-                .collect()
-        })
-        .collect();
-    let encoding = Encoding {
-        data_chunks: required as u8,
-        code_chunks: (total - required) as u8,
+    let encoding = shard_encoding(total, required)?;
+    return match header.field {
+        ShardField::Gf256 => restore_rounds(header.encoder(encoding)?.as_ref(), encoding, &present),
+        ShardField::Gf65536 => {
+            restore_rounds(header.encoder_65536(encoding)?.as_ref(), encoding, &present)
+        }
+        ShardField::Secp256k1 => restore_scalar::<Secp256k1Order>(&present, required),
+        ShardField::P256 => restore_scalar::<P256Order>(&present, required),
+        ShardField::Ed25519 => restore_scalar::<Curve25519Order>(&present, required),
     };
+}
+
+// Restores a secret scalar from the present shards (those without a header or index), which must
+// each be a scalar of M.
+fn restore_scalar<M: Modulus>(
+    present: &[Option<&[u8]>],
+    required: usize,
+) -> Result<Secret, String> {
+    let mut shards = Vec::with_capacity(required);
+    for (x, y) in present.iter().enumerate() {
+        if let Some(y) = y {
+            if y.len() != M::bytes() {
+                return Err(format!(
+                    "Shard {} should be a scalar of {} words, but has {}.",
+                    x,
+                    M::bytes(),
+                    y.len()
+                ));
+            }
+            shards.push((x, ShareBytes::new(y.to_vec())));
+        }
+    }
+    return unshamir_scalar::<M>(&shards, required);
+}
+
+// Restores a byte of the secret from each round of symbols of the present shards (those without a
+// header or index), which must all have the same length.
+fn restore_rounds<F: Field>(
+    rs_encoder: &dyn RSEncoder<F>,
+    encoding: Encoding,
+    present: &[Option<&[u8]>],
+) -> Result<Secret, String> {
+    // Ensure the length of the phrases is uniform. This is how many RS rounds we have to do.
+    let width = symbol_words::<F::Symbol>();
+    let words: usize = present
+        .iter()
+        .map(|x| x.map_or(0, |x| x.len()))
+        .max()
+        .unwrap_or(0);
+    if words % width != 0 || !present.iter().all(|x| x.map_or(true, |x| x.len() == words)) {
+        return Err(String::from("The shards have different lengths."));
+    }
+    let length = words / width;
+    println!("Length: {}", length);
     println!("Encoding: {:?}", encoding);
-    let encoder = VandermondeEncoder::default();
-    let field = ExpLogField::default();
 
-    let mut password_words: Vec<&str> = Vec::with_capacity(length);
-    for chunk in codes {
-        let stream = RSStream {
-            length: required,
+    let field = F::default();
+    let valid: Vec<bool> = present.iter().map(|x| x.is_some()).collect();
+    let mut secret = Secret::zeroed(length);
+    for i in 0..length {
+        let chunk = present
+            .iter()
+            .map(|x| match x {
+                None => F::zero(),
+                Some(phrase) => {
+                    F::Symbol::from_usize(symbol_value(&phrase[i * width..(i + 1) * width]))
+                }
+            })
+            .collect();
+        let mut stream = RSStream {
+            length: encoding.data_chunks as usize,
             encoding: encoding,
             codes: vec![chunk],
             valid: valid.clone(),
         };
-        match encoder.decode_bytes(&stream, &field) {
-            Ok(data) => password_words.push(&wordlist[data[0] as usize]),
-            Err(e) => panic!("Got an error {} while decoding.", e),
-        };
+        let decoded = rs_encoder.decode_bytes(&stream, &field);
+        zeroize_vec(&mut stream.codes[0]);
+        // The rest of the data is the random symbols, which would reveal the secret too.
+        let mut data = decoded?;
+        let byte = data[0].to_usize();
+        zeroize_vec(&mut data);
+        // Every byte was split as a symbol below 256.
+        if byte > 0xff {
+            return Err(String::from("The shards do not restore a secret."));
+        }
+        secret.expose_mut()[i] = byte as u8;
     }
-    let password = password_words.join(" ");
-    println!("Shards: {}, required: {}", phrases.len(), required);
-    println!("Password: {}", password);
-    return password;
+    return Ok(secret);
+}
+
+// Splits a secret scalar (big-endian, and less than the prime of M) into shards, as shamir_secret
+// does with bytes. Shard i, for i from 1, is the value at i of a polynomial whose value at 0 is the
+// secret and at 1 to required - 1 are random, written big-endian in as many bytes as the prime.
+pub fn shamir_scalar<M: Modulus, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    secret: &[u8],
+    shards: usize,
+    required: usize,
+) -> Result<Vec<(usize, ShareBytes)>, String> {
+    if required == 0 || shards < required {
+        return Err(String::from(
+            "At least one shard must be required, and no more than there are.",
+        ));
+    }
+    let mut points = Vec::with_capacity(required);
+    points.push((Residue::from(0), Residue::from_bytes_be::<M>(secret)?));
+    for x in 1..required {
+        points.push((Residue::from_usize(x), Residue::random::<M, _>(rng)));
+    }
+    let polynomial = Polynomial::<PrimeField<M>>::interpolate_points(&points);
+    return Ok((1..=shards)
+        .map(|x| {
+            let y = polynomial.evaluate(Residue::from_usize(x));
+            (x, ShareBytes::new(y.to_bytes_be(M::bytes())))
+        })
+        .collect());
+}
+
+// Restores the secret scalar from at least `required` of the shards of shamir_scalar.
+pub fn unshamir_scalar<M: Modulus>(
+    shards: &[(usize, ShareBytes)],
+    required: usize,
+) -> Result<Secret, String> {
+    if shards.len() < required {
+        return Err(format!(
+            "{} shards are required, but there are only {}.",
+            required,
+            shards.len()
+        ));
+    }
+    let mut points = Vec::with_capacity(required);
+    for (x, y) in shards.iter().take(required) {
+        if *x == 0
+            || points
+                .iter()
+                .any(|(seen, _)| *seen == Residue::from_usize(*x))
+        {
+            return Err(format!("Shard {} is invalid or repeated.", x));
+        }
+        points.push((
+            Residue::from_usize(*x),
+            Residue::from_bytes_be::<M>(y.expose())?,
+        ));
+    }
+    let secret = Polynomial::<PrimeField<M>>::interpolate_at(&points, Residue::from(0))?;
+    return Ok(Secret::new(secret.to_bytes_be(M::bytes())));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prime_field::{Curve25519Order, Secp256k1Order};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn phrases(strings: &[&str]) -> Vec<Phrase> {
+        return strings
+            .iter()
+            .map(|s| Phrase::from_string(s.to_string()))
+            .collect();
+    }
+
+    fn texts(phrases: &[Phrase]) -> Vec<&str> {
+        return phrases.iter().map(|p| p.expose_str()).collect();
+    }
 
     #[test]
     fn shamir_unshamir() {
-        let phrases = shamir(6, 3, 10);
+        let phrases = shamir(6, 3, 10).unwrap();
         assert_eq!(
             unshamir(
                 &vec![phrases[1].clone(), phrases[3].clone(), phrases[5].clone(),],
                 3,
                 6,
-            ),
+            )
+            .unwrap(),
+            phrases[0]
+        );
+        // Extra spaces between the words are ignored.
+        let spaced: Vec<String> = [1, 3, 5]
+            .iter()
+            .map(|i| format!(" {} ", phrases[*i].expose_str().replace(" ", "  ")))
+            .collect();
+        let spaced: Vec<&str> = spaced.iter().map(|s| s.as_str()).collect();
+        assert_eq!(
+            unshamir(&super::tests::phrases(&spaced), 3, 6).unwrap(),
             phrases[0]
         );
     }
 
+    #[test]
+    fn shard_encoding_checks_counts() {
+        assert_eq!(
+            shard_encoding(5, 2),
+            Ok(Encoding {
+                data_chunks: 2,
+                code_chunks: 3
+            })
+        );
+        assert!(shard_encoding(2, 3).is_err());
+        assert!(shard_encoding(2, 0).is_err());
+        assert!(shard_encoding(70000, 2).is_err());
+    }
+
+    #[test]
+    fn words_for_bits_rounds_up() {
+        let encoding = shard_encoding(4, 3).unwrap();
+        assert_eq!(words_for_bits(128, 256, encoding), Ok(16));
+        assert_eq!(words_for_bits(129, 256, encoding), Ok(17));
+        assert_eq!(words_for_bits(1, 1296, encoding), Ok(1));
+        assert!(words_for_bits(0, 256, encoding).is_err());
+        assert!(words_for_bits(128, 255, encoding).is_err());
+        // Every shard needs its own symbol, of GF(2^16) beyond 256 shards.
+        let encoding = shard_encoding(257, 3).unwrap();
+        assert_eq!(words_for_bits(128, 256, encoding), Ok(16));
+        let encoding = shard_encoding(65535, 3).unwrap();
+        assert_eq!(words_for_bits(128, 256, encoding), Ok(16));
+    }
+
+    #[test]
+    fn shamir_with_seeded_rng() {
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        assert_eq!(
+            texts(&shamir_with_rng(&mut rng, 4, 2, 4).unwrap()),
+            vec![
+                "legal eject salon stays",
+                "acid mummy acid acid acid affix blast plot wilt stain",
+                "acid mummy acid acid acid agony rake vegan cork spree",
+                "acid mummy acid acid acid ajar shove carol gore spill",
+            ]
+        );
+    }
+
+    #[test]
+    fn shamir_secret_checks_counts() {
+        let secret = [1, 2, 3, 4];
+        let phrases = shamir_secret_with(
+            &mut OsRng,
+            &secret,
+            256,
+            200,
+            ShardHeader::new(ShardEncoder::Lagrange),
+        );
+        assert_eq!(phrases.map(|phrases| phrases.len()), Ok(256));
+        // The Vandermonde matrices are invertible for any threshold, unlike ExpLogField's.
+        let phrases = shamir_secret(&mut OsRng, &secret, 256, 255).unwrap();
+        assert_eq!(
+            unshamir_bytes(&phrases[1..], 255, 256).unwrap().expose(),
+            &secret[..]
+        );
+        assert!(shamir_secret_with(
+            &mut OsRng,
+            &secret,
+            257,
+            200,
+            ShardHeader::new(ShardEncoder::Lagrange)
+        )
+        .is_err());
+        // The Cauchy matrices have only half the field for the chunks.
+        assert!(shamir_secret_with(
+            &mut OsRng,
+            &secret,
+            129,
+            2,
+            ShardHeader::new(ShardEncoder::Cauchy)
+        )
+        .is_err());
+        assert!(shamir_secret(&mut OsRng, &secret, 65537, 2).is_err());
+        assert!(shamir_secret(&mut OsRng, &secret, 2, 3).is_err());
+    }
+
+    #[test]
+    fn shamir_secret_gf65536() {
+        let secret: Vec<u8> = (0..16).map(|i| i * 17).collect();
+        assert_eq!(ShardField::for_shards(256), Ok(ShardField::Gf256));
+        assert_eq!(ShardField::for_shards(257), Ok(ShardField::Gf65536));
+        assert!(ShardField::for_shards(65537).is_err());
+        for encoder in &[
+            ShardEncoder::Vandermonde,
+            ShardEncoder::Lagrange,
+            ShardEncoder::Cauchy,
+        ] {
+            let header = ShardHeader::new(*encoder).with_field(ShardField::Gf65536);
+            let phrases = shamir_secret_with(&mut OsRng, &secret, 300, 3, header).unwrap();
+            assert_eq!(phrases[0].expose_str().split(" ").count(), secret.len());
+            // The header, then two words for the index and for every byte.
+            let words: Vec<&str> = phrases[299].expose_str().split(" ").collect();
+            assert_eq!(words.len(), 5 + 2 * (1 + secret.len()));
+            assert_eq!(&words[5..7], &["affix", "clump"]);
+            let some = vec![
+                phrases[299].clone(),
+                phrases[7].clone(),
+                phrases[150].clone(),
+            ];
+            assert_eq!(unshamir_bytes(&some, 3, 300).unwrap().expose(), &secret[..]);
+            // A shard cut in the middle of a symbol.
+            let mut cut = some.clone();
+            let text = words[..words.len() - 1].join(" ");
+            cut[0] = Phrase::from_string(text);
+            assert!(unshamir_bytes(&cut, 3, 300).is_err());
+        }
+        // Beyond 256 shards GF(2^16) is chosen by default, and it works for fewer as well.
+        let phrases = shamir_secret(&mut OsRng, &secret, 257, 2).unwrap();
+        assert_eq!(
+            shard_header(phrases[1].expose_str()).map(|h| h.field),
+            Ok(ShardField::Gf65536)
+        );
+        let header = ShardHeader::new(ShardEncoder::Vandermonde).with_field(ShardField::Gf65536);
+        let phrases = shamir_secret_with(&mut OsRng, &secret, 5, 2, header).unwrap();
+        let some = vec![phrases[3].clone(), phrases[2].clone()];
+        assert_eq!(unshamir_bytes(&some, 2, 5).unwrap().expose(), &secret[..]);
+    }
+
+    #[test]
+    fn shamir_secret_scalar_fields() {
+        for field in &[ShardField::Secp256k1, ShardField::P256, ShardField::Ed25519] {
+            let length = field.scalar_bytes().unwrap();
+            let secret = field.reduce_scalar(&[0xa5; 48]).unwrap();
+            assert_eq!(secret.len(), length);
+            let header = ShardHeader::new(ShardEncoder::Lagrange).with_field(*field);
+            let phrases = shamir_secret_with(&mut OsRng, secret.expose(), 6, 3, header).unwrap();
+            assert_eq!(phrases[0].expose_str().split(" ").count(), length);
+            // The header, then a word for the index and one for every byte of the scalar.
+            let words: Vec<&str> = phrases[5].expose_str().split(" ").collect();
+            assert_eq!(words.len(), 5 + 1 + length);
+            assert_eq!(shard_header(phrases[5].expose_str()), Ok(header));
+            let some = vec![phrases[5].clone(), phrases[1].clone(), phrases[3].clone()];
+            assert_eq!(
+                unshamir_bytes(&some, 3, 6).unwrap().expose(),
+                secret.expose()
+            );
+            // Shards that are not a scalar.
+            let mut cut = some.clone();
+            cut[0] = Phrase::from_string(words[..words.len() - 1].join(" "));
+            assert!(unshamir_bytes(&cut, 3, 6).is_err());
+            // Scalars have a fixed length, are interpolated, and have one word indices.
+            assert!(shamir_secret_with(&mut OsRng, &[1, 2, 3], 6, 3, header).is_err());
+            let vandermonde = ShardHeader::new(ShardEncoder::Vandermonde).with_field(*field);
+            assert!(shamir_secret_with(&mut OsRng, secret.expose(), 6, 3, vandermonde).is_err());
+            assert!(shamir_secret_with(&mut OsRng, secret.expose(), 257, 3, header).is_err());
+        }
+        // The secret must be less than the prime.
+        let header = ShardHeader::new(ShardEncoder::Lagrange).with_field(ShardField::Secp256k1);
+        assert!(shamir_secret_with(&mut OsRng, &[0xff; 32], 4, 2, header).is_err());
+        assert!(ShardField::Gf256.reduce_scalar(&[1; 48]).is_err());
+        assert_eq!("ed25519".parse(), Ok(ShardField::Ed25519));
+        assert!("curve25519".parse::<ShardField>().is_err());
+    }
+
+    #[test]
+    fn shamir_secret_unshamir_bytes() {
+        let secret: Vec<u8> = (0..16).map(|i| i * 13).collect();
+        let phrases = shamir_secret(&mut OsRng, &secret, 5, 2).unwrap();
+        assert_eq!(
+            unshamir_bytes(&vec![phrases[4].clone(), phrases[2].clone()], 2, 5)
+                .unwrap()
+                .expose(),
+            &secret[..]
+        );
+    }
+
+    #[test]
+    fn shamir_secret_with_every_encoder() {
+        let secret: Vec<u8> = (0..16).map(|i| i * 7).collect();
+        for encoder in &[
+            ShardEncoder::Vandermonde,
+            ShardEncoder::Lagrange,
+            ShardEncoder::Cauchy,
+        ] {
+            let phrases =
+                shamir_secret_with(&mut OsRng, &secret, 6, 3, ShardHeader::new(*encoder)).unwrap();
+            assert_eq!(phrases[0].expose_str().split(" ").count(), secret.len());
+            for phrase in &phrases[1..] {
+                assert_eq!(
+                    shard_header(phrase.expose_str()).map(|h| h.encoder),
+                    Ok(*encoder)
+                );
+            }
+            let some = vec![phrases[5].clone(), phrases[1].clone(), phrases[4].clone()];
+            assert_eq!(unshamir_bytes(&some, 3, 6).unwrap().expose(), &secret[..]);
+        }
+    }
+
+    #[test]
+    fn shard_headers() {
+        let legacy = |encoder| ShardHeader {
+            encoder,
+            format: None,
+            field: ShardField::Gf256,
+            versioned: false,
+        };
+        assert_eq!(
+            parse_header(&[3, 10, 11]),
+            Ok((legacy(ShardEncoder::Vandermonde), 3, &[10, 11][..]))
+        );
+        assert_eq!(
+            parse_header(&[0, 2, 3, 10]),
+            Ok((legacy(ShardEncoder::Cauchy), 3, &[10][..]))
+        );
+        assert_eq!(
+            parse_header(&[0, 129, 0, 0, 0, 3, 10]),
+            Ok((ShardHeader::new(ShardEncoder::Vandermonde), 3, &[10][..]))
+        );
+        // GF(2^16) indices take two words.
+        let wide = ShardHeader::new(ShardEncoder::Vandermonde).with_field(ShardField::Gf65536);
+        assert_eq!(
+            parse_header(&[0, 129, 0, 0, 1, 1, 44, 10, 0]),
+            Ok((wide, 300, &[10, 0][..]))
+        );
+        // Scalar indices take one word.
+        let p256 = ShardHeader::new(ShardEncoder::Lagrange).with_field(ShardField::P256);
+        assert_eq!(
+            parse_header(&[0, 129, 1, 0, 3, 5, 10]),
+            Ok((p256, 5, &[10][..]))
+        );
+        let bip39 = ShardHeader::new(ShardEncoder::Lagrange).with_format(Some(SecretFormat::Bip39));
+        assert_eq!(
+            parse_header(&[0, 129, 1, 2, 0, 3, 10]),
+            Ok((bip39, 3, &[10][..]))
+        );
+        for encoder in &[ShardEncoder::Vandermonde, ShardEncoder::Cauchy] {
+            let hex = ShardHeader::new(*encoder).with_format(Some(SecretFormat::Hex));
+            for header in &[ShardHeader::new(*encoder), hex, legacy(*encoder)] {
+                let mut phrase = header.words();
+                phrase.extend(&[5, 10]);
+                assert_eq!(parse_header(&phrase), Ok((*header, 5, &[10][..])));
+            }
+        }
+        assert!(parse_header(&[]).is_err());
+        assert!(parse_header(&[0, 1]).is_err());
+        assert!(parse_header(&[0, 0, 3, 10]).is_err());
+        assert!(parse_header(&[0, 9, 3, 10]).is_err());
+        assert!(parse_header(&[0, 129, 0, 0, 0]).is_err());
+        assert!(parse_header(&[0, 129, 0, 0, 1, 3]).is_err());
+        assert!(parse_header(&[0, 129, 9, 0, 0, 3, 10]).is_err());
+        assert!(parse_header(&[0, 129, 0, 9, 0, 3, 10]).is_err());
+        assert!(parse_header(&[0, 129, 0, 0, 9, 3, 10]).is_err());
+        assert!(parse_header(&[0, 130, 0, 0, 0, 3, 10]).is_err());
+        // Raw secrets are not recorded.
+        assert_eq!(
+            ShardHeader::new(ShardEncoder::Cauchy).with_format(Some(SecretFormat::Raw)),
+            ShardHeader::new(ShardEncoder::Cauchy)
+        );
+        assert_eq!("cauchy".parse::<ShardEncoder>(), Ok(ShardEncoder::Cauchy));
+        assert!("reed-solomon".parse::<ShardEncoder>().is_err());
+    }
+
+    #[test]
+    fn unshamir_bytes_mixed_encoders() {
+        let secret = [1, 2, 3, 4];
+        let lagrange = shamir_secret_with(
+            &mut OsRng,
+            &secret,
+            4,
+            2,
+            ShardHeader::new(ShardEncoder::Lagrange),
+        )
+        .unwrap();
+        let cauchy = shamir_secret_with(
+            &mut OsRng,
+            &secret,
+            4,
+            2,
+            ShardHeader::new(ShardEncoder::Cauchy),
+        )
+        .unwrap();
+        assert!(unshamir_bytes(&vec![lagrange[1].clone(), cauchy[2].clone()], 2, 4).is_err());
+    }
+
+    #[test]
+    fn unshamir_bytes_rejects_bad_shards() {
+        // A header marker with nothing after it.
+        assert!(unshamir_bytes(&phrases(&["affix", "ajar agony"]), 2, 4).is_err());
+        // An index beyond the total.
+        assert!(unshamir_bytes(&phrases(&["ajar agony", "zesty agony"]), 2, 4).is_err());
+        // Different lengths.
+        assert!(unshamir_bytes(&phrases(&["ajar agony", "agony agony ajar"]), 2, 4).is_err());
+        // A mistyped word is named in the error.
+        assert_eq!(
+            unshamir_bytes(&phrases(&["ajar agoyn", "zesty agony"]), 2, 4).unwrap_err(),
+            "Unknown word \"agoyn\"."
+        );
+        assert!(shard_header("affix agoyn").is_err());
+    }
+
+    #[test]
+    fn shamir_scalar_unshamir_scalar() {
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        // A secp256k1 private key.
+        let secret: Vec<u8> = (1..=32).collect();
+        let shards = shamir_scalar::<Secp256k1Order, _>(&mut rng, &secret, 5, 3).unwrap();
+        assert_eq!(shards.len(), 5);
+        assert!(shards.iter().all(|(_, y)| y.len() == 32));
+        let some: Vec<_> = vec![shards[4].clone(), shards[0].clone(), shards[2].clone()];
+        assert_eq!(
+            unshamir_scalar::<Secp256k1Order>(&some, 3)
+                .unwrap()
+                .expose(),
+            &secret[..]
+        );
+        // Too few, or the same shard twice.
+        assert!(unshamir_scalar::<Secp256k1Order>(&some[..2], 3).is_err());
+        let repeated = vec![shards[1].clone(), shards[1].clone(), shards[2].clone()];
+        assert!(unshamir_scalar::<Secp256k1Order>(&repeated, 3).is_err());
+        // An even threshold.
+        let shards = shamir_scalar::<Secp256k1Order, _>(&mut rng, &secret, 5, 4).unwrap();
+        assert_eq!(
+            unshamir_scalar::<Secp256k1Order>(&shards[1..], 4)
+                .unwrap()
+                .expose(),
+            &secret[..]
+        );
+        // With fewer shards, the secret is a different scalar.
+        assert_ne!(
+            unshamir_scalar::<Secp256k1Order>(&some[..2], 2)
+                .unwrap()
+                .expose(),
+            &secret[..]
+        );
+    }
+
+    #[test]
+    fn shamir_scalar_rejects_large_secrets() {
+        let secret = [0xff; 32];
+        assert!(shamir_scalar::<Secp256k1Order, _>(&mut OsRng, &secret, 3, 2).is_err());
+        // Ed25519 scalars are reduced modulo a prime just above 2^252.
+        let secret = [0x0f; 32];
+        let shards = shamir_scalar::<Curve25519Order, _>(&mut OsRng, &secret, 3, 2).unwrap();
+        assert_eq!(
+            unshamir_scalar::<Curve25519Order>(&shards[1..], 2)
+                .unwrap()
+                .expose(),
+            &secret[..]
+        );
+    }
+
+    // Interpolates the shards at 0 with plain integer arithmetic, as other Shamir implementations
+    // do, so the shards work with them, including with an even threshold.
+    #[test]
+    fn shamir_scalar_known_answer() {
+        use num_bigint::BigUint;
+        let mut rng = ChaCha20Rng::seed_from_u64(11);
+        let secret: Vec<u8> = (1..=32).collect();
+        let prime = Secp256k1Order::prime().clone();
+        for required in 2..=5 {
+            let shards =
+                shamir_scalar::<Secp256k1Order, _>(&mut rng, &secret, 6, required).unwrap();
+            let points = &shards[6 - required..];
+            let mut sum = BigUint::from(0u32);
+            for (xi, yi) in points {
+                // The Lagrange basis polynomial of xi at 0 is the product of xj / (xj - xi).
+                let mut numerator = BigUint::from(1u32);
+                let mut denominator = BigUint::from(1u32);
+                for (xj, _) in points.iter().filter(|(xj, _)| xj != xi) {
+                    numerator = numerator * BigUint::from(*xj) % &prime;
+                    let difference = (&prime + BigUint::from(*xj) - BigUint::from(*xi)) % &prime;
+                    denominator = denominator * difference % &prime;
+                }
+                let inverse = denominator.modpow(&(&prime - BigUint::from(2u32)), &prime);
+                let y = BigUint::from_bytes_be(yi.expose());
+                sum = (sum + y * numerator % &prime * inverse) % &prime;
+            }
+            assert_eq!(sum, BigUint::from_bytes_be(&secret));
+        }
+    }
+
     #[test]
     fn test_unshamir() {
         assert_eq!(
             unshamir(
-                &vec![
-                    "affix spill lens showy quota baggy tart start affix spew path".to_string(),
-                    "ajar agony shove draw fried vest grid hump date urban card".to_string(),
-                    "angle neon from silk stud aqua opal ajar aqua shove dingy".to_string(),
-                ],
+                &phrases(&[
+                    "affix spill lens showy quota baggy tart start affix spew path",
+                    "ajar agony shove draw fried vest grid hump date urban card",
+                    "angle neon from silk stud aqua opal ajar aqua shove dingy",
+                ]),
                 3,
                 6,
-            ),
+            )
+            .unwrap()
+            .expose_str(),
             "ozone puma shirt shy swab stunt trade july sharp dusk"
         );
     }
@@ -0,0 +1,1062 @@
+// SLIP-0039: Shamir's Secret-Sharing for Mnemonic Codes.
+//
+// See https://github.com/satoshilabs/slips/blob/master/slip-0039.md for the specification. This is
+// a two level scheme: the (encrypted) master secret is split into groups with a group threshold
+// and each group's share is split again into member shares with a member threshold. Each member
+// share is serialized as a mnemonic of 10-bit words from a 1024 word list, protected by an RS1024
+// checksum.
+//
+// The secret sharing itself happens over the same GF(2^8) (AES polynomial) as the rest of this
+// crate, so we reuse `Polynomial` for the interpolation.
+
+use crate::field::Gf;
+use crate::finite_field::DirectField;
+use crate::polynomial::Polynomial;
+use crate::secret::{zeroize_vec, Secret, ShareBytes};
+use crate::shamir::gen_random_bytes;
+use hmac::{Hmac, KeyInit, Mac};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::include_bytes;
+
+const WORDLIST_RAW: &'static [u8] = include_bytes!("../assets/slip39_wordlist.txt");
+
+// The number of bits encoded by each word.
+const RADIX_BITS: usize = 10;
+// The length of the random identifier in bits.
+const ID_LENGTH_BITS: usize = 15;
+// The number of words in the share header (identifier, extendable flag, iteration exponent, group
+// index, group threshold, group count, member index and member threshold).
+const HEADER_WORDS: usize = 4;
+// The number of words in the checksum.
+const CHECKSUM_WORDS: usize = 3;
+// The shortest allowed master secret is 128 bits.
+const MIN_STRENGTH_BYTES: usize = 16;
+// The minimum mnemonic length, which corresponds to a 128 bit master secret.
+const MIN_MNEMONIC_WORDS: usize = HEADER_WORDS + 13 + CHECKSUM_WORDS;
+// The maximum number of shares (or groups) that can be created.
+const MAX_SHARE_COUNT: u8 = 16;
+
+// The x-coordinates used for the digest share and the shared secret.
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const DIGEST_LENGTH_BYTES: usize = 4;
+
+// The total number of PBKDF2 iterations over all Feistel rounds at iteration exponent 0.
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+
+const CUSTOMIZATION_STRING: &'static [u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &'static [u8] = b"shamir_extendable";
+
+// A single member share of a SLIP-39 split.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Share {
+    // Random identifier, common to all shares of a master secret.
+    pub identifier: u16,
+    // Whether the identifier is excluded from the encryption salt, which lets new shares be
+    // created for the same master secret with a different identifier.
+    pub extendable: bool,
+    // The PBKDF2 iteration count is 10000 * 2^iteration_exponent.
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: ShareBytes,
+}
+
+// The parameters of a group: how many member shares are required out of how many are generated.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Group {
+    pub threshold: u8,
+    pub count: u8,
+}
+
+pub fn load_word_list() -> Vec<String> {
+    return String::from_utf8_lossy(WORDLIST_RAW)
+        .lines()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect();
+}
+
+// The length in bytes of the shortest master secret with at least the given number of bits. Master
+// secrets are at least 128 bits and a whole number of 16-bit words.
+pub fn secret_length_for_bits(bits: usize) -> Result<usize, String> {
+    if bits == 0 {
+        return Err(String::from(
+            "The secret must have at least 1 bit of entropy.",
+        ));
+    }
+    let length = std::cmp::max((bits + 7) / 8, MIN_STRENGTH_BYTES);
+    return Ok(length + length % 2);
+}
+
+fn customization_string(extendable: bool) -> &'static [u8] {
+    if extendable {
+        return CUSTOMIZATION_STRING_EXTENDABLE;
+    }
+    return CUSTOMIZATION_STRING;
+}
+
+// The RS1024 checksum is a Reed-Solomon code over GF(1024) which guarantees detection of any error
+// affecting at most 3 words.
+fn rs1024_polymod(values: &[u32]) -> u32 {
+    const GEN: [u32; 10] = [
+        0xE0E040, 0x1C1C080, 0x3838100, 0x7070200, 0xE0E0009, 0x1C0C2412, 0x38086C24, 0x3090FC48,
+        0x21B1F890, 0x3F3F120,
+    ];
+    let mut chk: u32 = 1;
+    for v in values {
+        let b = chk >> 20;
+        chk = ((chk & 0xFFFFF) << 10) ^ v;
+        for i in 0..10 {
+            if (b >> i) & 1 == 1 {
+                chk ^= GEN[i];
+            }
+        }
+    }
+    return chk;
+}
+
+fn rs1024_create_checksum(data: &[u32], extendable: bool) -> Vec<u32> {
+    let values: Vec<u32> = customization_string(extendable)
+        .iter()
+        .map(|b| *b as u32)
+        .chain(data.iter().cloned())
+        .chain(std::iter::repeat(0).take(CHECKSUM_WORDS))
+        .collect();
+    let polymod = rs1024_polymod(&values) ^ 1;
+    return (0..CHECKSUM_WORDS)
+        .rev()
+        .map(|i| (polymod >> (RADIX_BITS * i)) & 1023)
+        .collect();
+}
+
+fn rs1024_verify_checksum(data: &[u32], extendable: bool) -> bool {
+    let values: Vec<u32> = customization_string(extendable)
+        .iter()
+        .map(|b| *b as u32)
+        .chain(data.iter().cloned())
+        .collect();
+    return rs1024_polymod(&values) == 1;
+}
+
+// Packs bytes into 10-bit words, left padding with zero bits so the bytes fill a whole number of
+// words.
+fn bytes_to_words(bytes: &[u8]) -> Vec<u32> {
+    let total_bits = bytes.len() * 8;
+    let word_count = (total_bits + RADIX_BITS - 1) / RADIX_BITS;
+    let padding = word_count * RADIX_BITS - total_bits;
+
+    let mut words = Vec::with_capacity(word_count);
+    let mut acc: u32 = 0;
+    let mut acc_bits = padding;
+    for byte in bytes {
+        acc = (acc << 8) | *byte as u32;
+        acc_bits += 8;
+        while acc_bits >= RADIX_BITS {
+            acc_bits -= RADIX_BITS;
+            words.push((acc >> acc_bits) & 1023);
+        }
+        acc &= (1 << acc_bits) - 1;
+    }
+    return words;
+}
+
+// The inverse of bytes_to_words. Fails if the padding bits are not zero or there are too many of
+// them.
+fn words_to_bytes(words: &[u32]) -> Result<Vec<u8>, String> {
+    let total_bits = words.len() * RADIX_BITS;
+    let padding = total_bits % 16;
+    if padding > 8 {
+        return Err(String::from("Invalid mnemonic length."));
+    }
+
+    let bits: Vec<u32> = words
+        .iter()
+        .flat_map(|w| (0..RADIX_BITS).rev().map(move |i| (w >> i) & 1))
+        .collect();
+    if bits[..padding].iter().any(|b| *b != 0) {
+        return Err(String::from("Invalid mnemonic padding."));
+    }
+    return Ok(bits[padding..]
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, b| (acc << 1) | *b as u8))
+        .collect());
+}
+
+impl Share {
+    // The first two words encode the identifier, extendable flag and iteration exponent; the
+    // next two encode the group and member parameters.
+    fn header_words(&self) -> Vec<u32> {
+        let id_exp: u32 = ((self.identifier as u32) << 5)
+            | ((self.extendable as u32) << 4)
+            | self.iteration_exponent as u32;
+        let group_params: u32 = ((self.group_index as u32) << 16)
+            | (((self.group_threshold - 1) as u32) << 12)
+            | (((self.group_count - 1) as u32) << 8)
+            | ((self.member_index as u32) << 4)
+            | (self.member_threshold - 1) as u32;
+        return vec![
+            (id_exp >> RADIX_BITS) & 1023,
+            id_exp & 1023,
+            (group_params >> RADIX_BITS) & 1023,
+            group_params & 1023,
+        ];
+    }
+
+    pub fn to_mnemonic<S: AsRef<str>>(&self, wordlist: &[S]) -> String {
+        let mut data = self.header_words();
+        data.extend(bytes_to_words(self.value.expose()));
+        let checksum = rs1024_create_checksum(&data, self.extendable);
+        data.extend(checksum);
+        return data
+            .iter()
+            .map(|i| wordlist[*i as usize].as_ref())
+            .collect::<Vec<&str>>()
+            .join(" ");
+    }
+
+    pub fn from_mnemonic(mnemonic: &str, wordmap: &HashMap<&str, u32>) -> Result<Share, String> {
+        let mut data = Vec::new();
+        for word in mnemonic.split_whitespace() {
+            match wordmap.get(word.to_lowercase().as_str()) {
+                Some(index) => data.push(*index),
+                None => return Err(format!("Invalid mnemonic word {:?}.", word)),
+            }
+        }
+        if data.len() < MIN_MNEMONIC_WORDS {
+            return Err(format!(
+                "Invalid mnemonic length. The length of each mnemonic must be at least {} words.",
+                MIN_MNEMONIC_WORDS
+            ));
+        }
+
+        let id_exp = (data[0] << RADIX_BITS) | data[1];
+        let extendable = (id_exp >> 4) & 1 == 1;
+        if !rs1024_verify_checksum(&data, extendable) {
+            return Err(String::from("Invalid mnemonic checksum."));
+        }
+
+        let group_params = (data[2] << RADIX_BITS) | data[3];
+        let share = Share {
+            identifier: (id_exp >> 5) as u16,
+            extendable: extendable,
+            iteration_exponent: (id_exp & 0xF) as u8,
+            group_index: ((group_params >> 16) & 0xF) as u8,
+            group_threshold: ((group_params >> 12) & 0xF) as u8 + 1,
+            group_count: ((group_params >> 8) & 0xF) as u8 + 1,
+            member_index: ((group_params >> 4) & 0xF) as u8,
+            member_threshold: (group_params & 0xF) as u8 + 1,
+            value: ShareBytes::new(words_to_bytes(
+                &data[HEADER_WORDS..data.len() - CHECKSUM_WORDS],
+            )?),
+        };
+        if share.group_count < share.group_threshold {
+            return Err(String::from(
+                "Invalid mnemonic. Group threshold cannot be greater than group count.",
+            ));
+        }
+        if share.value.len() < MIN_STRENGTH_BYTES {
+            return Err(String::from("Invalid mnemonic length."));
+        }
+        return Ok(share);
+    }
+}
+
+pub fn words_map<'a, S: AsRef<str>>(wordlist: &'a [S]) -> HashMap<&'a str, u32> {
+    assert!(wordlist.len() == 1 << RADIX_BITS);
+    return wordlist
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (w.as_ref(), i as u32))
+        .collect();
+}
+
+// Evaluates, byte by byte, the polynomials passing through the given shares at x.
+fn interpolate(shares: &[(u8, ShareBytes)], x: u8) -> Result<ShareBytes, String> {
+    let length = shares[0].1.len();
+    if shares.iter().any(|(_, value)| value.len() != length) {
+        return Err(String::from(
+            "Invalid set of shares. All share values must have the same length.",
+        ));
+    }
+    if let Some((_, value)) = shares.iter().find(|(index, _)| *index == x) {
+        return Ok(value.clone());
+    }
+
+    // The weights only depend on the indices, so they are shared by every byte.
+    let xs: Vec<u8> = shares.iter().map(|(x, _)| *x).collect();
+    let weights = Polynomial::<DirectField>::lagrange_weights(&xs, x)?;
+    let mut result = ShareBytes::zeroed(length);
+    for (w, (_, value)) in weights.iter().zip(shares) {
+        for (r, y) in result.expose_mut().iter_mut().zip(value.expose()) {
+            *r = (Gf::new(*r) + *w * Gf::new(*y)).value();
+        }
+    }
+    return Ok(result);
+}
+
+fn create_digest(random_data: &[u8], shared_secret: &[u8]) -> Vec<u8> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(random_data).expect("HMAC accepts keys of any length");
+    mac.update(shared_secret);
+    return mac.finalize().into_bytes()[..DIGEST_LENGTH_BYTES].to_vec();
+}
+
+fn split_secret<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    threshold: u8,
+    count: u8,
+    secret: &[u8],
+) -> Result<Vec<(u8, ShareBytes)>, String> {
+    if threshold < 1 {
+        return Err(String::from("The threshold must be a positive integer."));
+    }
+    if threshold > count {
+        return Err(String::from(
+            "The threshold must not exceed the number of shares.",
+        ));
+    }
+    if count > MAX_SHARE_COUNT {
+        return Err(format!(
+            "The number of shares must not exceed {}.",
+            MAX_SHARE_COUNT
+        ));
+    }
+
+    // If the threshold is 1, then the digest of the shared secret is not used.
+    if threshold == 1 {
+        return Ok((0..count)
+            .map(|i| (i, ShareBytes::from_slice(secret)))
+            .collect());
+    }
+
+    let random_share_count = threshold - 2;
+    let mut shares: Vec<(u8, ShareBytes)> = (0..random_share_count)
+        .map(|i| (i, ShareBytes::new(gen_random_bytes(rng, secret.len()))))
+        .collect();
+
+    let random_part = ShareBytes::new(gen_random_bytes(rng, secret.len() - DIGEST_LENGTH_BYTES));
+    let mut digest = Vec::with_capacity(secret.len());
+    digest.extend(create_digest(random_part.expose(), secret));
+    digest.extend(random_part.expose());
+
+    let mut base_shares = shares.clone();
+    base_shares.push((DIGEST_INDEX, ShareBytes::new(digest)));
+    base_shares.push((SECRET_INDEX, ShareBytes::from_slice(secret)));
+
+    for i in random_share_count..count {
+        shares.push((i, interpolate(&base_shares, i)?));
+    }
+    return Ok(shares);
+}
+
+fn recover_secret(threshold: u8, shares: &[(u8, ShareBytes)]) -> Result<ShareBytes, String> {
+    // If the threshold is 1, then the digest of the shared secret is not used.
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+
+    let shared_secret = interpolate(shares, SECRET_INDEX)?;
+    let digest_share = interpolate(shares, DIGEST_INDEX)?;
+    let (digest, random_part) = digest_share.expose().split_at(DIGEST_LENGTH_BYTES);
+    if digest != &create_digest(random_part, shared_secret.expose())[..] {
+        return Err(String::from("Invalid digest of the shared secret."));
+    }
+    return Ok(shared_secret);
+}
+
+fn round_function(round: u8, passphrase: &[u8], exponent: u8, salt: &[u8], r: &[u8]) -> Vec<u8> {
+    // Sized up front so the buffers are not reallocated, which would leave unwiped copies.
+    let mut password = Vec::with_capacity(1 + passphrase.len());
+    password.push(round);
+    password.extend(passphrase);
+    let mut salted = Vec::with_capacity(salt.len() + r.len());
+    salted.extend(salt);
+    salted.extend(r);
+    let mut result = vec![0; r.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(
+        &password,
+        &salted,
+        (BASE_ITERATION_COUNT << exponent) / ROUND_COUNT as u32,
+        &mut result,
+    );
+    zeroize_vec(&mut password);
+    zeroize_vec(&mut salted);
+    return result;
+}
+
+fn get_salt(identifier: u16, extendable: bool) -> Vec<u8> {
+    if extendable {
+        return Vec::new();
+    }
+    let mut salt = CUSTOMIZATION_STRING.to_vec();
+    salt.extend(&identifier.to_be_bytes());
+    return salt;
+}
+
+// Runs the four round Feistel network over the secret. Decryption is the same network with the
+// rounds in reverse order.
+fn feistel<I: Iterator<Item = u8>>(
+    input: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: I,
+) -> Vec<u8> {
+    let half = input.len() / 2;
+    let mut l = ShareBytes::from_slice(&input[..half]);
+    let mut r = ShareBytes::from_slice(&input[half..]);
+    let salt = get_salt(identifier, extendable);
+    for i in rounds {
+        let f = ShareBytes::new(round_function(
+            i,
+            passphrase,
+            iteration_exponent,
+            &salt,
+            r.expose(),
+        ));
+        let mut new_r = l;
+        for (x, y) in new_r.expose_mut().iter_mut().zip(f.expose()) {
+            *x ^= y;
+        }
+        l = r;
+        r = new_r;
+    }
+    let mut output = Vec::with_capacity(input.len());
+    output.extend(r.expose());
+    output.extend(l.expose());
+    return output;
+}
+
+pub fn encrypt(
+    master_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> ShareBytes {
+    return ShareBytes::new(feistel(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        0..ROUND_COUNT,
+    ));
+}
+
+pub fn decrypt(
+    encrypted_secret: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Secret {
+    return Secret::new(feistel(
+        encrypted_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        (0..ROUND_COUNT).rev(),
+    ));
+}
+
+fn random_identifier<R: RngCore + CryptoRng>(rng: &mut R) -> u16 {
+    let bytes = gen_random_bytes(rng, 2);
+    return u16::from_be_bytes([bytes[0], bytes[1]]) & ((1 << ID_LENGTH_BITS) - 1);
+}
+
+// Splits a master secret into groups of member shares. The result has one list of shares per
+// group, in group order.
+pub fn split_ems<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    group_threshold: u8,
+    groups: &[Group],
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    encrypted_secret: &[u8],
+) -> Result<Vec<Vec<Share>>, String> {
+    if encrypted_secret.len() < MIN_STRENGTH_BYTES {
+        return Err(format!(
+            "The length of the master secret must be at least {} bytes.",
+            MIN_STRENGTH_BYTES
+        ));
+    }
+    if encrypted_secret.len() % 2 != 0 {
+        return Err(String::from(
+            "The length of the master secret in bytes must be an even number.",
+        ));
+    }
+    if group_threshold as usize > groups.len() {
+        return Err(String::from(
+            "The requested group threshold must not exceed the number of groups.",
+        ));
+    }
+    if iteration_exponent > 0xF {
+        return Err(String::from("The iteration exponent must be at most 15."));
+    }
+    for group in groups {
+        if group.threshold == 1 && group.count > 1 {
+            return Err(String::from(
+                "Creating multiple member shares with member threshold 1 is not allowed. Use 1-of-1 member sharing instead.",
+            ));
+        }
+    }
+
+    let group_shares = split_secret(rng, group_threshold, groups.len() as u8, encrypted_secret)?;
+    let mut result = Vec::with_capacity(groups.len());
+    for ((group_index, group_secret), group) in group_shares.into_iter().zip(groups) {
+        let member_shares = split_secret(rng, group.threshold, group.count, group_secret.expose())?;
+        result.push(
+            member_shares
+                .into_iter()
+                .map(|(member_index, value)| Share {
+                    identifier: identifier,
+                    extendable: extendable,
+                    iteration_exponent: iteration_exponent,
+                    group_index: group_index,
+                    group_threshold: group_threshold,
+                    group_count: groups.len() as u8,
+                    member_index: member_index,
+                    member_threshold: group.threshold,
+                    value: value,
+                })
+                .collect(),
+        );
+    }
+    return Ok(result);
+}
+
+// Encrypts the master secret with the passphrase and splits it into mnemonics, one list per group.
+pub fn generate_mnemonics<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    group_threshold: u8,
+    groups: &[Group],
+    master_secret: &[u8],
+    passphrase: &[u8],
+    extendable: bool,
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<String>>, String> {
+    if passphrase.iter().any(|c| *c < 32 || *c > 126) {
+        return Err(String::from(
+            "The passphrase must contain only printable ASCII characters (code points 32-126).",
+        ));
+    }
+    let identifier = random_identifier(rng);
+    let encrypted_secret = encrypt(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+    );
+    let wordlist = load_word_list();
+    let groups = split_ems(
+        rng,
+        group_threshold,
+        groups,
+        identifier,
+        extendable,
+        iteration_exponent,
+        encrypted_secret.expose(),
+    )?;
+    return Ok(groups
+        .iter()
+        .map(|shares| shares.iter().map(|s| s.to_mnemonic(&wordlist)).collect())
+        .collect());
+}
+
+// Recovers the encrypted master secret from a set of shares, checking that they all belong to the
+// same split and that enough groups are complete.
+pub fn recover_ems(shares: &[Share]) -> Result<ShareBytes, String> {
+    if shares.len() == 0 {
+        return Err(String::from("The list of shares is empty."));
+    }
+    let first = &shares[0];
+    let mut groups: BTreeMap<u8, (u8, Vec<(u8, ShareBytes)>)> = BTreeMap::new();
+    for share in shares {
+        if share.identifier != first.identifier
+            || share.extendable != first.extendable
+            || share.iteration_exponent != first.iteration_exponent
+        {
+            return Err(String::from(
+                "Invalid set of mnemonics. All mnemonics must begin with the same 2 words.",
+            ));
+        }
+        if share.group_threshold != first.group_threshold || share.group_count != first.group_count
+        {
+            return Err(String::from(
+                "Invalid set of mnemonics. All mnemonics must have the same group threshold and group count.",
+            ));
+        }
+        let group = groups
+            .entry(share.group_index)
+            .or_insert((share.member_threshold, Vec::new()));
+        if group.0 != share.member_threshold {
+            return Err(String::from(
+                "Invalid set of mnemonics. All mnemonics in a group must have the same member threshold.",
+            ));
+        }
+        if group
+            .1
+            .iter()
+            .any(|(index, _)| *index == share.member_index)
+        {
+            return Err(String::from(
+                "Invalid set of mnemonics. Share indices must be unique within a group.",
+            ));
+        }
+        group.1.push((share.member_index, share.value.clone()));
+    }
+
+    let complete: Vec<(u8, &(u8, Vec<(u8, ShareBytes)>))> = groups
+        .iter()
+        .filter(|(_, (threshold, members))| members.len() >= *threshold as usize)
+        .map(|(index, group)| (*index, group))
+        .collect();
+    if complete.len() < first.group_threshold as usize {
+        return Err(format!(
+            "Insufficient number of complete groups. {} of the required {} groups are complete.",
+            complete.len(),
+            first.group_threshold
+        ));
+    }
+
+    let mut group_shares = Vec::with_capacity(first.group_threshold as usize);
+    for (group_index, (threshold, members)) in
+        complete.into_iter().take(first.group_threshold as usize)
+    {
+        let members = &members[..*threshold as usize];
+        group_shares.push((group_index, recover_secret(*threshold, members)?));
+    }
+    return recover_secret(first.group_threshold, &group_shares);
+}
+
+// Combines mnemonics into the master secret, decrypting it with the passphrase.
+pub fn combine_mnemonics<S: AsRef<str>>(
+    mnemonics: &[S],
+    passphrase: &[u8],
+) -> Result<Secret, String> {
+    let wordlist = load_word_list();
+    let wordmap = words_map(&wordlist);
+    let mut shares = Vec::with_capacity(mnemonics.len());
+    for mnemonic in mnemonics {
+        shares.push(Share::from_mnemonic(mnemonic.as_ref(), &wordmap)?);
+    }
+    let encrypted_secret = recover_ems(&shares)?;
+    let first = &shares[0];
+    return Ok(decrypt(
+        encrypted_secret.expose(),
+        passphrase,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        return (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+    }
+
+    #[test]
+    fn wordlist_is_complete() {
+        let wordlist = load_word_list();
+        assert_eq!(wordlist.len(), 1024);
+        assert_eq!(wordlist[0], "academic");
+        assert_eq!(wordlist[1023], "zero");
+        // Every word is uniquely identified by its first four letters.
+        let prefixes: std::collections::HashSet<&str> = wordlist.iter().map(|w| &w[..4]).collect();
+        assert_eq!(prefixes.len(), 1024);
+    }
+
+    #[test]
+    fn secret_length_for_bits_rounds_up() {
+        assert_eq!(secret_length_for_bits(1), Ok(16));
+        assert_eq!(secret_length_for_bits(128), Ok(16));
+        assert_eq!(secret_length_for_bits(129), Ok(18));
+        assert_eq!(secret_length_for_bits(256), Ok(32));
+        assert!(secret_length_for_bits(0).is_err());
+    }
+
+    #[test]
+    fn bytes_words_round_trip() {
+        for length in &[16, 18, 32] {
+            let bytes: Vec<u8> = (0..*length).map(|i| (i * 37 + 11) as u8).collect();
+            assert_eq!(words_to_bytes(&bytes_to_words(&bytes)).unwrap(), bytes);
+        }
+    }
+
+    // The following are from the official SLIP-39 test vectors, all using the passphrase
+    // "TREZOR".
+
+    #[test]
+    fn vector_valid_128_bits() {
+        let mnemonics = ["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard"];
+        assert_eq!(
+            combine_mnemonics(&mnemonics, b"TREZOR").unwrap().expose(),
+            &from_hex("bb54aac4b89dc868ba37d9cc21b2cece")[..]
+        );
+    }
+
+    #[test]
+    fn vector_invalid_checksum_128_bits() {
+        let mnemonics = ["duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney"];
+        assert!(combine_mnemonics(&mnemonics, b"TREZOR").is_err());
+    }
+
+    #[test]
+    fn vector_basic_sharing_2_of_3_128_bits() {
+        let mnemonics = [
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ];
+        assert_eq!(
+            combine_mnemonics(&mnemonics, b"TREZOR").unwrap().expose(),
+            &from_hex("b43ceb7e57a0ea8766221624d01b0864")[..]
+        );
+    }
+
+    #[test]
+    fn vector_basic_sharing_2_of_3_insufficient_shares() {
+        let mnemonics = ["shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed"];
+        assert!(combine_mnemonics(&mnemonics, b"TREZOR").is_err());
+    }
+
+    #[test]
+    fn vector_valid_256_bits() {
+        let mnemonics = ["theory painting academic academic armed sweater year military elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect luck"];
+        assert_eq!(
+            combine_mnemonics(&mnemonics, b"TREZOR").unwrap().expose(),
+            &from_hex("989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92")[..]
+        );
+    }
+
+    #[test]
+    fn vector_valid_extendable_128_bits() {
+        let mnemonics = ["testify swimming academic academic column loyalty smear include exotic bedroom exotic wrist lobe cover grief golden smart junior estimate learn"];
+        assert_eq!(
+            combine_mnemonics(&mnemonics, b"TREZOR").unwrap().expose(),
+            &from_hex("1679b4516e0ee5954351d288a838f45e")[..]
+        );
+    }
+
+    // The official SLIP-39 test vectors (vectors.json of python-shamir-mnemonic) as description,
+    // mnemonics and master secret, which is empty when the mnemonics must be rejected. All use the
+    // passphrase "TREZOR".
+    const VECTORS: &[(&str, &[&str], &str)] = &[
+        (
+            "1. Valid mnemonic without sharing (128 bits)",
+            &[
+                "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard",
+            ],
+            "bb54aac4b89dc868ba37d9cc21b2cece",
+        ),
+        (
+            "2. Mnemonic with invalid checksum (128 bits)",
+            &[
+                "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney",
+            ],
+            "",
+        ),
+        (
+            "3. Mnemonic with invalid padding (128 bits)",
+            &[
+                "duckling enlarge academic academic email result length solution fridge kidney coal piece deal husband erode duke ajar music cargo fitness",
+            ],
+            "",
+        ),
+        (
+            "4. Basic sharing 2-of-3 (128 bits)",
+            &[
+                "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+                "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+            ],
+            "b43ceb7e57a0ea8766221624d01b0864",
+        ),
+        (
+            "5. Basic sharing 2-of-3 (128 bits)",
+            &[
+                "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            ],
+            "",
+        ),
+        (
+            "6. Mnemonics with different identifiers (128 bits)",
+            &[
+                "adequate smoking academic acid debut wine petition glen cluster slow rhyme slow simple epidemic rumor junk tracks treat olympic tolerate",
+                "adequate stay academic agency agency formal party ting frequent learn upstairs remember smear leaf damage anatomy ladle market hush corner",
+            ],
+            "",
+        ),
+        (
+            "7. Mnemonics with different iteration exponents (128 bits)",
+            &[
+                "peasant leaves academic acid desert exact olympic math alive axle trial tackle drug deny decent smear dominant desert bucket remind",
+                "peasant leader academic agency cultural blessing percent network envelope medal junk primary human pumps jacket fragment payroll ticket evoke voice",
+            ],
+            "",
+        ),
+        (
+            "9. Mnemonics with mismatching group counts (128 bits)",
+            &[
+                "average senior academic leaf broken teacher expect surface hour capture obesity desire negative dynamic dominant pistol mineral mailman iris aide",
+                "average senior academic agency curious pants blimp spew clothes slice script dress wrap firm shaft regular slavery negative theater roster",
+            ],
+            "",
+        ),
+        (
+            "10. Mnemonics with greater group threshold than group counts (128 bits)",
+            &[
+                "music husband acrobat acid artist finance center either graduate swimming object bike medical clothes station aspect spider maiden bulb welcome",
+                "music husband acrobat agency advance hunting bike corner density careful material civil evil tactics remind hawk discuss hobo voice rainbow",
+                "music husband beard academic black tricycle clock mayor estimate level photo episode exclude ecology papa source amazing salt verify divorce",
+            ],
+            "",
+        ),
+        (
+            "11. Mnemonics with duplicate member indices (128 bits)",
+            &[
+                "device stay academic always dive coal antenna adult black exceed stadium herald advance soldier busy dryer daughter evaluate minister laser",
+                "device stay academic always dwarf afraid robin gravity crunch adjust soul branch walnut coastal dream costume scholar mortgage mountain pumps",
+            ],
+            "",
+        ),
+        (
+            "12. Mnemonics with mismatching member thresholds (128 bits)",
+            &[
+                "hour painting academic academic device formal evoke guitar random modern justice filter withdraw trouble identify mailman insect general cover oven",
+                "hour painting academic agency artist again daisy capital beaver fiber much enjoy suitable symbolic identify photo editor romp float echo",
+            ],
+            "",
+        ),
+        (
+            "13. Mnemonics giving an invalid digest (128 bits)",
+            &[
+                "guilt walnut academic acid deliver remove equip listen vampire tactics nylon rhythm failure husband fatigue alive blind enemy teaspoon rebound",
+                "guilt walnut academic agency brave hamster hobo declare herd taste alpha slim criminal mild arcade formal romp branch pink ambition",
+            ],
+            "",
+        ),
+        (
+            "14. Insufficient number of groups (128 bits, case 1)",
+            &[
+                "eraser senior beard romp adorn nuclear spill corner cradle style ancient family general leader ambition exchange unusual garlic promise voice",
+            ],
+            "",
+        ),
+        (
+            "15. Insufficient number of groups (128 bits, case 2)",
+            &[
+                "eraser senior ceramic snake clay various huge numb argue hesitate auction category timber browser greatest hanger petition script leaf pickup",
+                "eraser senior ceramic shaft dynamic become junior wrist silver peasant force math alto coal amazing segment yelp velvet image paces",
+            ],
+            "",
+        ),
+        (
+            "18. Threshold number of groups and members in each group (128 bits, case 2)",
+            &[
+                "eraser senior decision smug corner ruin rescue cubic angel tackle skin skunk program roster trash rumor slush angel flea amazing",
+                "eraser senior beard romp adorn nuclear spill corner cradle style ancient family general leader ambition exchange unusual garlic promise voice",
+                "eraser senior decision scared cargo theory device idea deliver modify curly include pancake both news skin realize vitamins away join",
+            ],
+            "7c3397a292a5941682d7a4ae2d898d11",
+        ),
+        (
+            "19. Threshold number of groups and members in each group (128 bits, case 3)",
+            &[
+                "eraser senior beard romp adorn nuclear spill corner cradle style ancient family general leader ambition exchange unusual garlic promise voice",
+                "eraser senior acrobat romp bishop medical gesture pumps secret alive ultimate quarter priest subject class dictate spew material endless market",
+            ],
+            "7c3397a292a5941682d7a4ae2d898d11",
+        ),
+        (
+            "20. Valid mnemonic without sharing (256 bits)",
+            &[
+                "theory painting academic academic armed sweater year military elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect luck",
+            ],
+            "989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92",
+        ),
+        (
+            "21. Mnemonic with invalid checksum (256 bits)",
+            &[
+                "theory painting academic academic armed sweater year military elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect lunar",
+            ],
+            "",
+        ),
+        (
+            "23. Basic sharing 2-of-3 (256 bits)",
+            &[
+                "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse duckling lying evidence network walnut tactics forget hairy rebound impulse brother survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+                "humidity disease academic agency actress jacket gross physics cylinder solution fake mortgage benefit public busy prepare sharp friar change work slow purchase ruler again tricycle involve viral wireless mixture anatomy desert cargo upgrade",
+            ],
+            "c938b319067687e990e05e0da0ecce1278f75ff58d9853f19dcaeed5de104aae",
+        ),
+        (
+            "24. Basic sharing 2-of-3 (256 bits)",
+            &[
+                "humidity disease academic always aluminum jewelry energy woman receiver strategy amuse duckling lying evidence network walnut tactics forget hairy rebound impulse brother survive clothes stadium mailman rival ocean reward venture always armed unwrap",
+            ],
+            "",
+        ),
+        (
+            "39. Mnemonic with insufficient length",
+            &[
+                "junk necklace academic academic acne isolate join hesitate lunar roster dough calcium chemical ladybug amount mobile glasses verify cylinder",
+            ],
+            "",
+        ),
+        (
+            "40. Mnemonic with invalid master secret length",
+            &[
+                "fraction necklace academic academic award teammate mouse regular testify coding building member verdict purchase blind camera duration email prepare spirit quarter",
+            ],
+            "",
+        ),
+        (
+            "41. Valid mnemonics which can detect some errors in modular arithmetic",
+            &[
+                "herald flea academic cage avoid space trend estate dryer hairy evoke eyebrow improve airline artwork garlic premium duration prevent oven",
+                "herald flea academic client blue skunk class goat luxury deny presence impulse graduate clay join blanket bulge survive dish necklace",
+                "herald flea academic acne advance fused brother frozen broken game ranked ajar already believe check install theory angry exercise adult",
+            ],
+            "ad6f2ad8b59bbbaa01369b9006208d9a",
+        ),
+        (
+            "42. Valid extendable mnemonic without sharing (128 bits)",
+            &[
+                "testify swimming academic academic column loyalty smear include exotic bedroom exotic wrist lobe cover grief golden smart junior estimate learn",
+            ],
+            "1679b4516e0ee5954351d288a838f45e",
+        ),
+        (
+            "43. Extendable basic sharing 2-of-3 (128 bits)",
+            &[
+                "enemy favorite academic acid cowboy phrase havoc level response walnut budget painting inside trash adjust froth kitchen learn tidy punish",
+                "enemy favorite academic always academic sniff script carpet romp kind promise scatter center unfair training emphasis evening belong fake enforce",
+            ],
+            "48b1a4b80b8c209ad42c33672bdaa428",
+        ),
+        (
+            "44. Valid extendable mnemonic without sharing (256 bits)",
+            &[
+                "impulse calcium academic academic alcohol sugar lyrics pajamas column facility finance tension extend space birthday rainbow swimming purple syndrome facility trial warn duration snapshot shadow hormone rhyme public spine counter easy hawk album",
+            ],
+            "8340611602fe91af634a5f4608377b5235fa2d757c51d720c0c7656249a3035f",
+        ),
+    ];
+
+    #[test]
+    fn official_vectors() {
+        for (description, mnemonics, secret) in VECTORS {
+            let result = combine_mnemonics(mnemonics, b"TREZOR");
+            if secret.is_empty() {
+                assert!(result.is_err(), "{}", description);
+            } else {
+                assert_eq!(
+                    result.unwrap().expose(),
+                    &from_hex(secret)[..],
+                    "{}",
+                    description
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mnemonic_round_trip() {
+        let wordlist = load_word_list();
+        let wordmap = words_map(&wordlist);
+        let share = Share {
+            identifier: 0x1234,
+            extendable: true,
+            iteration_exponent: 3,
+            group_index: 2,
+            group_threshold: 2,
+            group_count: 4,
+            member_index: 5,
+            member_threshold: 3,
+            value: ShareBytes::new(from_hex("00112233445566778899aabbccddeeff")),
+        };
+        let mnemonic = share.to_mnemonic(&wordlist);
+        assert_eq!(mnemonic.split(" ").count(), 20);
+        assert_eq!(Share::from_mnemonic(&mnemonic, &wordmap).unwrap(), share);
+    }
+
+    #[test]
+    fn generate_combine_groups() {
+        let secret = from_hex("0f1e2d3c4b5a69788796a5b4c3d2e1f0");
+        let groups = [
+            Group {
+                threshold: 1,
+                count: 1,
+            },
+            Group {
+                threshold: 2,
+                count: 3,
+            },
+            Group {
+                threshold: 3,
+                count: 5,
+            },
+        ];
+        let mnemonics = generate_mnemonics(&mut OsRng, 2, &groups, &secret, b"", true, 0).unwrap();
+        assert_eq!(mnemonics.len(), 3);
+        assert_eq!(mnemonics[2].len(), 5);
+
+        let subset = vec![
+            mnemonics[1][2].clone(),
+            mnemonics[2][4].clone(),
+            mnemonics[1][0].clone(),
+            mnemonics[2][0].clone(),
+            mnemonics[2][3].clone(),
+        ];
+        assert_eq!(
+            combine_mnemonics(&subset, b"").unwrap().expose(),
+            &secret[..]
+        );
+
+        // A wrong passphrase yields a different (but valid looking) secret.
+        assert!(combine_mnemonics(&subset, b"TREZOR").unwrap().expose() != &secret[..]);
+
+        // One complete group is not enough.
+        let incomplete = vec![mnemonics[1][2].clone(), mnemonics[1][0].clone()];
+        assert!(combine_mnemonics(&incomplete, b"").is_err());
+    }
+
+    #[test]
+    fn generate_rejects_bad_parameters() {
+        let secret = from_hex("0f1e2d3c4b5a69788796a5b4c3d2e1f0");
+        let group = Group {
+            threshold: 2,
+            count: 3,
+        };
+        // Odd length secret.
+        assert!(generate_mnemonics(&mut OsRng, 1, &[group], &secret[..15], b"", true, 0).is_err());
+        // Group threshold larger than the number of groups.
+        assert!(generate_mnemonics(&mut OsRng, 2, &[group], &secret, b"", true, 0).is_err());
+        // 1-of-n member sharing.
+        let group = Group {
+            threshold: 1,
+            count: 3,
+        };
+        assert!(generate_mnemonics(&mut OsRng, 1, &[group], &secret, b"", true, 0).is_err());
+    }
+}
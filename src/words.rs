@@ -1,12 +1,13 @@
 // Convert between bytes and string words
 
+use crate::secret::{zeroize_vec, ShareBytes};
 use std::collections::HashMap;
 use std::include_bytes;
 use std::iter::FromIterator;
 
 #[allow(unused)]
-const WORDLIST_PATH: &'static str = "../assets/wordlist256.txt";
-const WORDLIST_RAW: &'static [u8] = include_bytes!("../assets/wordlist256.txt");
+const WORDLIST_PATH: &str = "../assets/wordlist256.txt";
+const WORDLIST_RAW: &[u8] = include_bytes!("../assets/wordlist256.txt");
 
 #[allow(unused)]
 pub fn load_word_list(path: &str) -> Vec<String> {
@@ -26,7 +27,6 @@ pub fn load_static_word_list() -> Vec<String> {
         .collect();
 }
 
-#[allow(unused)]
 pub fn to_words<'a, I: Iterator<Item = u8>>(bytes: I, wordlist: &[&'a str]) -> Vec<&'a str> {
     assert!(wordlist.len() >= 256);
     return bytes.map(|b| wordlist[b as usize]).collect();
@@ -44,11 +44,23 @@ pub fn words_map<'a, S: AsRef<str>>(wordlist: &'a [S]) -> HashMap<&'a str, u8> {
     return words_index;
 }
 
+// Maps the words back to bytes, failing on the first word that is not in the list. The bytes
+// read so far are wiped before the error is returned.
 pub fn from_words<'a, I: Iterator<Item = &'a str>>(
     words: I,
     wordmap: &HashMap<&'a str, u8>,
-) -> Vec<u8> {
-    return words.map(|w| wordmap[w] as u8).collect();
+) -> Result<ShareBytes, String> {
+    let mut bytes = Vec::new();
+    for word in words {
+        match wordmap.get(word) {
+            Some(b) => bytes.push(*b),
+            None => {
+                zeroize_vec(&mut bytes);
+                return Err(format!("Unknown word {:?}.", word));
+            }
+        }
+    }
+    return Ok(ShareBytes::new(bytes));
 }
 
 // #[cfg(test)]
@@ -0,0 +1,359 @@
+// Multiplying regions by a GF(2^8) matrix with nothing but XORs, as in Cauchy Reed-Solomon codes
+// (Blömer et al., "An XOR-Based Erasure-Resilient Coding Scheme", 1995) and jerasure.
+//
+// Multiplication by a constant c is linear over GF(2), so it is an 8x8 bit matrix whose column j is
+// c * x^j (as in bitslice.rs), and a matrix with r rows and k columns is a bit matrix with 8r rows
+// and 8k columns. Each region is split into groups of 8 packets, packet j of a group standing for
+// bit j of the symbols. Output packet i of row r is then the XOR of the input packets selected by
+// bit row 8r + i, so encoding costs one pass over a packet per one in the bit matrix.
+//
+// The XORs are planned once as a schedule. Bit rows often share most of their ones, so instead of
+// computing each from the inputs alone, the schedule may start from an output packet it already
+// computed and only XOR in the difference (jerasure's "smart" schedules). How many ones there are
+// also depends on the matrix, and a Cauchy matrix can be scaled to have fewer of them (see
+// fewest_ones).
+
+use crate::field::Gf;
+use crate::finite_field::Field256;
+use crate::matrix::Matrix;
+use std::convert::TryFrom;
+
+// The largest packet. Smaller ones are used at the end of a region, which must be a multiple of 8.
+const PACKET: usize = 1024;
+
+// Where a scheduled XOR reads from: packet j of input region k is Input(8k + j), and likewise
+// for the outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Input(usize),
+    Output(usize),
+}
+
+// Sets output packet `to` += `from`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Operation {
+    from: Source,
+    to: usize,
+}
+
+// The rows of the bit matrix of a matrix, as bitsets over its columns.
+fn bit_rows<F: Field256>(matrix: &Matrix<F>) -> Vec<Vec<u64>> {
+    let cols = matrix.mat.first().map_or(0, |row| row.len());
+    let words = (8 * cols + 63) / 64;
+    let mut rows = Vec::with_capacity(8 * matrix.mat.len());
+    for row in matrix.mat.iter() {
+        let mut bits = vec![vec![0u64; words]; 8];
+        for (k, c) in row.iter().enumerate() {
+            for j in 0..8 {
+                let column = (*c * Gf::new(1 << j)).value();
+                for i in 0..8 {
+                    if (column >> i) & 1 == 1 {
+                        let bit = 8 * k + j;
+                        bits[i][bit / 64] |= 1 << (bit % 64);
+                    }
+                }
+            }
+        }
+        rows.extend(bits);
+    }
+    return rows;
+}
+
+fn ones(bits: &[u64]) -> usize {
+    return bits.iter().map(|w| w.count_ones() as usize).sum();
+}
+
+fn difference(a: &[u64], b: &[u64]) -> Vec<u64> {
+    return a.iter().zip(b).map(|(x, y)| x ^ y).collect();
+}
+
+// The indices of the set bits.
+fn set_bits(bits: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    return bits.iter().enumerate().flat_map(|(w, word)| {
+        return (0..64)
+            .filter(move |b| (word >> b) & 1 == 1)
+            .map(move |b| 64 * w + b);
+    });
+}
+
+// The number of ones in the bit matrix of a matrix, i.e. the XORs of its naive schedule.
+pub fn bit_ones<F: Field256>(matrix: &Matrix<F>) -> usize {
+    return bit_rows(matrix).iter().map(|row| ones(row)).sum();
+}
+
+// Scales a Cauchy matrix (or any matrix under an identity, whose square submatrices must stay
+// invertible) to have fewer ones in its bit matrix, as jerasure's cauchy_good_general_coding_matrix
+// does. Scaling the columns so that the first row is all ones doesn't change which submatrices are
+// invertible, and neither does scaling each of the other rows by whichever of its inverses leaves
+// it with the fewest ones.
+pub fn fewest_ones<F: Field256>(matrix: &Matrix<F>) -> Result<Matrix<F>, &'static str> {
+    let mut mat = matrix.mat.clone();
+    if mat.len() == 0 {
+        return Ok(matrix.clone());
+    }
+    if mat[0].iter().any(|x| x.is_zero()) {
+        return Err("The first row of the matrix has a zero");
+    }
+    for k in 0..mat[0].len() {
+        let scale = mat[0][k].inv();
+        for row in mat.iter_mut() {
+            row[k] *= scale;
+        }
+    }
+    for row in mat.iter_mut().skip(1) {
+        let count = |row: &Vec<Gf<F>>| {
+            return bit_ones(&Matrix::<F>::try_from(vec![row.clone()]).unwrap());
+        };
+        let mut best = row.clone();
+        let mut best_ones = count(&best);
+        for c in row.iter().filter(|c| !c.is_zero()) {
+            let scaled: Vec<Gf<F>> = row.iter().map(|x| *x / *c).collect();
+            let scaled_ones = count(&scaled);
+            if scaled_ones < best_ones {
+                best = scaled;
+                best_ones = scaled_ones;
+            }
+        }
+        *row = best;
+    }
+    return Matrix::try_from(mat);
+}
+
+// The XORs that multiply regions by a matrix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XorSchedule {
+    inputs: usize,
+    outputs: usize,
+    operations: Vec<Operation>,
+}
+
+impl XorSchedule {
+    // The schedule computing each output packet from the inputs alone.
+    pub fn naive<F: Field256>(matrix: &Matrix<F>) -> XorSchedule {
+        let rows = bit_rows(matrix);
+        let operations = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(to, row)| {
+                return set_bits(row)
+                    .map(move |bit| Operation {
+                        from: Source::Input(bit),
+                        to,
+                    })
+                    .collect::<Vec<_>>();
+            })
+            .collect();
+        return XorSchedule::from_rows(matrix, operations);
+    }
+
+    // The schedule that greedily computes the output packet that is cheapest to compute next,
+    // either from the inputs or from an output packet that was already computed, which costs one
+    // XOR plus one per bit the two rows differ in.
+    pub fn new<F: Field256>(matrix: &Matrix<F>) -> XorSchedule {
+        let rows = bit_rows(matrix);
+        // The cost of each row so far, and the output it would start from.
+        let mut costs: Vec<(usize, Option<usize>)> =
+            rows.iter().map(|row| (ones(row), None)).collect();
+        let mut done = vec![false; rows.len()];
+        let mut operations = Vec::new();
+        for _ in 0..rows.len() {
+            let next = (0..rows.len())
+                .filter(|r| !done[*r])
+                .min_by_key(|r| costs[*r].0)
+                .unwrap();
+            done[next] = true;
+            let inputs = match costs[next].1 {
+                None => rows[next].clone(),
+                Some(from) => {
+                    operations.push(Operation {
+                        from: Source::Output(from),
+                        to: next,
+                    });
+                    difference(&rows[next], &rows[from])
+                }
+            };
+            for bit in set_bits(&inputs) {
+                operations.push(Operation {
+                    from: Source::Input(bit),
+                    to: next,
+                });
+            }
+            for r in 0..rows.len() {
+                if done[r] {
+                    continue;
+                }
+                let cost = 1 + ones(&difference(&rows[r], &rows[next]));
+                if cost < costs[r].0 {
+                    costs[r] = (cost, Some(next));
+                }
+            }
+        }
+        return XorSchedule::from_rows(matrix, operations);
+    }
+
+    fn from_rows<F: Field256>(matrix: &Matrix<F>, operations: Vec<Operation>) -> XorSchedule {
+        return XorSchedule {
+            inputs: matrix.mat.first().map_or(0, |row| row.len()),
+            outputs: matrix.mat.len(),
+            operations,
+        };
+    }
+
+    // The number of packet XORs per group of packets.
+    pub fn xors(&self) -> usize {
+        return self.operations.len();
+    }
+
+    // Sets out to the matrix times the regions, whose length must be a multiple of 8.
+    pub fn apply(&self, regions: &[Vec<u8>], out: &mut [Vec<u8>]) {
+        assert_eq!(regions.len(), self.inputs);
+        assert_eq!(out.len(), self.outputs);
+        let length = regions.first().or(out.first()).map_or(0, |r| r.len());
+        assert!(length % 8 == 0);
+        assert!(regions.iter().chain(out.iter()).all(|r| r.len() == length));
+        for region in out.iter_mut() {
+            for x in region.iter_mut() {
+                *x = 0;
+            }
+        }
+        let mut offset = 0;
+        while offset < length {
+            let packet = PACKET.min((length - offset) / 8);
+            // The start of packet p of a region in this group.
+            let start = |p: usize| offset + (p % 8) * packet;
+            for operation in self.operations.iter() {
+                let to = operation.to;
+                match operation.from {
+                    Source::Input(p) => {
+                        let src = &regions[p / 8][start(p)..start(p) + packet];
+                        let dst = &mut out[to / 8][start(to)..start(to) + packet];
+                        xor_packet(src, dst);
+                    }
+                    Source::Output(p) => {
+                        let (src, dst) = two_packets(out, (p / 8, start(p)), (to / 8, start(to)));
+                        xor_packet(&src[..packet], &mut dst[..packet]);
+                    }
+                }
+            }
+            offset += 8 * packet;
+        }
+    }
+}
+
+// Sets dst[i] ^= src[i].
+fn xor_packet(src: &[u8], dst: &mut [u8]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= *s;
+    }
+}
+
+// The regions from the (region, start) src onwards and, mutably, from dst onwards, which must be
+// at least a packet apart if they are in the same region.
+fn two_packets<'a>(
+    regions: &'a mut [Vec<u8>],
+    src: (usize, usize),
+    dst: (usize, usize),
+) -> (&'a [u8], &'a mut [u8]) {
+    if src.0 == dst.0 {
+        let region = &mut regions[src.0];
+        if src.1 < dst.1 {
+            let (low, high) = region.split_at_mut(dst.1);
+            return (&low[src.1..], high);
+        }
+        let (low, high) = region.split_at_mut(src.1);
+        return (high, &mut low[dst.1..]);
+    }
+    if src.0 < dst.0 {
+        let (low, high) = regions.split_at_mut(dst.0);
+        return (&low[src.0][src.1..], &mut high[0][dst.1..]);
+    }
+    let (low, high) = regions.split_at_mut(src.0);
+    return (&high[0][src.1..], &mut low[dst.0][dst.1..]);
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+    use super::*;
+    use crate::finite_field::DirectField;
+    use crate::matrix::cauchy_matrix;
+
+    fn random_matrix(rows: usize, cols: usize) -> Matrix<DirectField> {
+        let mat: Vec<Vec<Gf<DirectField>>> = (0..rows)
+            .map(|_| (0..cols).map(|_| Gf::new(rand::random::<u8>())).collect())
+            .collect();
+        return Matrix::try_from(mat).unwrap();
+    }
+
+    fn random_regions(n: usize, length: usize) -> Vec<Vec<u8>> {
+        return (0..n)
+            .map(|_| (0..length).map(|_| rand::random::<u8>()).collect())
+            .collect();
+    }
+
+    // The symbols of a region of one group of 8 one-byte packets: bit j of symbol b is bit b of
+    // packet j.
+    fn symbols(region: &[u8]) -> Vec<u8> {
+        return (0..8)
+            .map(|b| (0..8).fold(0, |s, j| s | ((region[j] >> b) & 1) << j))
+            .collect();
+    }
+
+    fn schedule_multiplies(schedule: &XorSchedule, matrix: &Matrix<DirectField>) {
+        let regions = random_regions(matrix.mat[0].len(), 8);
+        let mut out = vec![vec![0xff; 8]; matrix.mat.len()];
+        schedule.apply(&regions, &mut out);
+        let inputs: Vec<Vec<u8>> = regions.iter().map(|r| symbols(r)).collect();
+        let outputs: Vec<Vec<u8>> = out.iter().map(|r| symbols(r)).collect();
+        for b in 0..8 {
+            let vec: Vec<Gf<DirectField>> = inputs.iter().map(|s| Gf::new(s[b])).collect();
+            let mut expected = vec![Gf::zero(); matrix.mat.len()];
+            matrix.mul_vec(&vec, &mut expected);
+            for (e, o) in expected.iter().zip(outputs.iter()) {
+                assert_eq!(e.value(), o[b]);
+            }
+        }
+    }
+
+    #[test]
+    fn schedules_multiply() {
+        for (rows, cols) in &[(1, 1), (2, 4), (4, 3), (5, 10)] {
+            let matrix = random_matrix(*rows, *cols);
+            schedule_multiplies(&XorSchedule::naive(&matrix), &matrix);
+            schedule_multiplies(&XorSchedule::new(&matrix), &matrix);
+        }
+    }
+
+    #[test]
+    fn schedules_agree_across_packets() {
+        let matrix = random_matrix(3, 5);
+        // Several full groups followed by a smaller one.
+        let regions = random_regions(5, 3 * 8 * PACKET + 40);
+        let mut naive = vec![vec![0; regions[0].len()]; 3];
+        let mut smart = naive.clone();
+        XorSchedule::naive(&matrix).apply(&regions, &mut naive);
+        XorSchedule::new(&matrix).apply(&regions, &mut smart);
+        assert_eq!(naive, smart);
+    }
+
+    #[test]
+    fn smart_schedules_save_xors() {
+        let matrix = cauchy_matrix::<DirectField>(0, 4, 10).unwrap();
+        let naive = XorSchedule::naive(&matrix);
+        assert_eq!(naive.xors(), bit_ones(&matrix));
+        assert!(XorSchedule::new(&matrix).xors() < naive.xors());
+    }
+
+    #[test]
+    fn fewest_ones_scales() {
+        let matrix = cauchy_matrix::<DirectField>(0, 4, 10).unwrap();
+        let improved = fewest_ones(&matrix).unwrap();
+        assert!(bit_ones(&improved) < bit_ones(&matrix));
+        assert!(improved.mat[0].iter().all(|x| *x == Gf::one()));
+        // Each row is a multiple of the row with its columns scaled.
+        for (row, original) in improved.mat.iter().zip(matrix.mat.iter()) {
+            let ratio = |k: usize| row[k] * matrix.mat[0][k] / original[k];
+            assert!((0..10).all(|k| ratio(k) == ratio(0)));
+        }
+    }
+}